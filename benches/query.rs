@@ -0,0 +1,59 @@
+//! Compares region queries on a `QuadTree` against a linear scan with `NaiveIndex`.
+//! Run with `cargo bench`.
+
+use quadtree::naive::NaiveIndex;
+use quadtree::prelude::*;
+
+use std::hint::black_box;
+use std::time::Instant;
+
+const ITERATIONS: u32 = 10_000;
+
+/// A small xorshift generator, so every run fills the indexes the same way.
+struct XorShift(u32);
+
+impl XorShift {
+    fn gen_range(&mut self, low: u32, high: u32) -> u32 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 17;
+        self.0 ^= self.0 << 5;
+        low + self.0 % (high - low)
+    }
+}
+
+/// Fill an index with `count` random non-overlapping small rects.
+fn fill<T: SpatialIndex>(index: &mut T, count: usize) {
+    let mut rng = XorShift(0x9e3779b9);
+    let mut inserted = 0;
+    while inserted < count {
+        let origin = Point::new(rng.gen_range(0, 256) as f64, rng.gen_range(0, 256) as f64);
+        let size = Size::new(rng.gen_range(1, 4) as f64, rng.gen_range(1, 4) as f64);
+        if index.insert(Rect::new(origin, size)) {
+            inserted += 1;
+        }
+    }
+}
+
+fn bench_query<T: SpatialIndex>(name: &str, index: &mut T, count: usize) {
+    fill(index, count);
+    let region = Rect::new(Point::new(100., 100.), Size::new(16., 16.));
+
+    let start = Instant::now();
+    for _ in 0..ITERATIONS {
+        black_box(index.query(black_box(&region)));
+    }
+    let elapsed = start.elapsed();
+
+    println!("{:<24} {:>10} ns/iter", name, elapsed.as_nanos() / ITERATIONS as u128);
+}
+
+fn new_tree() -> QuadTree {
+    QuadTree::builder().bounds(Rect::new(Point::new(0., 0.), Size::new(256., 256.))).build()
+}
+
+fn main() {
+    bench_query("query_naive_100", &mut NaiveIndex::new(), 100);
+    bench_query("query_quadtree_100", &mut new_tree(), 100);
+    bench_query("query_naive_1000", &mut NaiveIndex::new(), 1000);
+    bench_query("query_quadtree_1000", &mut new_tree(), 1000);
+}
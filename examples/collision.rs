@@ -0,0 +1,179 @@
+//! Hundreds of rects bouncing around a window, using a quadtree as the broad phase of
+//! collision detection. Each body is moved in the tree every frame, so this doubles as
+//! a stress test of removing and reinserting members.
+//!
+//! Space switches between the tree and a naive all-pairs check, `n` toggles drawing
+//! the tree's leaves, and up and down add or remove bodies.
+//!
+//! Build and run with `cargo run --release --features macroquad --example collision`.
+
+use macroquad::prelude::*;
+use quadtree::prelude::{Point, QuadTree, Rect, Size};
+
+const WORLD_WIDTH: f64 = 800.;
+const WORLD_HEIGHT: f64 = 600.;
+const BATCH: usize = 100;
+
+struct Body {
+    rect: Rect,
+    velocity: Point,
+}
+
+impl Body {
+    fn random() -> Body {
+        let size = Size::new(rand::gen_range(4., 14.), rand::gen_range(4., 14.));
+        let origin = Point::new(rand::gen_range(0., WORLD_WIDTH - size.width), rand::gen_range(0., WORLD_HEIGHT - size.height));
+        let velocity = Point::new(rand::gen_range(-80., 80.), rand::gen_range(-80., 80.));
+
+        Body { rect: Rect::new(origin, size), velocity }
+    }
+
+    /// The body's rect after `dt` seconds, bouncing its velocity off the world's edges.
+    fn step(&mut self, dt: f64) -> Rect {
+        let mut origin = Point::new(self.rect.origin.x + self.velocity.x * dt, self.rect.origin.y + self.velocity.y * dt);
+        if origin.x < 0. || origin.x + self.rect.width() > WORLD_WIDTH {
+            self.velocity.x = -self.velocity.x;
+            origin.x = origin.x.clamp(0., WORLD_WIDTH - self.rect.width());
+        }
+        if origin.y < 0. || origin.y + self.rect.height() > WORLD_HEIGHT {
+            self.velocity.y = -self.velocity.y;
+            origin.y = origin.y.clamp(0., WORLD_HEIGHT - self.rect.height());
+        }
+
+        Rect::new(origin, self.rect.size)
+    }
+}
+
+struct Demo {
+    tree: QuadTree,
+    bodies: Vec<Body>,
+    naive: bool,
+    show_leaves: bool,
+}
+
+impl Demo {
+    fn new() -> Demo {
+        let bounds = Rect::new(Point::new(0., 0.), Size::new(WORLD_WIDTH, WORLD_HEIGHT));
+        let tree = QuadTree::builder().bounds(bounds).leaf_capacity(8).allow_overlap(true).build();
+        let mut demo = Demo { tree, bodies: Vec::new(), naive: false, show_leaves: true };
+        demo.add_bodies(BATCH * 3);
+
+        demo
+    }
+
+    fn add_bodies(&mut self, count: usize) {
+        for _ in 0..count {
+            let body = Body::random();
+            // Identical rects are one member, so skip a body that matches another exactly.
+            if self.tree.try_insert(body.rect, 0).is_ok() {
+                self.bodies.push(body);
+            }
+        }
+    }
+
+    fn remove_bodies(&mut self, count: usize) {
+        let keep = self.bodies.len().saturating_sub(count);
+        for body in self.bodies.drain(keep..) {
+            self.tree.remove_rect(&body.rect);
+        }
+    }
+
+    /// Move every body, updating its rect in the tree. A body whose new rect is already
+    /// taken stays where it was for this frame.
+    fn step(&mut self, dt: f64) {
+        for body in self.bodies.iter_mut() {
+            let moved = body.step(dt);
+            self.tree.remove_rect(&body.rect);
+            match self.tree.try_insert(moved, 0) {
+                Ok(()) => body.rect = moved,
+                Err(_) => {
+                    let _ = self.tree.try_insert(body.rect, 0);
+                },
+            }
+        }
+    }
+
+    /// Find which bodies overlap another, and how many pairs of rects were compared.
+    fn collisions(&self) -> (Vec<bool>, usize) {
+        let mut checks = 0;
+        let colliding = self.bodies.iter().enumerate().map(|(i, body)| {
+            if self.naive {
+                checks += self.bodies.len() - 1;
+                self.bodies.iter().enumerate().any(|(j, other)| i != j && other.rect.overlaps(&body.rect))
+            } else {
+                let candidates = self.tree.query(&body.rect);
+                checks += candidates.len().saturating_sub(1);
+                candidates.iter().any(|other| *other != body.rect && other.overlaps(&body.rect))
+            }
+        }).collect();
+
+        (colliding, checks)
+    }
+
+    fn draw(&self, colliding: &[bool], checks: usize, elapsed: f64) {
+        clear_background(WHITE);
+        let scale = (screen_width() as f64 / WORLD_WIDTH).min(screen_height() as f64 / WORLD_HEIGHT) as f32;
+        let to_screen = |rect: &Rect| (rect.min_x() as f32 * scale, rect.min_y() as f32 * scale,
+                                       rect.width() as f32 * scale, rect.height() as f32 * scale);
+
+        if self.show_leaves {
+            for leaf in self.tree.leaves() {
+                let (x, y, width, height) = to_screen(&leaf.rect);
+                draw_rectangle_lines(x, y, width, height, 1., LIGHTGRAY);
+            }
+        }
+
+        for (body, &hit) in self.bodies.iter().zip(colliding.iter()) {
+            let (x, y, width, height) = to_screen(&body.rect);
+            draw_rectangle(x, y, width, height, if hit { RED } else { BLUE });
+        }
+
+        let mode = if self.naive { "naive" } else { "quadtree" };
+        let status = format!("{} bodies, {} broad phase: {} checks in {:.2} ms, {} fps",
+                             self.bodies.len(), mode, checks, elapsed * 1000., get_fps());
+        draw_text(&status, 10., 20., 20., BLACK);
+        draw_text("space: switch broad phase, n: toggle leaves, up/down: add/remove bodies", 10., 40., 20., DARKGRAY);
+    }
+}
+
+fn window_conf() -> Conf {
+    Conf {
+        window_title: "quadtree collision".to_string(),
+        window_width: WORLD_WIDTH as i32,
+        window_height: WORLD_HEIGHT as i32,
+        ..Default::default()
+    }
+}
+
+#[macroquad::main(window_conf)]
+async fn main() {
+    let mut demo = Demo::new();
+
+    loop {
+        if is_key_pressed(KeyCode::Escape) || is_key_pressed(KeyCode::Q) {
+            break;
+        }
+        if is_key_pressed(KeyCode::Space) {
+            demo.naive = !demo.naive;
+        }
+        if is_key_pressed(KeyCode::N) {
+            demo.show_leaves = !demo.show_leaves;
+        }
+        if is_key_pressed(KeyCode::Up) {
+            demo.add_bodies(BATCH);
+        }
+        if is_key_pressed(KeyCode::Down) {
+            demo.remove_bodies(BATCH);
+        }
+
+        // Cap the step so a stalled frame doesn't tunnel bodies through each other.
+        demo.step((get_frame_time() as f64).min(0.05));
+
+        let start = get_time();
+        let (colliding, checks) = demo.collisions();
+        let elapsed = get_time() - start;
+
+        demo.draw(&colliding, checks, elapsed);
+        next_frame().await
+    }
+}
@@ -0,0 +1,10 @@
+use quadtree::prelude::*;
+
+fn main() {
+    let origin = Point::new(0., 0.);
+    let size = Size::new(1., 1.);
+    let rect = Rect::new(origin, size);
+    let qt = QuadTree::new_autosized(rect);
+
+    println!("tree: {:?}", qt);
+}
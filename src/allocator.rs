@@ -0,0 +1,162 @@
+use crate::geometry::Point;
+use crate::geometry::Rect;
+use crate::geometry::Size;
+use crate::quadtree::QuadTree;
+
+/**
+ Hands out non-overlapping rects from a fixed area, e.g. for packing sprites or
+ glyphs into a texture, using a quadtree to track which parts are in use.
+ */
+pub struct Allocator {
+    bounds: Rect,
+    tree: QuadTree,
+}
+
+impl Allocator {
+    /**
+     Create an allocator handing out rects within `bounds`.
+     */
+    pub fn new(bounds: Rect) -> Allocator {
+        Allocator { bounds, tree: Allocator::empty_tree(&bounds) }
+    }
+
+    /**
+     Find and reserve an empty rect of the given size within the allocator's bounds.
+     Free space is searched bottom-left first: the candidate with the lowest y, then
+     the lowest x, wins. Returns `None` if `size` isn't finite with a positive width and
+     height, or if no empty region of that size is found.
+     */
+    pub fn allocate(&mut self, size: Size) -> Option<Rect> {
+        if !(size.width > 0. && size.height > 0. && size.width.is_finite() && size.height.is_finite()) {
+            return None
+        }
+
+        let mut best: Option<Rect> = None;
+
+        for origin in self.candidate_origins().iter() {
+            let rect = Rect::new(*origin, size);
+            if !self.is_free(&rect) {
+                continue;
+            }
+
+            let is_better = match best {
+                Some(best_rect) => (rect.min_y(), rect.min_x()) < (best_rect.min_y(), best_rect.min_x()),
+                None => true,
+            };
+
+            if is_better {
+                best = Some(rect);
+            }
+        }
+
+        match best {
+            Some(rect) if self.tree.insert(rect) => Some(rect),
+            _ => None,
+        }
+    }
+
+    /**
+     Release a rect previously returned by `allocate`.
+     Returns false if `rect` is not currently allocated.
+     */
+    pub fn free(&mut self, rect: &Rect) -> bool {
+        self.tree.remove_rect(rect)
+    }
+
+    /// The area rects are allocated from.
+    pub fn bounds(&self) -> Rect {
+        self.bounds
+    }
+
+    /**
+     Find a large free rect, as described by `QuadTree::largest_empty_rect_in`.
+     */
+    pub fn largest_free_rect(&self) -> Option<Rect> {
+        self.tree.largest_empty_rect_in(&self.bounds)
+    }
+
+    /// Every rect currently allocated.
+    pub fn allocated(&self) -> Vec<Rect> {
+        self.tree.members()
+    }
+
+    fn empty_tree(bounds: &Rect) -> QuadTree {
+        QuadTree::builder().bounds(*bounds).build()
+    }
+
+    /**
+     Check if `rect` lies within the bounds without overlapping any allocated rect.
+     */
+    fn is_free(&self, rect: &Rect) -> bool {
+        self.bounds.contains(rect)
+        && !self.tree.query(rect).iter().any(|allocated_rect| allocated_rect.overlaps(rect))
+    }
+
+    /**
+     Find positions where a new rect could start: the corner of the bounds, the
+     origins of empty leaves, and the corners to the right of and above each allocated rect.
+     */
+    fn candidate_origins(&self) -> Vec<Point> {
+        let mut origins = vec![self.bounds.origin];
+
+        for leaf in self.tree.leaves() {
+            if !leaf.is_occupied() {
+                origins.push(leaf.rect.origin);
+            }
+
+            for rect in leaf.members.iter() {
+                origins.push(Point::new(rect.max_x(), rect.min_y()));
+                origins.push(Point::new(rect.min_x(), rect.max_y()));
+            }
+        }
+
+        origins
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::geometry::Point;
+    use crate::geometry::Rect;
+    use crate::geometry::Size;
+    use super::Allocator;
+
+    fn rect(x: f64, y: f64, width: f64, height: f64) -> Rect {
+        Rect::new(Point::new(x, y), Size::new(width, height))
+    }
+
+    #[test]
+    fn test_allocate_packs_bottom_left_first() {
+        let mut allocator = Allocator::new(rect(0., 0., 8., 8.));
+        let allocated: Vec<Option<Rect>> = (0..4).map(|_| allocator.allocate(Size::new(4., 4.))).collect();
+
+        assert_eq!(allocated, vec![Some(rect(0., 0., 4., 4.)), Some(rect(4., 0., 4., 4.)),
+                                   Some(rect(0., 4., 4., 4.)), Some(rect(4., 4., 4., 4.))]);
+        assert_eq!(allocator.allocate(Size::new(1., 1.)), None);
+        assert_eq!(allocator.allocated().len(), 4);
+    }
+
+    #[test]
+    fn test_allocate_rejects_sizes_that_cant_fit() {
+        let mut allocator = Allocator::new(rect(0., 0., 8., 8.));
+
+        for size in [Size::new(0., 0.), Size::new(4., 0.), Size::new(-1., 4.), Size::new(f64::NAN, 4.),
+                     Size::new(f64::INFINITY, 4.), Size::new(9., 1.)] {
+            assert_eq!(allocator.allocate(size), None, "{:?} was allocated", size);
+        }
+        assert!(allocator.allocated().is_empty());
+    }
+
+    #[test]
+    fn test_free_makes_room() {
+        let mut allocator = Allocator::new(rect(0., 0., 8., 8.));
+        let first = allocator.allocate(Size::new(8., 4.)).unwrap();
+        allocator.allocate(Size::new(8., 4.)).unwrap();
+        assert_eq!(allocator.allocate(Size::new(2., 2.)), None);
+
+        assert!(allocator.free(&first));
+        assert!(!allocator.free(&first));
+        assert!(!allocator.free(&rect(0., 0., 2., 2.)));
+        assert_eq!(allocator.allocate(Size::new(2., 2.)), Some(rect(0., 0., 2., 2.)));
+    }
+}
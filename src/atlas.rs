@@ -0,0 +1,196 @@
+use crate::allocator::Allocator;
+use crate::geometry::Point;
+use crate::geometry::Rect;
+use crate::geometry::Size;
+
+/**
+ Usage figures for an `AtlasAllocator`, for deciding when an atlas should be
+ repacked or grown.
+ */
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct AtlasStats {
+    /// The number of live allocations.
+    pub allocations: usize,
+    /// The area of the atlas.
+    pub total_area: f64,
+    /// The area asked for by live allocations.
+    pub requested_area: f64,
+    /// The area reserved by live allocations, including padding and power-of-two snapping.
+    pub reserved_area: f64,
+    /// The area of the largest free rect found, which is approximate.
+    pub largest_free_area: f64,
+    /// How scattered the free space is, from 0 (one free block) towards 1 (many small gaps).
+    pub fragmentation: f64,
+}
+
+/**
+ Packs glyphs or sprites into a texture atlas. Each allocation can be padded on every
+ side, to keep texture filtering from bleeding between neighbors, and snapped to
+ power-of-two dimensions.
+ */
+pub struct AtlasAllocator {
+    allocator: Allocator,
+    padding: f64,
+    snap_to_power_of_two: bool,
+    /// The rects handed out, each paired with the padded, snapped rect reserved for it.
+    allocations: Vec<(Rect, Rect)>,
+}
+
+impl AtlasAllocator {
+    /**
+     Create an atlas covering `bounds`, with `padding` added around each allocation.
+     A negative or NaN `padding` is treated as no padding.
+     */
+    pub fn new(bounds: Rect, padding: f64, snap_to_power_of_two: bool) -> AtlasAllocator {
+        AtlasAllocator {
+            allocator: Allocator::new(bounds),
+            padding: if padding > 0. { padding } else { 0. },
+            snap_to_power_of_two,
+            allocations: Vec::new(),
+        }
+    }
+
+    /**
+     Reserve space for an item of the given size. The returned rect has exactly the
+     requested size; the padding around it is reserved but not part of the result.
+     Returns `None` if `size` isn't finite with a positive width and height, as
+     padding would otherwise reserve space for nothing, or if the atlas is full.
+     */
+    pub fn allocate(&mut self, size: Size) -> Option<Rect> {
+        if !(size.width > 0. && size.height > 0. && size.width.is_finite() && size.height.is_finite()) {
+            return None
+        }
+
+        let mut reserved_size = Size::new(size.width + self.padding * 2., size.height + self.padding * 2.);
+        if self.snap_to_power_of_two {
+            reserved_size = Size::new(next_power_of_two(reserved_size.width),
+                                     next_power_of_two(reserved_size.height));
+        }
+
+        self.allocator.allocate(reserved_size).map(|reserved| {
+            let padding_offset = Point::new(self.padding, self.padding);
+            let rect = Rect::new(reserved.origin.add(padding_offset), size);
+            self.allocations.push((rect, reserved));
+            rect
+        })
+    }
+
+    /**
+     Release a rect previously returned by `allocate`, along with its padding.
+     Returns false if `rect` is not currently allocated.
+     */
+    pub fn free(&mut self, rect: &Rect) -> bool {
+        let index = match self.allocations.iter().position(|&(allocated, _)| allocated == *rect) {
+            Some(index) => index,
+            None => return false,
+        };
+
+        let (_, reserved) = self.allocations.remove(index);
+        self.allocator.free(&reserved)
+    }
+
+    /**
+     Summarize how much of the atlas is used and how fragmented the free space is.
+     */
+    pub fn stats(&self) -> AtlasStats {
+        let bounds = self.allocator.bounds();
+        let total_area = bounds.size.area();
+
+        let mut requested_area = 0.;
+        let mut reserved_area = 0.;
+        for &(rect, reserved) in self.allocations.iter() {
+            requested_area += rect.size.area();
+            reserved_area += reserved.size.area();
+        }
+
+        let largest_free_area = match self.allocator.largest_free_rect() {
+            Some(rect) => rect.size.area(),
+            None => 0.,
+        };
+
+        let free_area = total_area - reserved_area;
+        let fragmentation = if free_area > 0. {
+            (1. - largest_free_area / free_area).max(0.)
+        } else {
+            0.
+        };
+
+        AtlasStats {
+            allocations: self.allocations.len(),
+            total_area,
+            requested_area,
+            reserved_area,
+            largest_free_area,
+            fragmentation,
+        }
+    }
+}
+
+/**
+ Find the smallest power of two that is at least `value`.
+ */
+fn next_power_of_two(value: f64) -> f64 {
+    if value <= 0. {
+        value
+    } else {
+        (2f64).powf(value.log2().ceil())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::geometry::Point;
+    use crate::geometry::Rect;
+    use crate::geometry::Size;
+    use super::AtlasAllocator;
+
+    fn rect(x: f64, y: f64, width: f64, height: f64) -> Rect {
+        Rect::new(Point::new(x, y), Size::new(width, height))
+    }
+
+    #[test]
+    fn test_allocate_pads_and_snaps() {
+        let mut atlas = AtlasAllocator::new(rect(0., 0., 16., 16.), 1., true);
+
+        // 5 plus padding on both sides snaps up to 8.
+        assert_eq!(atlas.allocate(Size::new(5., 5.)), Some(rect(1., 1., 5., 5.)));
+        assert_eq!(atlas.allocate(Size::new(6., 3.)), Some(rect(9., 1., 6., 3.)));
+
+        let stats = atlas.stats();
+        assert_eq!(stats.allocations, 2);
+        assert_eq!((stats.total_area, stats.requested_area, stats.reserved_area), (256., 43., 128.));
+    }
+
+    #[test]
+    fn test_allocate_fails_when_full_or_empty() {
+        let mut atlas = AtlasAllocator::new(rect(0., 0., 16., 16.), 1., false);
+
+        for size in [Size::new(0., 0.), Size::new(0., 4.), Size::new(-2., 4.), Size::new(f64::NAN, 4.)] {
+            assert_eq!(atlas.allocate(size), None, "{:?} was allocated", size);
+        }
+        assert!(atlas.allocate(Size::new(14., 14.)).is_some());
+        assert_eq!(atlas.allocate(Size::new(1., 1.)), None);
+        assert_eq!(atlas.stats().allocations, 1);
+    }
+
+    #[test]
+    fn test_free_releases_padding() {
+        let mut atlas = AtlasAllocator::new(rect(0., 0., 16., 16.), 1., false);
+        let full = atlas.allocate(Size::new(14., 14.)).unwrap();
+
+        assert!(!atlas.free(&rect(0., 0., 16., 16.)));
+        assert!(atlas.free(&full));
+        assert!(!atlas.free(&full));
+        assert_eq!(atlas.stats().reserved_area, 0.);
+        assert_eq!(atlas.allocate(Size::new(14., 14.)), Some(full));
+    }
+    #[test]
+    fn test_invalid_padding_is_ignored() {
+        for padding in [-2., f64::NAN] {
+            let mut atlas = AtlasAllocator::new(rect(0., 0., 16., 16.), padding, false);
+
+            assert_eq!(atlas.allocate(Size::new(4., 4.)), Some(rect(0., 0., 4., 4.)));
+            assert_eq!(atlas.stats().reserved_area, 16.);
+        }
+    }
+}
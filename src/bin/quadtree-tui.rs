@@ -0,0 +1,240 @@
+//! A terminal inspector for a quadtree, usable over SSH where no GUI is available.
+//!
+//! Loads a dataset of rects, one `x y width height` per line with `#` starting a
+//! comment, and draws the tree's leaves and members with pan and zoom. Queries can be
+//! run interactively and their results are highlighted.
+//!
+//! Build and run with `cargo run --features tui --bin quadtree-tui -- <dataset>`.
+
+use quadtree::prelude::*;
+
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::Color;
+use ratatui::widgets::canvas::{Canvas, Rectangle};
+use ratatui::widgets::{Block, Paragraph};
+use ratatui::{DefaultTerminal, Frame};
+
+use std::env;
+use std::fs;
+use std::io;
+use std::process;
+
+const HELP: &str = "arrows/hjkl pan, +/- zoom, 0 fit, / query, v query view, c clear, q quit";
+
+struct App {
+    tree: QuadTree,
+    /// The world point at the middle of the view.
+    center: Point,
+    /// The width of the world shown in the view.
+    span: f64,
+    /// The world region drawn last, e.g. to query what is on screen.
+    view: Rect,
+    /// Set when the view should be fit to the root once its shape is known.
+    fit_pending: bool,
+    query: Option<Rect>,
+    results: Vec<Rect>,
+    /// The query being typed, if any.
+    input: Option<String>,
+    message: String,
+}
+
+impl App {
+    fn new(tree: QuadTree, message: String) -> App {
+        App {
+            tree,
+            center: Point::new(0., 0.),
+            span: 1.,
+            view: Rect::new(Point::new(0., 0.), Size::new(1., 1.)),
+            fit_pending: true,
+            query: None,
+            results: Vec::new(),
+            input: None,
+            message,
+        }
+    }
+
+    /// Show the whole root node in a view `aspect` times as tall as it is wide.
+    fn fit(&mut self, aspect: f64) {
+        let root = self.tree.bounds();
+        self.center = root.center();
+        self.span = root.width().max(root.height() / aspect).max(1.);
+        self.fit_pending = false;
+    }
+
+    fn run_query(&mut self, region: Rect) {
+        self.results = self.tree.query(&region);
+        self.message = format!("{} members intersect {:?}", self.results.len(), region);
+        self.query = Some(region);
+    }
+
+    /// Handle a key press, returning false once the inspector should exit.
+    fn handle_key(&mut self, code: KeyCode) -> bool {
+        if let Some(ref mut input) = self.input {
+            match code {
+                KeyCode::Char(c) => input.push(c),
+                KeyCode::Backspace => { input.pop(); },
+                KeyCode::Esc => self.input = None,
+                KeyCode::Enter => {
+                    let text = self.input.take().unwrap_or_default();
+                    match parse_rect(&text) {
+                        Some(region) => {
+                            self.center = region.center();
+                            self.run_query(region);
+                        },
+                        None => self.message = format!("expected `x y width height`, got {:?}", text),
+                    }
+                },
+                _ => (),
+            }
+
+            return true;
+        }
+
+        let step = self.span / 8.;
+        match code {
+            KeyCode::Char('q') | KeyCode::Esc => return false,
+            KeyCode::Left | KeyCode::Char('h') => self.center.x -= step,
+            KeyCode::Right | KeyCode::Char('l') => self.center.x += step,
+            KeyCode::Up | KeyCode::Char('k') => self.center.y -= step,
+            KeyCode::Down | KeyCode::Char('j') => self.center.y += step,
+            KeyCode::Char('+') | KeyCode::Char('=') => self.span /= 1.5,
+            KeyCode::Char('-') => self.span *= 1.5,
+            KeyCode::Char('0') => self.fit_pending = true,
+            KeyCode::Char('/') => self.input = Some(String::new()),
+            KeyCode::Char('v') => self.run_query(self.view),
+            KeyCode::Char('c') => {
+                self.query = None;
+                self.results.clear();
+                self.message.clear();
+            },
+            _ => (),
+        }
+
+        true
+    }
+
+    fn draw(&mut self, frame: &mut Frame) {
+        let [canvas_area, status_area] = Layout::vertical([Constraint::Min(0), Constraint::Length(1)]).areas(frame.area());
+
+        // Terminal cells are about twice as tall as they are wide.
+        let columns = f64::from(canvas_area.width.saturating_sub(2).max(1));
+        let rows = f64::from(canvas_area.height.saturating_sub(2).max(1));
+        let aspect = 2. * rows / columns;
+        if self.fit_pending {
+            self.fit(aspect);
+        }
+
+        let size = Size::new(self.span, self.span * aspect);
+        self.view = Rect::new(Point::new(self.center.x - size.width / 2., self.center.y - size.height / 2.), size);
+
+        let view = self.view;
+        let leaves: Vec<Rect> = self.tree.leaves().map(|leaf| leaf.rect).filter(|rect| rect.intersects(&view)).collect();
+        let members = self.tree.query(&view);
+        let title = format!(" {} members, {} leaves in view ", members.len(), leaves.len());
+
+        let canvas = Canvas::default()
+            .block(Block::bordered().title(title))
+            .x_bounds([view.min_x(), view.max_x()])
+            // The canvas's y axis points up, so flip it to draw the tree's way up.
+            .y_bounds([-view.max_y(), -view.min_y()])
+            .paint(|ctx| {
+                for rect in leaves.iter() {
+                    ctx.draw(&shape(rect, Color::DarkGray));
+                }
+                for rect in members.iter() {
+                    let color = if self.results.contains(rect) { Color::Yellow } else { Color::Cyan };
+                    ctx.draw(&shape(rect, color));
+                }
+                if let Some(ref region) = self.query {
+                    ctx.draw(&shape(region, Color::Red));
+                }
+            });
+        frame.render_widget(canvas, canvas_area);
+
+        let status = match self.input {
+            Some(ref input) => format!("query x y width height: {}", input),
+            None if self.message.is_empty() => HELP.to_string(),
+            None => format!("{} | {}", self.message, HELP),
+        };
+        frame.render_widget(Paragraph::new(status), status_area);
+    }
+}
+
+/// A canvas shape outlining `rect`, flipped to the canvas's upward y axis.
+fn shape(rect: &Rect, color: Color) -> Rectangle {
+    Rectangle { x: rect.min_x(), y: -rect.max_y(), width: rect.width(), height: rect.height(), color }
+}
+
+fn parse_rect(text: &str) -> Option<Rect> {
+    let values: Vec<f64> = text.split_whitespace().map(|value| value.parse().ok()).collect::<Option<_>>()?;
+    match values[..] {
+        [x, y, width, height] => Some(Rect::new(Point::new(x, y), Size::new(width, height))),
+        _ => None,
+    }
+}
+
+/**
+ Read a dataset into a tree allowing overlap, returning it along with a summary of
+ what was loaded.
+ */
+fn load(path: &str) -> Result<(QuadTree, String), Error> {
+    let contents = fs::read_to_string(path)?;
+    let mut tree = QuadTree::builder().allow_overlap(true).build();
+    let mut rejected = 0;
+
+    for (number, line) in contents.lines().enumerate() {
+        let line = line.split('#').next().unwrap_or("").trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        let rect = parse_rect(line)
+            .ok_or_else(|| Error::Serialization(format!("line {}: expected `x y width height`", number + 1)))?;
+        if tree.try_insert(rect, 0).is_err() {
+            rejected += 1;
+        }
+    }
+
+    let message = format!("loaded {} members, rejected {}", tree.members().len(), rejected);
+    Ok((tree, message))
+}
+
+fn run(terminal: &mut DefaultTerminal, mut app: App) -> io::Result<()> {
+    loop {
+        terminal.draw(|frame| app.draw(frame))?;
+
+        if let Event::Key(key) = event::read()? {
+            if key.kind == KeyEventKind::Press && !app.handle_key(key.code) {
+                return Ok(())
+            }
+        }
+    }
+}
+
+fn main() {
+    let path = match env::args().nth(1) {
+        Some(path) => path,
+        None => {
+            eprintln!("usage: quadtree-tui <dataset>");
+            process::exit(2);
+        },
+    };
+
+    let (tree, message) = match load(&path) {
+        Ok(loaded) => loaded,
+        Err(err) => {
+            eprintln!("quadtree-tui: {}: {}", path, err);
+            process::exit(1);
+        },
+    };
+
+    let mut terminal = ratatui::init();
+    let result = run(&mut terminal, App::new(tree, message));
+    ratatui::restore();
+
+    if let Err(err) = result {
+        eprintln!("quadtree-tui: {}", err);
+        process::exit(1);
+    }
+}
@@ -0,0 +1,220 @@
+use crate::error::Error;
+use crate::geometry::Point;
+use crate::geometry::Rect;
+use crate::index::SpatialIndex;
+use crate::quadtree::{Config, QuadTree};
+
+/**
+ A quadtree that, in debug builds, validates itself and cross-checks each result
+ after every mutating operation, panicking at the first sign of corruption rather than
+ on some later query. In release builds every operation goes straight to the tree.
+ */
+pub struct CheckedQuadTree {
+    tree: QuadTree,
+}
+
+impl CheckedQuadTree {
+    /**
+     Wrap `tree`, validating it first in debug builds.
+     */
+    pub fn new(tree: QuadTree) -> CheckedQuadTree {
+        let checked = CheckedQuadTree { tree };
+        checked.verify("new", true);
+
+        checked
+    }
+
+    /// Get the wrapped tree, for queries.
+    pub fn tree(&self) -> &QuadTree {
+        &self.tree
+    }
+
+    /// Unwrap the tree.
+    pub fn unwrap(self) -> QuadTree {
+        self.tree
+    }
+
+    /**
+     Insert `rect`, returning false if it conflicts with an existing member.
+     */
+    pub fn insert_rect(&mut self, rect: Rect) -> bool {
+        self.insert_rect_with_priority(rect, 0)
+    }
+
+    /**
+     Insert `rect` with the given priority, returning false if it conflicts with an
+     existing member.
+     */
+    pub fn insert_rect_with_priority(&mut self, rect: Rect, priority: i32) -> bool {
+        let before = self.members_if_checking();
+        let inserted = self.tree.try_insert(rect, priority).is_ok();
+
+        self.verify("insert_rect_with_priority", !self.is_checking() || if inserted {
+            self.tree.members().len() == before.len() + 1
+            && self.tree.get(&rect).map(|item| item.priority) == Some(priority)
+        } else {
+            self.tree.members().len() == before.len()
+        });
+
+        inserted
+    }
+
+    /**
+     Remove and return every member intersecting `region`.
+     */
+    pub fn remove_in(&mut self, region: &Rect) -> Vec<Rect> {
+        let before = self.members_if_checking();
+        let removed = self.tree.remove_in(region);
+
+        self.verify("remove_in", !self.is_checking() ||
+            self.tree.members().len() + removed.len() == before.len()
+            && self.tree.query(region).is_empty());
+
+        removed
+    }
+
+    /**
+     Remove the member `rect`, returning false if it wasn't a member.
+     */
+    pub fn remove(&mut self, rect: &Rect) -> bool {
+        let before = self.members_if_checking();
+        let removed = self.tree.remove_rect(rect);
+
+        self.verify("remove", !self.is_checking() ||
+            self.tree.members().len() + removed as usize == before.len()
+            && !self.tree.contains(rect));
+
+        removed
+    }
+
+    /**
+     Remove and return every member lying entirely inside `region`.
+     */
+    pub fn remove_contained(&mut self, region: &Rect) -> Vec<Rect> {
+        let before = self.members_if_checking();
+        let removed = self.tree.remove_contained(region);
+
+        self.verify("remove_contained", !self.is_checking() ||
+            self.tree.members().len() + removed.len() == before.len()
+            && self.tree.query_contained(region).is_empty());
+
+        removed
+    }
+
+    /**
+     Move every member intersecting `region` by `delta`, returning the members that
+     could not be moved.
+     */
+    pub fn translate_in(&mut self, region: &Rect, delta: Point) -> Vec<Rect> {
+        let before = self.members_if_checking();
+        let blocked = self.tree.translate_in(region, delta);
+
+        self.verify("translate_in", !self.is_checking() ||
+            self.tree.members().len() == before.len()
+            && blocked.iter().all(|rect| self.tree.contains(rect)));
+
+        blocked
+    }
+
+    /**
+     Change the priority of the member `rect`. Returns false if `rect` is not a member.
+     */
+    pub fn set_priority(&mut self, rect: &Rect, priority: i32) -> bool {
+        let found = self.tree.set_priority(rect, priority);
+
+        self.verify("set_priority", !self.is_checking() || !found ||
+            self.tree.get(rect).map(|item| item.priority) == Some(priority));
+
+        found
+    }
+
+    /**
+     Change the tree's settings. The tree is restructured lazily.
+     */
+    pub fn set_config(&mut self, config: Config) {
+        self.tree.set_config(config);
+        self.verify("set_config", true);
+    }
+
+    /**
+     Grow the root node until it contains `rect`.
+     */
+    pub fn expand_to_include(&mut self, rect: &Rect) -> Result<(), Error> {
+        let before = self.members_if_checking();
+        let result = self.tree.expand_to_include(rect);
+
+        self.verify("expand_to_include", !self.is_checking() ||
+            (result.is_err() || self.tree.bounds().contains(rect)) && self.tree.members().len() == before.len());
+
+        result
+    }
+
+    fn is_checking(&self) -> bool {
+        cfg!(debug_assertions)
+    }
+
+    /// The tree's members in debug builds, to compare against after an operation.
+    fn members_if_checking(&self) -> Vec<Rect> {
+        if self.is_checking() {
+            self.tree.members()
+        } else {
+            Vec::new()
+        }
+    }
+
+    /**
+     In debug builds, panic if the tree is invalid or if `result_ok`, the cross-check of
+     the operation's result, is false.
+     */
+    fn verify(&self, operation: &str, result_ok: bool) {
+        if !self.is_checking() {
+            return
+        }
+
+        if let Err(message) = self.tree.validate() {
+            panic!("QuadTree invalid after {}: {}", operation, message);
+        }
+
+        if !result_ok {
+            panic!("QuadTree returned an inconsistent result from {}", operation);
+        }
+    }
+}
+
+impl SpatialIndex for CheckedQuadTree {
+    fn insert(&mut self, rect: Rect) -> bool {
+        self.insert_rect(rect)
+    }
+
+    fn members(&self) -> Vec<Rect> {
+        self.tree.members()
+    }
+
+    fn query(&self, region: &Rect) -> Vec<Rect> {
+        self.tree.query(region)
+    }
+
+    fn contains_member(&self, rect: &Rect) -> bool {
+        self.tree.contains(rect)
+    }
+
+    fn query_contained(&self, region: &Rect) -> Vec<Rect> {
+        self.tree.query_contained(region)
+    }
+
+    fn query_touching(&self, rect: &Rect) -> Vec<Rect> {
+        self.tree.query_touching(rect)
+    }
+
+    fn remove_in(&mut self, region: &Rect) -> Vec<Rect> {
+        self.remove_in(region)
+    }
+
+    fn remove(&mut self, rect: &Rect) -> bool {
+        self.remove(rect)
+    }
+
+    fn nearest(&self, point: Point) -> Option<Rect> {
+        self.tree.nearest(point)
+    }
+}
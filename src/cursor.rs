@@ -0,0 +1,313 @@
+use crate::error::Error;
+use crate::geometry::Rect;
+use crate::quadtree::{Item, Node, QuadTree, Quadrant};
+use crate::split::{CapacitySplit, SplitPolicy};
+
+/**
+ A position within a quadtree that can be moved down to a child node or back
+ up to its parent, for walking the tree explicitly in custom algorithms.
+ */
+pub struct Cursor<'a> {
+    /// The nodes from the root down to the current node.
+    path: Vec<&'a Node>,
+}
+
+impl<'a> Cursor<'a> {
+    /**
+     Create a cursor positioned at the root of `tree`.
+     */
+    pub fn new<T, P: SplitPolicy>(tree: &'a QuadTree<T, P>) -> Cursor<'a> {
+        Cursor { path: vec![tree.root()] }
+    }
+
+    /**
+     Move to the child of the current node in the given quadrant.
+     Returns false, without moving, if the current node has no children.
+     */
+    pub fn descend(&mut self, quadrant: Quadrant) -> bool {
+        match self.node().child(quadrant) {
+            Some(child) => {
+                self.path.push(child);
+                true
+            },
+            None => false,
+        }
+    }
+
+    /**
+     Move down from the current node to the smallest descendant that entirely contains
+     `rect`. Returns false, without moving, if the current node doesn't contain `rect`.
+     */
+    pub fn descend_to_enclosing(&mut self, rect: &Rect) -> bool {
+        if !self.bounds().contains(rect) {
+            return false;
+        }
+
+        let mut descended = true;
+        while descended {
+            descended = false;
+            for quadrant in Quadrant::ALL.iter() {
+                if !self.descend(*quadrant) {
+                    break;
+                }
+                if self.bounds().contains(rect) {
+                    descended = true;
+                    break;
+                }
+                self.ascend();
+            }
+        }
+
+        true
+    }
+
+    /**
+     Move to the parent of the current node.
+     Returns false, without moving, if the cursor is at the root.
+     */
+    pub fn ascend(&mut self) -> bool {
+        if self.path.len() > 1 {
+            self.path.pop();
+            true
+        } else {
+            false
+        }
+    }
+
+    /// The bounds of the current node.
+    pub fn bounds(&self) -> Rect {
+        self.node().bounds()
+    }
+
+    /**
+     The bounds of each node from the current node's parent up to the root, nearest first.
+     */
+    pub fn ancestor_bounds(&self) -> Vec<Rect> {
+        let count = self.path.len() - 1;
+        self.path[..count].iter().rev().map(|node| node.bounds()).collect()
+    }
+
+    /// The number of steps from the root to the current node.
+    pub fn depth(&self) -> usize {
+        self.path.len() - 1
+    }
+
+    /// Check if the current node has no children.
+    pub fn is_leaf(&self) -> bool {
+        self.node().is_leaf()
+    }
+
+    /**
+     Find every member stored in the current node or its descendants.
+     */
+    pub fn members(&self) -> Vec<Rect> {
+        self.node().members()
+    }
+
+    fn node(&self) -> &'a Node {
+        self.path.last().unwrap()
+    }
+}
+
+/**
+ A cursor like `Cursor` that can also change the members of the node it is at, e.g.
+ to reprioritize or prune members while growing a region. Changes go through the
+ tree, so every copy of a spanning member changes together. A change that collapses
+ the node the cursor is at moves the cursor up to the nearest node left standing.
+ */
+pub struct CursorMut<'a, T = (), P = CapacitySplit> {
+    tree: &'a mut QuadTree<T, P>,
+    /// The quadrants leading from the root down to the current node.
+    path: Vec<Quadrant>,
+}
+
+impl<'a, T, P: SplitPolicy> CursorMut<'a, T, P> {
+    /**
+     Create a cursor positioned at the root of `tree`.
+     */
+    pub fn new(tree: &'a mut QuadTree<T, P>) -> CursorMut<'a, T, P> {
+        CursorMut { tree, path: Vec::new() }
+    }
+
+    /**
+     Move to the child of the current node in the given quadrant.
+     Returns false, without moving, if the current node has no children.
+     */
+    pub fn descend(&mut self, quadrant: Quadrant) -> bool {
+        let has_children = !self.node().is_leaf();
+        if has_children {
+            self.path.push(quadrant);
+        }
+
+        has_children
+    }
+
+    /**
+     Move to the parent of the current node.
+     Returns false, without moving, if the cursor is at the root.
+     */
+    pub fn ascend(&mut self) -> bool {
+        self.path.pop().is_some()
+    }
+
+    /// The bounds of the current node.
+    pub fn bounds(&self) -> Rect {
+        self.node().bounds()
+    }
+
+    /// The number of steps from the root to the current node.
+    pub fn depth(&self) -> usize {
+        self.path.len()
+    }
+
+    /// Check if the current node has no children.
+    pub fn is_leaf(&self) -> bool {
+        self.node().is_leaf()
+    }
+
+    /**
+     Find every member stored in the current node or its descendants.
+     */
+    pub fn members(&self) -> Vec<Rect> {
+        self.node().members()
+    }
+
+    /**
+     Look up the item for the member `rect` if it is stored in the current node or
+     its descendants.
+     */
+    pub fn get(&self, rect: &Rect) -> Option<Item> {
+        self.node().get(rect)
+    }
+
+    /**
+     Change the priority of the member `rect`, which must be stored in the current node
+     or its descendants. Returns false if it isn't.
+     */
+    pub fn set_priority(&mut self, rect: &Rect, priority: i32) -> bool {
+        self.get(rect).is_some() && self.tree.set_priority(rect, priority)
+    }
+
+    /**
+     Insert `rect` into the tree, as `QuadTree::try_insert` does. Fails with `InvalidNode`
+     if `rect` doesn't lie within the current node, since the tree never grows from here.
+     */
+    pub fn insert(&mut self, rect: Rect, priority: i32) -> Result<(), Error> {
+        let bounds = self.bounds();
+        if !bounds.contains(&rect) {
+            return Err(Error::InvalidNode(bounds))
+        }
+
+        let result = self.tree.try_insert(rect, priority);
+        self.settle();
+        result
+    }
+
+    /**
+     Remove the member `rect`, which must be stored in the current node or its
+     descendants, from the tree. Returns false if it isn't.
+     */
+    pub fn remove(&mut self, rect: &Rect) -> bool {
+        let removed = self.get(rect).is_some() && self.tree.remove_rect(rect);
+        self.settle();
+        removed
+    }
+
+    fn node(&self) -> &Node {
+        let mut node = self.tree.root();
+        for quadrant in self.path.iter() {
+            node = node.child(*quadrant).unwrap();
+        }
+
+        node
+    }
+
+    /**
+     Move up to the deepest node on the path that still exists.
+     */
+    fn settle(&mut self) {
+        let mut node = self.tree.root();
+        let mut depth = 0;
+        while let Some(child) = self.path.get(depth).and_then(|quadrant| node.child(*quadrant)) {
+            node = child;
+            depth += 1;
+        }
+
+        self.path.truncate(depth);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::error::Error;
+    use crate::geometry::{Point, Rect, Size};
+    use crate::quadtree::{QuadTree, Quadrant};
+
+    fn rect(x: f64, y: f64, width: f64, height: f64) -> Rect {
+        Rect::new(Point::new(x, y), Size::new(width, height))
+    }
+
+    fn new_tree() -> QuadTree {
+        let mut tree = QuadTree::builder().bounds(rect(0., 0., 64., 64.)).leaf_capacity(1).build();
+        for member in [rect(1., 1., 2., 2.), rect(5., 5., 2., 2.), rect(40., 40., 2., 2.)] {
+            tree.try_insert(member, 0).unwrap();
+        }
+
+        tree
+    }
+
+    #[test]
+    fn test_cursor_walks_to_enclosing_node() {
+        let tree = new_tree();
+        let mut cursor = tree.cursor_enclosing(&rect(1., 1., 2., 2.)).unwrap();
+
+        assert!(cursor.is_leaf());
+        assert_eq!(cursor.members(), vec![rect(1., 1., 2., 2.)]);
+        assert_eq!(cursor.ancestor_bounds().last(), Some(&rect(0., 0., 64., 64.)));
+        while cursor.ascend() {}
+        assert_eq!(cursor.depth(), 0);
+        assert!(!cursor.ascend());
+        assert!(tree.cursor_enclosing(&rect(60., 60., 8., 8.)).is_none());
+    }
+
+    #[test]
+    fn test_cursor_mut_edits_members_of_its_node() {
+        let mut tree = new_tree();
+        let mut cursor = tree.cursor_mut();
+        assert!(cursor.descend(Quadrant::BottomRight));
+        assert_eq!(cursor.members(), vec![rect(40., 40., 2., 2.)]);
+
+        // Only members under the cursor can be changed from it.
+        assert!(!cursor.set_priority(&rect(1., 1., 2., 2.), 4));
+        assert!(cursor.set_priority(&rect(40., 40., 2., 2.), 4));
+        assert!(matches!(cursor.insert(rect(1., 40., 2., 2.), 0), Err(Error::InvalidNode(_))));
+        cursor.insert(rect(50., 50., 2., 2.), 1).unwrap();
+        assert_eq!(cursor.members().len(), 2);
+
+        assert!(!cursor.remove(&rect(5., 5., 2., 2.)));
+        assert!(cursor.remove(&rect(40., 40., 2., 2.)));
+        assert_eq!(cursor.members(), vec![rect(50., 50., 2., 2.)]);
+
+        assert_eq!(tree.get(&rect(50., 50., 2., 2.)).unwrap().priority, 1);
+        assert!(!tree.contains(&rect(40., 40., 2., 2.)));
+        assert_eq!(tree.len(), 3);
+        assert_eq!(tree.validate(), Ok(()));
+    }
+
+    #[test]
+    fn test_cursor_mut_moves_up_when_its_node_collapses() {
+        let mut tree = new_tree();
+        let mut cursor = tree.cursor_mut();
+        cursor.descend(Quadrant::TopLeft);
+        while cursor.descend(Quadrant::TopLeft) {}
+        assert_eq!(cursor.members(), vec![rect(1., 1., 2., 2.)]);
+        assert!(cursor.depth() > 1);
+
+        cursor.ascend();
+        assert!(cursor.remove(&rect(5., 5., 2., 2.)));
+        // The top left quadrant coalesced into a leaf holding the remaining member.
+        assert_eq!(cursor.depth(), 1);
+        assert!(cursor.is_leaf());
+        assert_eq!(cursor.members(), vec![rect(1., 1., 2., 2.)]);
+    }
+}
@@ -0,0 +1,64 @@
+use crate::geometry::Rect;
+
+use std::error;
+use std::fmt;
+use std::io;
+
+/**
+ The ways an operation on a quadtree can fail.
+ */
+#[derive(Debug)]
+pub enum Error {
+    /// The rect being inserted overlaps these existing members.
+    Overlap(Vec<Rect>),
+    /// The rect being inserted is already a member.
+    Duplicate(Rect),
+    /// The rect is not a member.
+    NotFound(Rect),
+    /// The rect has a non-finite coordinate or a negative size.
+    InvalidRect(Rect),
+    /// The elements given for the node with these bounds don't fit within it.
+    InvalidNode(Rect),
+    /// The root can't be grown to contain this rect, e.g. because it is too far away.
+    CannotGrow(Rect),
+    /// The rect would span several leaves, which the tree's placement rejects.
+    SpansLeaves(Rect),
+    /// A fixed-capacity tree has no room left for the rect.
+    Full,
+    /// Data being read or written in some serialized format was malformed.
+    Serialization(String),
+    /// Reading or writing failed.
+    Io(io::Error),
+}
+
+impl error::Error for Error {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match *self {
+            Error::Io(ref err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::Overlap(ref rects) => write!(f, "rect overlaps existing members {:?}", rects),
+            Error::Duplicate(rect) => write!(f, "rect {:?} is already a member", rect),
+            Error::NotFound(rect) => write!(f, "rect {:?} is not a member", rect),
+            Error::InvalidRect(rect) => write!(f, "rect {:?} is not finite or has a negative size", rect),
+            Error::InvalidNode(rect) => write!(f, "elements don't fit node {:?}", rect),
+            Error::CannotGrow(rect) => write!(f, "root can't be grown to contain {:?}", rect),
+            Error::SpansLeaves(rect) => write!(f, "rect {:?} would span several leaves", rect),
+            Error::Full => write!(f, "tree has no free capacity"),
+            Error::Serialization(ref message) => write!(f, "malformed serialized data: {}", message),
+            Error::Io(ref err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Error {
+        Error::Io(err)
+    }
+}
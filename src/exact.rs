@@ -0,0 +1,292 @@
+/*!
+ Exact predicates and queries, for callers who can't accept answers changed by rounding.
+
+ Coordinates stay `f64`, and each is exactly some rational number, but a rect's far
+ edges are found as `origin + size`, which rounds, so rects that are slightly apart can
+ seem to touch, and a thin rect can seem to be a segment. The predicates here convert
+ each coordinate to an arbitrary precision rational before adding, so every comparison
+ is exact. They're much slower than those in `geometry::predicates`.
+
+ A rect with a non-finite coordinate has no exact edges, and is disjoint from everything.
+ */
+
+use crate::geometry::predicates::{interval_contains, interval_interiors_meet, intervals_intersect};
+use crate::geometry::Rect;
+use crate::geometry::Relation;
+use crate::quadtree::QuadTree;
+use crate::split::SplitPolicy;
+
+use num_rational::BigRational;
+
+/**
+ Check if the intervals `[a_min, a_max]` and `[b_min, b_max]` share more than a single point.
+ */
+fn intervals_overlap(a_min: &BigRational, a_max: &BigRational, b_min: &BigRational, b_max: &BigRational) -> bool {
+    a_min < a_max && b_min < b_max && a_min < b_max && b_min < a_max
+}
+
+/**
+ A rect's edges as exact rationals.
+ */
+struct Edges {
+    min_x: BigRational,
+    max_x: BigRational,
+    min_y: BigRational,
+    max_y: BigRational,
+}
+
+impl Edges {
+    fn of(rect: &Rect) -> Option<Edges> {
+        let min_x = BigRational::from_float(rect.origin.x)?;
+        let min_y = BigRational::from_float(rect.origin.y)?;
+        let max_x = &min_x + BigRational::from_float(rect.size.width)?;
+        let max_y = &min_y + BigRational::from_float(rect.size.height)?;
+
+        Some(Edges { min_x, max_x, min_y, max_y })
+    }
+
+    fn intersects(&self, other: &Edges) -> bool {
+        intervals_intersect(&self.min_x, &self.max_x, &other.min_x, &other.max_x)
+        && intervals_intersect(&self.min_y, &self.max_y, &other.min_y, &other.max_y)
+    }
+
+    fn overlaps(&self, other: &Edges) -> bool {
+        intervals_overlap(&self.min_x, &self.max_x, &other.min_x, &other.max_x)
+        && intervals_overlap(&self.min_y, &self.max_y, &other.min_y, &other.max_y)
+    }
+
+    fn contains(&self, inner: &Edges) -> bool {
+        interval_contains(&self.min_x, &self.max_x, &inner.min_x, &inner.max_x)
+        && interval_contains(&self.min_y, &self.max_y, &inner.min_y, &inner.max_y)
+    }
+
+    fn interiors_meet(&self, other: &Edges) -> bool {
+        interval_interiors_meet(&self.min_x, &self.max_x, &other.min_x, &other.max_x)
+        && interval_interiors_meet(&self.min_y, &self.max_y, &other.min_y, &other.max_y)
+    }
+}
+
+/**
+ Apply `predicate` to the exact edges of `a` and `b`, or return false if either has none.
+ */
+fn exactly(a: &Rect, b: &Rect, predicate: impl FnOnce(&Edges, &Edges) -> bool) -> bool {
+    match (Edges::of(a), Edges::of(b)) {
+        (Some(a), Some(b)) => predicate(&a, &b),
+        _ => false,
+    }
+}
+
+/**
+ Check if two rects have any point in common, including points on their edges.
+ */
+pub fn intersects(a: &Rect, b: &Rect) -> bool {
+    exactly(a, b, Edges::intersects)
+}
+
+/**
+ Check if the interiors of two rects overlap, i.e. they intersect in a region with positive area.
+ */
+pub fn overlaps(a: &Rect, b: &Rect) -> bool {
+    exactly(a, b, Edges::overlaps)
+}
+
+/**
+ Check if `outer` entirely contains `inner`. Shared edges are allowed.
+ */
+pub fn contains(outer: &Rect, inner: &Rect) -> bool {
+    exactly(outer, inner, Edges::contains)
+}
+
+/**
+ Check if two rects share part of their boundary without their interiors meeting.
+ */
+pub fn touches(a: &Rect, b: &Rect) -> bool {
+    relate(a, b) == Relation::Touches
+}
+
+/**
+ Find how `a` is related to `b`, as `geometry::predicates::relate` does.
+ */
+pub fn relate(a: &Rect, b: &Rect) -> Relation {
+    let (a_edges, b_edges) = match (Edges::of(a), Edges::of(b)) {
+        (Some(a_edges), Some(b_edges)) => (a_edges, b_edges),
+        _ => return Relation::Disjoint,
+    };
+
+    if a == b {
+        Relation::Equals
+    } else if !a_edges.intersects(&b_edges) {
+        Relation::Disjoint
+    } else if !a_edges.interiors_meet(&b_edges) {
+        Relation::Touches
+    } else if a_edges.contains(&b_edges) {
+        Relation::Contains
+    } else if b_edges.contains(&a_edges) {
+        Relation::Within
+    } else {
+        Relation::Overlaps
+    }
+}
+
+impl<T, P: SplitPolicy> QuadTree<T, P> {
+    /**
+     Find the members exactly intersecting `region`. Candidates are found with the
+     tree's float predicates, widened by a margin covering their rounding, then each
+     is checked exactly.
+     */
+    pub fn query_exact(&self, region: &Rect) -> Vec<Rect> {
+        self.exact_candidates(region).into_iter().filter(|rect| intersects(rect, region)).collect()
+    }
+
+    /**
+     Find the members exactly related to `region` by `relation`, like `query_related`.
+     */
+    pub fn query_related_exact(&self, region: &Rect, relation: Relation) -> Vec<Rect> {
+        let candidates = if relation == Relation::Disjoint { self.members() } else { self.exact_candidates(region) };
+        candidates.into_iter().filter(|rect| relate(rect, region) == relation).collect()
+    }
+
+    /**
+     Check the tree as `validate` does, then check exactly that each leaf's members
+     intersect it, and that no members overlap unless the tree allows it. The nodes
+     themselves are placed by float arithmetic, so only `validate` checks they tile.
+     */
+    pub fn validate_exact(&self) -> Result<(), String> {
+        self.validate()?;
+
+        for leaf in self.leaves() {
+            if let Some(rect) = leaf.members.iter().find(|rect| !intersects(&leaf.rect, rect)) {
+                return Err(format!("leaf {:?} holds member {:?} outside its exact bounds", leaf.rect, rect))
+            }
+        }
+
+        if !self.config().allow_overlap {
+            for rect in self.members().iter() {
+                if let Some(other) = self.exact_candidates(rect).iter().find(|other| *other != rect && overlaps(other, rect)) {
+                    return Err(format!("members {:?} and {:?} overlap exactly", rect, other))
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /**
+     Find every member that might exactly intersect `region`: those whose float edges
+     are within the rounding error of the root's and the region's edges from it.
+     */
+    fn exact_candidates(&self, region: &Rect) -> Vec<Rect> {
+        let bounds = self.bounds();
+        let scale = [bounds.min_x(), bounds.max_x(), bounds.min_y(), bounds.max_y(),
+                     region.min_x(), region.max_x(), region.min_y(), region.max_y()]
+            .iter().fold(0f64, |scale, edge| scale.max(edge.abs()));
+        if !scale.is_finite() {
+            return Vec::new()
+        }
+
+        self.query_inflated(region, 4. * f64::EPSILON * scale + f64::MIN_POSITIVE)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::geometry::predicates;
+    use crate::geometry::Point;
+    use crate::geometry::Rect;
+    use crate::geometry::Relation;
+    use crate::geometry::Size;
+    use crate::quadtree::QuadTree;
+    use super::{contains, intersects, overlaps, relate, touches};
+
+    fn rect(x: f64, y: f64, width: f64, height: f64) -> Rect {
+        Rect::new(Point::new(x, y), Size::new(width, height))
+    }
+
+    /// A rect at `x = 1` whose width is lost when its right edge is rounded.
+    fn sliver() -> Rect {
+        rect(1., 0., 2f64.powi(-53), 1.)
+    }
+
+    /// A rect at `x = 1` whose right edge is rounded up to the next float after 1.
+    fn rounded_up() -> Rect {
+        rect(1., 2., 3. * 2f64.powi(-54), 1.)
+    }
+
+    #[test]
+    fn test_exact_agrees_on_representable_edges() {
+        let base = rect(2., 2., 2., 2.);
+        let intervals = [(0., 1.), (0., 2.), (1., 3.), (2.5, 3.5), (2., 4.), (1., 5.),
+                         (3., 5.), (4., 5.), (5., 6.), (3., 3.), (2., 2.), (6., 6.)];
+
+        for &(x_min, x_max) in intervals.iter() {
+            for &(y_min, y_max) in intervals.iter() {
+                let other = rect(x_min, y_min, x_max - x_min, y_max - y_min);
+
+                assert_eq!(intersects(&base, &other), predicates::intersects(&base, &other));
+                assert_eq!(overlaps(&base, &other), predicates::overlaps(&base, &other));
+                assert_eq!(contains(&base, &other), predicates::contains(&base, &other));
+                assert_eq!(contains(&other, &base), predicates::contains(&other, &base));
+                assert_eq!(touches(&base, &other), predicates::touches(&base, &other));
+                assert_eq!(relate(&base, &other), predicates::relate(&base, &other));
+                assert_eq!(relate(&other, &base), predicates::relate(&other, &base));
+            }
+        }
+    }
+
+    #[test]
+    fn test_exact_edges_are_not_rounded() {
+        let cell = rect(1., 0., 1., 1.);
+        // Rounded, the sliver is a segment along the cell's left edge.
+        assert_eq!(predicates::relate(&sliver(), &cell), Relation::Touches);
+        assert_eq!(relate(&sliver(), &cell), Relation::Within);
+        assert!(overlaps(&sliver(), &cell));
+
+        // Rounded, this rect's right edge meets the next one's left edge, but it stops short.
+        let next = rect(1. + f64::EPSILON, 2., 1., 1.);
+        assert!(predicates::touches(&rounded_up(), &next));
+        assert!(!intersects(&rounded_up(), &next));
+        assert_eq!(relate(&rounded_up(), &next), Relation::Disjoint);
+    }
+
+    #[test]
+    fn test_exact_non_finite_rects() {
+        let base = rect(0., 0., 2., 2.);
+        let infinite = rect(0., 0., f64::INFINITY, 1.);
+        let nan = rect(f64::NAN, 0., 1., 1.);
+
+        assert!(!intersects(&base, &infinite));
+        assert!(!contains(&infinite, &base));
+        assert_eq!(relate(&nan, &nan), Relation::Disjoint);
+        assert_eq!(relate(&base, &infinite), Relation::Disjoint);
+    }
+
+    #[test]
+    fn test_query_exact() {
+        let mut tree = QuadTree::builder().bounds(rect(0., 0., 4., 4.)).leaf_capacity(1).build();
+        for member in [sliver(), rect(1., 0., 1., 1.), rounded_up(), rect(3., 3., 1., 1.)].iter() {
+            tree.try_insert(*member, 0).unwrap();
+        }
+
+        let region = rect(1. + f64::EPSILON, 2., 1., 1.);
+        assert!(tree.query(&region).contains(&rounded_up()));
+        assert_eq!(tree.query_exact(&region), vec![]);
+        assert_eq!(tree.query_exact(&rect(0.5, 0.5, 3., 3.)).len(), 4);
+
+        assert_eq!(tree.query_related(&rect(1., 0., 1., 1.), Relation::Within), vec![]);
+        assert_eq!(tree.query_related_exact(&rect(1., 0., 1., 1.), Relation::Within), vec![sliver()]);
+        assert_eq!(tree.query_related_exact(&region, Relation::Disjoint).len(), 4);
+    }
+
+    #[test]
+    fn test_validate_exact() {
+        let mut tree = QuadTree::builder().bounds(rect(0., 0., 4., 4.)).build();
+        tree.try_insert(rect(1., 0., 1., 1.), 0).unwrap();
+        tree.try_insert(rect(3., 3., 1., 1.), 0).unwrap();
+        assert_eq!(tree.validate_exact(), Ok(()));
+
+        // Rounded, the sliver only touches the cell, so the tree accepts both.
+        tree.try_insert(sliver(), 0).unwrap();
+        assert_eq!(tree.validate(), Ok(()));
+        assert!(tree.validate_exact().is_err());
+    }
+}
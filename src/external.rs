@@ -0,0 +1,226 @@
+/*!
+ Building a quadtree from more rects than fit in memory.
+
+ Rects are streamed into spill files, one for each quadrant of the root, and each spill
+ file is split again the same way until its rects fit within the memory limit. Each
+ cell small enough is then built into an ordinary tree and handed to the caller, e.g.
+ to be flattened and written out, so only one cell's tree is in memory at a time.
+ */
+
+use crate::error::Error;
+use crate::geometry::Point;
+use crate::geometry::Rect;
+use crate::geometry::Size;
+use crate::quadtree::{Config, QuadTree};
+
+use std::fs;
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::PathBuf;
+
+/// The size of a rect in a spill file: its x, y, width, and height as little-endian f64s.
+const RECORD_LEN: usize = 32;
+
+/**
+ Builds the cells of a quadtree over `bounds` from a stream of rects, spilling rects
+ to files in `spill_dir` while they are partitioned.
+ */
+#[derive(Clone, Debug)]
+pub struct ExternalBuilder {
+    bounds: Rect,
+    config: Config,
+    max_in_memory: usize,
+    spill_dir: PathBuf,
+}
+
+impl ExternalBuilder {
+    /**
+     Create a builder with the default config, holding at most about a million rects
+     in memory at once.
+     */
+    pub fn new(bounds: Rect, spill_dir: impl Into<PathBuf>) -> ExternalBuilder {
+        ExternalBuilder { bounds, config: Default::default(), max_in_memory: 1 << 20, spill_dir: spill_dir.into() }
+    }
+
+    /**
+     Set the config each cell's tree is built with.
+     */
+    pub fn config(self, config: Config) -> ExternalBuilder {
+        ExternalBuilder { config, ..self }
+    }
+
+    /**
+     Set the most rects a cell may hold before it is split into spill files.
+     */
+    pub fn max_in_memory(self, max_in_memory: usize) -> ExternalBuilder {
+        ExternalBuilder { max_in_memory: max_in_memory.max(1), ..self }
+    }
+
+    /**
+     Partition `rects` into cells that each fit in memory, and call `emit` with the
+     bounds and tree of each cell in turn. Cells are quadrants of `bounds` at some depth,
+     so together they tile it. A rect spanning several cells is in each of their trees,
+     as it would be in the leaves of a single tree, and those trees' roots are not grown
+     to contain it. Within a cell, a rect is rejected as `QuadTree::try_insert` would.
+
+     Cells at the config's maximum depth are built however many rects they hold.
+     Fails with `CannotGrow` if a rect is outside `bounds`, and with `Io` if a spill
+     file can't be written or read. Spill files are removed once they are used.
+     */
+    pub fn build<I, E>(&self, rects: I, mut emit: E) -> Result<(), Error>
+        where I: IntoIterator<Item = Rect>,
+              E: FnMut(Rect, QuadTree) -> Result<(), Error> {
+        fs::create_dir_all(&self.spill_dir)?;
+
+        let bounds = self.bounds;
+        let rects = rects.into_iter().map(|rect| if bounds.contains(&rect) { Ok(rect) } else { Err(Error::CannotGrow(rect)) });
+        self.build_cell(bounds, "", 0, rects, &mut emit)
+    }
+
+    /**
+     Build the cell at `path` below the root, whose rects are `rects`, or spill them
+     into its quadrants and build those instead.
+     */
+    fn build_cell(&self, cell: Rect, path: &str, depth: usize, mut rects: impl Iterator<Item = Result<Rect, Error>>,
+                  emit: &mut dyn FnMut(Rect, QuadTree) -> Result<(), Error>) -> Result<(), Error> {
+        let mut buffered = Vec::new();
+        for rect in rects.by_ref() {
+            buffered.push(rect?);
+            if buffered.len() > self.max_in_memory && self.config.can_split(&cell, depth) {
+                break;
+            }
+        }
+
+        if buffered.len() <= self.max_in_memory || !self.config.can_split(&cell, depth) {
+            let mut tree = QuadTree::builder().bounds(cell).config(self.config).build();
+            for rect in buffered.into_iter() {
+                let _ = tree.try_insert_in_root(rect, 0);
+            }
+            return emit(cell, tree);
+        }
+
+        let quadrants = quadrants_of(&cell);
+        let paths: Vec<PathBuf> = (0..4).map(|q| self.spill_dir.join(format!("quadtree-spill-{}{}.bin", path, q))).collect();
+        let mut writers = Vec::new();
+        for spill_path in paths.iter() {
+            writers.push(BufWriter::new(File::create(spill_path)?));
+        }
+
+        for rect in buffered.into_iter().map(Ok).chain(rects) {
+            let rect = rect?;
+            // As in the tree, a point is only stored in the first quadrant holding it.
+            for (quadrant, writer) in quadrants.iter().zip(writers.iter_mut()) {
+                if quadrant.intersects(&rect) {
+                    write_record(writer, &rect)?;
+                    if rect.is_point() {
+                        break;
+                    }
+                }
+            }
+        }
+
+        for writer in writers.iter_mut() {
+            writer.flush()?;
+        }
+        drop(writers);
+
+        for (q, (quadrant, spill_path)) in quadrants.iter().zip(paths.iter()).enumerate() {
+            let reader = SpillReader { reader: BufReader::new(File::open(spill_path)?) };
+            let result = self.build_cell(*quadrant, &format!("{}{}", path, q), depth + 1, reader, emit);
+            fs::remove_file(spill_path)?;
+            result?;
+        }
+
+        Ok(())
+    }
+}
+
+/**
+ The top left, top right, bottom right, and bottom left quarters of `rect`.
+ */
+fn quadrants_of(rect: &Rect) -> [Rect; 4] {
+    let size = rect.size / 2.;
+    let at = |x: f64, y: f64| Rect::new(Point::new(rect.origin.x + x * size.width, rect.origin.y + y * size.height), size);
+
+    [at(0., 0.), at(1., 0.), at(1., 1.), at(0., 1.)]
+}
+
+fn write_record(writer: &mut impl Write, rect: &Rect) -> Result<(), Error> {
+    for value in [rect.origin.x, rect.origin.y, rect.size.width, rect.size.height].iter() {
+        writer.write_all(&value.to_le_bytes())?;
+    }
+
+    Ok(())
+}
+
+/**
+ Reads back the rects written to a spill file.
+ */
+struct SpillReader {
+    reader: BufReader<File>,
+}
+
+impl Iterator for SpillReader {
+    type Item = Result<Rect, Error>;
+
+    fn next(&mut self) -> Option<Result<Rect, Error>> {
+        let mut record = [0; RECORD_LEN];
+        let mut filled = 0;
+        while filled < RECORD_LEN {
+            match self.reader.read(&mut record[filled..]) {
+                Ok(0) if filled == 0 => return None,
+                Ok(0) => return Some(Err(Error::Serialization("spill file ends mid-record".to_string()))),
+                Ok(count) => filled += count,
+                Err(err) => return Some(Err(err.into())),
+            }
+        }
+
+        let value = |i: usize| {
+            let mut word = [0; 8];
+            word.copy_from_slice(&record[i * 8..i * 8 + 8]);
+            f64::from_le_bytes(word)
+        };
+        Some(Ok(Rect::new(Point::new(value(0), value(1)), Size::new(value(2), value(3)))))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::geometry::{Point, Rect, Size};
+    use super::ExternalBuilder;
+
+    use std::env;
+    use std::fs;
+
+    #[test]
+    fn test_external_build_covers_every_rect() {
+        let bounds = Rect::new(Point::new(0., 0.), Size::new(64., 64.));
+        let mut rects = Vec::new();
+        for i in 0..20 {
+            for j in 0..20 {
+                rects.push(Rect::new(Point::new(i as f64 * 3., j as f64 * 3.), Size::new(2.5, 2.5)));
+            }
+        }
+        rects.push(Rect::new(Point::new(32., 32.), Size::new(0., 0.)));
+
+        let spill_dir = env::temp_dir().join(format!("quadtree-external-test-{}", std::process::id()));
+        let mut cells = Vec::new();
+        let mut found: Vec<Rect> = Vec::new();
+        ExternalBuilder::new(bounds, &spill_dir).max_in_memory(50).build(rects.iter().copied(), |cell, tree| {
+            assert!(tree.validate().is_ok());
+            for rect in tree.members().into_iter() {
+                if !found.contains(&rect) {
+                    found.push(rect);
+                }
+            }
+            cells.push(cell);
+            Ok(())
+        }).unwrap();
+
+        assert!(cells.len() > 4);
+        assert_eq!(cells.iter().map(|cell| cell.width() * cell.height()).sum::<f64>(), 64. * 64.);
+        assert_eq!(found.len(), rects.len());
+        assert!(fs::read_dir(&spill_dir).unwrap().next().is_none());
+        fs::remove_dir(&spill_dir).unwrap();
+    }
+}
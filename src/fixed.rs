@@ -0,0 +1,408 @@
+use crate::error::Error;
+use crate::geometry::Point;
+use crate::geometry::Rect;
+use crate::geometry::Size;
+
+/// Marks the end of a leaf's list of items, or a leaf with no children.
+const NONE: usize = usize::MAX;
+
+#[derive(Clone, Copy, Debug)]
+struct Node {
+    rect: Rect,
+    depth: usize,
+    /// The index of the first of this node's four children, stored consecutively in
+    /// `Quadrant::ALL` order, or `NONE` for a leaf.
+    first_child: usize,
+    /// The first slot of a leaf's list of items.
+    first_item: usize,
+    /// How many items a leaf holds.
+    len: usize,
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Slot {
+    rect: Rect,
+    /// The next slot in the same leaf's list, or in the free list.
+    next: usize,
+}
+
+const EMPTY_RECT: Rect = Rect { origin: Point { x: 0., y: 0. }, size: Size { width: 0., height: 0. } };
+
+/**
+ A quadtree backed by fixed arrays of `NODES` nodes and `ITEMS` item slots, which never
+ allocates, e.g. for embedded or real-time code where heap allocation is prohibited.
+ A member spanning several leaves takes a slot in each of them.
+
+ Unlike `QuadTree`, the root never grows, members may overlap, and nodes aren't
+ collapsed as members are removed; `clear` frees them all. A leaf that can't get four
+ more nodes, or enough slots to split its members, simply grows past its capacity.
+ */
+#[derive(Clone, Debug)]
+pub struct StaticQuadTree<const NODES: usize, const ITEMS: usize> {
+    nodes: [Node; NODES],
+    node_count: usize,
+    slots: [Slot; ITEMS],
+    free_slot: usize,
+    free_slot_count: usize,
+    len: usize,
+    leaf_capacity: usize,
+    max_depth: usize,
+}
+
+impl<const NODES: usize, const ITEMS: usize> StaticQuadTree<NODES, ITEMS> {
+    /**
+     Create an empty tree covering `bounds`, splitting leaves holding more than
+     `leaf_capacity` members until they are `max_depth` levels below the root.
+     */
+    pub fn new(bounds: Rect, leaf_capacity: usize, max_depth: usize) -> StaticQuadTree<NODES, ITEMS> {
+        assert!(NODES > 0, "A StaticQuadTree needs room for at least its root node.");
+
+        let mut tree = StaticQuadTree {
+            nodes: [Node { rect: EMPTY_RECT, depth: 0, first_child: NONE, first_item: NONE, len: 0 }; NODES],
+            node_count: 0,
+            slots: [Slot { rect: EMPTY_RECT, next: NONE }; ITEMS],
+            free_slot: NONE,
+            free_slot_count: 0,
+            len: 0,
+            leaf_capacity: leaf_capacity.max(1),
+            max_depth,
+        };
+        tree.nodes[0].rect = bounds;
+        tree.clear();
+
+        tree
+    }
+
+    /// Get the bounds of the root.
+    pub fn bounds(&self) -> Rect {
+        self.nodes[0].rect
+    }
+
+    /// Count the members.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Check if the tree has no members.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /**
+     Remove every member and every node but the root.
+     */
+    pub fn clear(&mut self) {
+        let root = &mut self.nodes[0];
+        *root = Node { rect: root.rect, depth: 0, first_child: NONE, first_item: NONE, len: 0 };
+        self.node_count = 1;
+
+        for (i, slot) in self.slots.iter_mut().enumerate() {
+            slot.next = if i + 1 < ITEMS { i + 1 } else { NONE };
+        }
+        self.free_slot = if ITEMS > 0 { 0 } else { NONE };
+        self.free_slot_count = ITEMS;
+        self.len = 0;
+    }
+
+    /**
+     Insert `rect`. Fails with `InvalidRect` if it isn't valid, `CannotGrow` if it isn't
+     inside the root, `Duplicate` if it is already a member, and `Full` if there aren't
+     enough free slots for it. A failed insert leaves the tree unchanged.
+     */
+    pub fn insert(&mut self, rect: Rect) -> Result<(), Error> {
+        if !rect.is_valid() {
+            return Err(Error::InvalidRect(rect))
+        }
+        if !self.bounds().contains(&rect) {
+            return Err(Error::CannotGrow(rect))
+        }
+        if self.contains(&rect) {
+            return Err(Error::Duplicate(rect))
+        }
+
+        let mut leaves = [NONE; NODES];
+        let leaf_count = self.leaves_intersecting(&rect, &mut leaves);
+        if leaf_count > self.free_slot_count {
+            return Err(Error::Full)
+        }
+
+        // Every leaf gets its slot before any splits, as splitting can use up free slots.
+        for &leaf in leaves[..leaf_count].iter() {
+            self.push_item(leaf, rect);
+        }
+        for &leaf in leaves[..leaf_count].iter() {
+            self.split_if_needed(leaf);
+        }
+        self.len += 1;
+
+        Ok(())
+    }
+
+    /**
+     Remove the member `rect`, returning false if it wasn't a member.
+     */
+    pub fn remove(&mut self, rect: &Rect) -> bool {
+        let mut leaves = [NONE; NODES];
+        let leaf_count = self.leaves_intersecting(rect, &mut leaves);
+        let mut removed = false;
+
+        for &leaf in leaves[..leaf_count].iter() {
+            let mut previous = NONE;
+            let mut slot = self.nodes[leaf].first_item;
+            while slot != NONE {
+                let next = self.slots[slot].next;
+                if self.slots[slot].rect == *rect {
+                    if previous == NONE {
+                        self.nodes[leaf].first_item = next;
+                    } else {
+                        self.slots[previous].next = next;
+                    }
+                    self.free(slot);
+                    self.nodes[leaf].len -= 1;
+                    removed = true;
+                    break;
+                }
+                previous = slot;
+                slot = next;
+            }
+        }
+
+        if removed {
+            self.len -= 1;
+        }
+        removed
+    }
+
+    /**
+     Check if `rect` is a member.
+     */
+    pub fn contains(&self, rect: &Rect) -> bool {
+        let mut found = false;
+        self.query_each(rect, |member| found |= member == rect);
+        found
+    }
+
+    /**
+     Call `visit` with each member intersecting `region`, once each.
+     */
+    pub fn query_each(&self, region: &Rect, mut visit: impl FnMut(&Rect)) {
+        let root = self.bounds();
+        if root.intersects(region) {
+            self.query_each_in(0, &root, region, &mut visit);
+        }
+    }
+
+    fn query_each_in(&self, node: usize, root: &Rect, region: &Rect, visit: &mut impl FnMut(&Rect)) {
+        let node = &self.nodes[node];
+        if node.first_child != NONE {
+            for child in node.first_child..node.first_child + 4 {
+                if self.nodes[child].rect.intersects(region) {
+                    self.query_each_in(child, root, region, visit);
+                }
+            }
+            return;
+        }
+
+        let mut slot = node.first_item;
+        while slot != NONE {
+            let rect = &self.slots[slot].rect;
+            // A member in several leaves is visited from the leaf holding the corner of
+            // its part of the region, counting each leaf's far edges as its neighbor's.
+            if rect.intersects(region) {
+                let corner = Point::new(rect.min_x().max(region.min_x()), rect.min_y().max(region.min_y()));
+                let owns_x = corner.x < node.rect.max_x() || node.rect.max_x() == root.max_x();
+                let owns_y = corner.y < node.rect.max_y() || node.rect.max_y() == root.max_y();
+                if owns_x && owns_y && node.rect.contains_point(&corner) {
+                    visit(rect);
+                }
+            }
+            slot = self.slots[slot].next;
+        }
+    }
+
+    /**
+     Write the indexes of the leaves intersecting `rect` to `leaves`, returning how many
+     there are.
+     */
+    fn leaves_intersecting(&self, rect: &Rect, leaves: &mut [usize; NODES]) -> usize {
+        let mut count = 0;
+        if self.bounds().intersects(rect) {
+            self.push_leaves_intersecting(0, rect, leaves, &mut count);
+        }
+        count
+    }
+
+    fn push_leaves_intersecting(&self, node: usize, rect: &Rect, leaves: &mut [usize; NODES], count: &mut usize) {
+        let first_child = self.nodes[node].first_child;
+        if first_child == NONE {
+            leaves[*count] = node;
+            *count += 1;
+            return;
+        }
+
+        for child in first_child..first_child + 4 {
+            if self.nodes[child].rect.intersects(rect) {
+                self.push_leaves_intersecting(child, rect, leaves, count);
+            }
+        }
+    }
+
+    /**
+     Split `leaf` into four children if it holds too many members, there are free nodes,
+     and there are enough free slots to store each of its members in every child it
+     intersects.
+     */
+    fn split_if_needed(&mut self, leaf: usize) {
+        let node = self.nodes[leaf];
+        if node.len <= self.leaf_capacity || node.depth >= self.max_depth || self.node_count + 4 > NODES {
+            return;
+        }
+
+        let child_rects = child_rects(&node.rect);
+        let mut needed = 0;
+        let mut slot = node.first_item;
+        while slot != NONE {
+            needed += child_rects.iter().filter(|child| child.intersects(&self.slots[slot].rect)).count();
+            slot = self.slots[slot].next;
+        }
+        // The leaf's own slots are freed as its members move to the children.
+        if needed > self.free_slot_count + node.len {
+            return;
+        }
+
+        let first_child = self.node_count;
+        for (i, child_rect) in child_rects.iter().enumerate() {
+            self.nodes[first_child + i] = Node { rect: *child_rect, depth: node.depth + 1, first_child: NONE, first_item: NONE, len: 0 };
+        }
+        self.node_count += 4;
+        self.nodes[leaf] = Node { first_child, first_item: NONE, len: 0, ..node };
+
+        let mut slot = node.first_item;
+        while slot != NONE {
+            let next = self.slots[slot].next;
+            let rect = self.slots[slot].rect;
+            self.free(slot);
+            for child in first_child..first_child + 4 {
+                if self.nodes[child].rect.intersects(&rect) {
+                    self.push_item(child, rect);
+                }
+            }
+            slot = next;
+        }
+    }
+
+    /// Add `rect` to the front of `leaf`'s list. Assumes there is a free slot.
+    fn push_item(&mut self, leaf: usize, rect: Rect) {
+        let slot = self.free_slot;
+        self.free_slot = self.slots[slot].next;
+        self.free_slot_count -= 1;
+
+        self.slots[slot] = Slot { rect, next: self.nodes[leaf].first_item };
+        self.nodes[leaf].first_item = slot;
+        self.nodes[leaf].len += 1;
+    }
+
+    fn free(&mut self, slot: usize) {
+        self.slots[slot].next = self.free_slot;
+        self.free_slot = slot;
+        self.free_slot_count += 1;
+    }
+}
+
+/// The bounds of the children of a node with bounds `rect`, in `Quadrant::ALL` order.
+fn child_rects(rect: &Rect) -> [Rect; 4] {
+    let size = rect.size.half();
+    let origin = rect.origin;
+
+    [Rect::new(origin, size),
+     Rect::new(Point::new(origin.x + size.width, origin.y), size),
+     Rect::new(Point::new(origin.x + size.width, origin.y + size.height), size),
+     Rect::new(Point::new(origin.x, origin.y + size.height), size)]
+}
+
+#[cfg(test)]
+mod test {
+    use crate::error::Error;
+    use crate::geometry::{Point, Rect, Size};
+    use super::StaticQuadTree;
+
+    fn rect(x: f64, y: f64, width: f64, height: f64) -> Rect {
+        Rect::new(Point::new(x, y), Size::new(width, height))
+    }
+
+    #[test]
+    fn test_static_quadtree() {
+        let mut tree: StaticQuadTree<64, 256> = StaticQuadTree::new(rect(0., 0., 64., 64.), 4, 4);
+        let mut members = Vec::new();
+        for i in 0..8 {
+            for j in 0..8 {
+                let member = rect(i as f64 * 8., j as f64 * 8., 4. + (i % 3) as f64 * 4., 4.);
+                tree.insert(member).unwrap();
+                members.push(member);
+            }
+        }
+
+        assert_eq!(tree.len(), 64);
+        assert!(matches!(tree.insert(members[0]), Err(Error::Duplicate(_))));
+        assert!(matches!(tree.insert(rect(60., 60., 8., 8.)), Err(Error::CannotGrow(_))));
+
+        for region in [rect(0., 0., 64., 64.), rect(10., 10., 20., 5.), rect(8., 8., 0., 0.), rect(63., 0., 1., 64.)] {
+            let mut found = Vec::new();
+            tree.query_each(&region, |member| found.push(*member));
+            let expected: Vec<&Rect> = members.iter().filter(|member| member.intersects(&region)).collect();
+            assert_eq!(found.len(), expected.len());
+            assert!(expected.iter().all(|member| found.contains(member)));
+        }
+
+        assert!(tree.remove(&members[9]));
+        assert!(!tree.remove(&members[9]));
+        assert!(!tree.contains(&members[9]));
+        assert_eq!(tree.len(), 63);
+
+        // A tiny tree runs out of slots rather than allocating.
+        let mut tiny: StaticQuadTree<1, 2> = StaticQuadTree::new(rect(0., 0., 8., 8.), 1, 4);
+        tiny.insert(rect(0., 0., 1., 1.)).unwrap();
+        tiny.insert(rect(2., 2., 1., 1.)).unwrap();
+        assert!(matches!(tiny.insert(rect(4., 4., 1., 1.)), Err(Error::Full)));
+        tiny.clear();
+        assert!(tiny.is_empty());
+        assert!(tiny.insert(rect(4., 4., 1., 1.)).is_ok());
+    }
+
+    #[test]
+    fn test_static_quadtree_fills_without_panicking() {
+        let mut tree: StaticQuadTree<32, 12> = StaticQuadTree::new(rect(0., 0., 64., 64.), 1, 6);
+        let members = [rect(42., 13., 13., 12.), rect(2., 56., 14., 8.), rect(55., 58., 9., 6.), rect(33., 25., 0., 4.), rect(17., 30., 36., 22.)];
+        for member in members {
+            tree.insert(member).unwrap();
+        }
+        // The last member takes the slots the leaves it spans would need to split.
+        assert_eq!(tree.len(), 5);
+        assert!(members.iter().all(|member| tree.contains(member)));
+
+        // A small xorshift generator, so the test is repeatable without a dependency.
+        let mut state = 2463534242u32;
+        let mut next = |high: u32| {
+            state ^= state << 13;
+            state ^= state >> 17;
+            state ^= state << 5;
+            state % high
+        };
+        for _ in 0..200 {
+            let mut tree: StaticQuadTree<32, 12> = StaticQuadTree::new(rect(0., 0., 64., 64.), 1, 6);
+            let mut members = Vec::new();
+            loop {
+                let (x, y) = (next(64), next(64));
+                let member = rect(x as f64, y as f64, next(65 - x) as f64, next(65 - y) as f64);
+                match tree.insert(member) {
+                    Ok(()) => members.push(member),
+                    Err(Error::Full) => break,
+                    Err(_) => (),
+                }
+            }
+
+            assert_eq!(tree.len(), members.len());
+            assert!(members.iter().all(|member| tree.contains(member)));
+        }
+    }
+}
@@ -0,0 +1,150 @@
+use crate::quadtree::QuadTree;
+use crate::split::SplitPolicy;
+
+/**
+ A node of a `FlatTree`, laid out to match a std430 struct of eight 32-bit fields:
+
+ `struct Node { vec4 bounds; uint first_child; uint first_item; uint item_count; uint pad; };`
+
+ `bounds` holds min x, min y, max x, and max y. The children of an inner node are
+ stored consecutively from `first_child` in top left, top right, bottom right, bottom
+ left order. A leaf has a `first_child` of 0, since the root is never a child, and its
+ members are `item_count` items starting at `first_item`.
+ */
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct GpuNode {
+    pub min_x: f32,
+    pub min_y: f32,
+    pub max_x: f32,
+    pub max_y: f32,
+    pub first_child: u32,
+    pub first_item: u32,
+    pub item_count: u32,
+    pub pad: u32,
+}
+
+/**
+ A member of a `FlatTree`, laid out to match a std430 struct of eight 32-bit fields:
+
+ `struct Item { vec4 bounds; int priority; uint pad[3]; };`
+ */
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct GpuItem {
+    pub min_x: f32,
+    pub min_y: f32,
+    pub max_x: f32,
+    pub max_y: f32,
+    pub priority: i32,
+    pub pad: [u32; 3],
+}
+
+/**
+ A quadtree flattened into two index-linked arrays, ready to be uploaded to storage
+ buffers for traversal in a compute shader. The root is `nodes[0]`. A member spanning
+ several leaves appears in the item range of each of them.
+ */
+#[derive(Clone, Debug, PartialEq)]
+pub struct FlatTree {
+    pub nodes: Vec<GpuNode>,
+    pub items: Vec<GpuItem>,
+}
+
+impl FlatTree {
+    /**
+     Flatten `tree` breadth first, so siblings are adjacent and nodes near the root,
+     which every traversal reads, are packed together at the start.
+     */
+    pub fn new<T, P: SplitPolicy>(tree: &QuadTree<T, P>) -> FlatTree {
+        let mut nodes = Vec::new();
+        let mut items = Vec::new();
+        let mut nodes_to_flatten = vec![tree.root()];
+        let mut next = 0;
+
+        while next < nodes_to_flatten.len() {
+            let node = nodes_to_flatten[next];
+            next += 1;
+
+            let bounds = node.bounds();
+            let mut flat_node = GpuNode {
+                min_x: bounds.min_x() as f32,
+                min_y: bounds.min_y() as f32,
+                max_x: bounds.max_x() as f32,
+                max_y: bounds.max_y() as f32,
+                first_child: 0,
+                first_item: items.len() as u32,
+                item_count: 0,
+                pad: 0,
+            };
+
+            match node.children() {
+                Some(children) => {
+                    flat_node.first_child = nodes_to_flatten.len() as u32;
+                    nodes_to_flatten.extend(children);
+                },
+                None => {
+                    let members = node.leaf_items();
+                    for item in members.iter() {
+                        items.push(GpuItem {
+                            min_x: item.rect.min_x() as f32,
+                            min_y: item.rect.min_y() as f32,
+                            max_x: item.rect.max_x() as f32,
+                            max_y: item.rect.max_y() as f32,
+                            priority: item.priority,
+                            pad: [0; 3],
+                        });
+                    }
+                    flat_node.item_count = members.len() as u32;
+                },
+            }
+
+            nodes.push(flat_node);
+        }
+
+        FlatTree { nodes, items }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::geometry::Point;
+    use crate::geometry::Rect;
+    use crate::geometry::Size;
+    use crate::quadtree::QuadTree;
+    use super::FlatTree;
+
+    fn rect(x: f64, y: f64, width: f64, height: f64) -> Rect {
+        Rect::new(Point::new(x, y), Size::new(width, height))
+    }
+
+    #[test]
+    fn test_flatten_links_children_and_items() {
+        let mut tree = QuadTree::builder().bounds(rect(0., 0., 64., 64.)).leaf_capacity(1).allow_overlap(true).build();
+        for corner in [rect(1., 1., 1., 1.), rect(61., 1., 1., 1.), rect(61., 61., 1., 1.), rect(1., 61., 1., 1.)] {
+            tree.try_insert(corner, 0).unwrap();
+        }
+        tree.try_insert(rect(0., 0., 64., 64.), 3).unwrap();
+
+        let flat = FlatTree::new(&tree);
+        assert_eq!(flat.nodes.len(), 5);
+        assert_eq!((flat.nodes[0].first_child, flat.nodes[0].item_count), (1, 0));
+        assert_eq!((flat.nodes[0].max_x, flat.nodes[0].max_y), (64., 64.));
+
+        // Each leaf holds its corner and a copy of the spanning member.
+        for (i, node) in flat.nodes[1..].iter().enumerate() {
+            assert_eq!((node.first_child, node.first_item, node.item_count), (0, 2 * i as u32, 2));
+        }
+        assert_eq!(flat.items.len(), 8);
+        assert_eq!(flat.items.iter().filter(|item| item.priority == 3).count(), 4);
+    }
+
+    #[test]
+    fn test_flatten_empty_tree() {
+        let flat = QuadTree::builder().bounds(rect(0., 0., 64., 64.)).build().flatten();
+
+        assert_eq!(flat.nodes.len(), 1);
+        assert_eq!((flat.nodes[0].first_child, flat.nodes[0].item_count), (0, 0));
+        assert!(flat.items.is_empty());
+    }
+}
@@ -0,0 +1,96 @@
+/*!
+ Geohash encoding of points, for exchanging locations with systems that index them
+ as geohash strings.
+
+ A point's `x` is its longitude and `y` its latitude, both in degrees. A geohash names
+ a cell of the longitude and latitude grid, and longer hashes name smaller cells
+ within it, so every point in a cell has a hash starting with the cell's hash.
+ */
+
+use crate::error::Error;
+use crate::geometry::Point;
+use crate::geometry::Rect;
+use crate::geometry::Size;
+
+/// The geohash alphabet, giving the character for each 5 bit value.
+const BASE32: &[u8; 32] = b"0123456789bcdefghjkmnpqrstuvwxyz";
+
+/**
+ Encode `point` as a geohash `precision` characters long. Coordinates outside the
+ valid longitude and latitude ranges are clamped to them.
+ */
+pub fn encode(point: Point, precision: usize) -> String {
+    let mut lon = (-180., 180.);
+    let mut lat = (-90., 90.);
+    let x = point.x.clamp(-180., 180.);
+    let y = point.y.clamp(-90., 90.);
+    let mut hash = String::with_capacity(precision);
+    let mut is_lon = true;
+
+    for _ in 0..precision {
+        let mut index = 0;
+        for _ in 0..5 {
+            let (range, value) = if is_lon { (&mut lon, x) } else { (&mut lat, y) };
+            let mid = (range.0 + range.1) / 2.;
+            index <<= 1;
+            if value >= mid {
+                index |= 1;
+                range.0 = mid;
+            } else {
+                range.1 = mid;
+            }
+            is_lon = !is_lon;
+        }
+        hash.push(BASE32[index] as char);
+    }
+
+    hash
+}
+
+/**
+ Decode `hash` to the cell it names, with longitudes along `x` and latitudes along `y`.
+ An empty hash names the whole grid. Fails with `Serialization` if `hash` has a
+ character outside the geohash alphabet.
+ */
+pub fn decode(hash: &str) -> Result<Rect, Error> {
+    let mut lon = (-180., 180.);
+    let mut lat = (-90., 90.);
+    let mut is_lon = true;
+
+    for c in hash.chars() {
+        let index = BASE32.iter().position(|&b| b as char == c.to_ascii_lowercase())
+            .ok_or_else(|| Error::Serialization(format!("invalid geohash character {:?}", c)))?;
+
+        for bit in (0..5).rev() {
+            let range = if is_lon { &mut lon } else { &mut lat };
+            let mid = (range.0 + range.1) / 2.;
+            if index & (1 << bit) != 0 {
+                range.0 = mid;
+            } else {
+                range.1 = mid;
+            }
+            is_lon = !is_lon;
+        }
+    }
+
+    Ok(Rect::new(Point::new(lon.0, lat.0), Size::new(lon.1 - lon.0, lat.1 - lat.0)))
+}
+
+#[cfg(test)]
+mod test {
+    use crate::geometry::Point;
+    use super::{decode, encode};
+
+    #[test]
+    fn test_encode_and_decode() {
+        // The example from the original geohash description.
+        let point = Point::new(-5.6, 42.6);
+        assert_eq!(encode(point, 5), "ezs42");
+
+        let cell = decode("ezs42").unwrap();
+        assert!(cell.contains_point(&point));
+        assert!(decode("ezs4").unwrap().contains(&cell));
+        assert_eq!(decode("EZS42").unwrap(), cell);
+        assert!(decode("ezs4a").is_err());
+    }
+}
@@ -0,0 +1,415 @@
+pub mod geohash;
+pub mod predicates;
+
+pub use self::predicates::Relation;
+
+use std::ops::{Div, Mul};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Point {
+    pub x: f64,
+    pub y: f64,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Size {
+    pub width: f64,
+    pub height: f64,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Rect {
+    pub origin: Point,
+    pub size: Size,
+}
+
+/// A half-line starting at `origin` and extending along `direction`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Ray {
+    pub origin: Point,
+    pub direction: Point,
+}
+
+impl Point {
+    pub fn new(x: f64, y: f64) -> Point {
+        Point { x, y }
+    }
+
+    pub fn add(&self, add_point: Point) -> Point {
+        Point::new(self.x + add_point.x, self.y + add_point.y)
+    }
+
+    pub fn subtract(&self, offset_point: Point) -> Point {
+        Point::new(self.x - offset_point.x, self.y - offset_point.y)
+    }
+
+    /// Interpolate linearly from this point at `t = 0` to `other` at `t = 1`.
+    pub fn lerp(&self, other: &Point, t: f64) -> Point {
+        Point::new(self.x + (other.x - self.x) * t, self.y + (other.y - self.y) * t)
+    }
+}
+
+impl Size {
+    pub fn new(width: f64, height: f64) -> Size {
+        Size { width, height }
+    }
+
+    /// Multiply both dimensions by `factor`.
+    pub fn scale(&self, factor: f64) -> Size {
+        Size::new(self.width * factor, self.height * factor)
+    }
+
+    /// Halve both dimensions, e.g. to get the size of a node's children.
+    pub fn half(&self) -> Size {
+        self.scale(0.5)
+    }
+
+    pub fn area(&self) -> f64 {
+        self.width * self.height
+    }
+}
+
+impl Mul<f64> for Size {
+    type Output = Size;
+
+    fn mul(self, factor: f64) -> Size {
+        self.scale(factor)
+    }
+}
+
+impl Div<f64> for Size {
+    type Output = Size;
+
+    fn div(self, divisor: f64) -> Size {
+        Size::new(self.width / divisor, self.height / divisor)
+    }
+}
+
+/// The straight line segment from `start` to `end`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Segment {
+    pub start: Point,
+    pub end: Point,
+}
+
+impl Segment {
+    pub fn new(start: Point, end: Point) -> Segment {
+        Segment { start, end }
+    }
+
+    /// Find the point at parameter `t`, from `start` at `t = 0` to `end` at `t = 1`.
+    pub fn point_at(&self, t: f64) -> Point {
+        self.start.lerp(&self.end, t)
+    }
+}
+
+impl Ray {
+    pub fn new(origin: Point, direction: Point) -> Ray {
+        Ray { origin, direction }
+    }
+
+    /// Find the point at parameter `t` along the ray.
+    pub fn point_at(&self, t: f64) -> Point {
+        Point::new(self.origin.x + self.direction.x * t, self.origin.y + self.direction.y * t)
+    }
+}
+
+/**
+ Find the range of `t` for which `origin + t * direction` lies within `[min, max]` along one axis.
+ */
+fn slab_interval(origin: f64, direction: f64, min: f64, max: f64) -> Option<(f64, f64)> {
+    if direction == 0. {
+        if origin >= min && origin <= max {
+            Some((f64::NEG_INFINITY, f64::INFINITY))
+        } else {
+            None
+        }
+    } else {
+        let t1 = (min - origin) / direction;
+        let t2 = (max - origin) / direction;
+        Some((t1.min(t2), t1.max(t2)))
+    }
+}
+
+impl Rect {
+    pub fn new(origin: Point, size: Size) -> Rect {
+        Rect { origin, size }
+    }
+
+    /// Find which the rect has an origin farther to the left.
+    pub fn min_x_rect<'a>(rect1: &'a Rect, rect2: &'a Rect) -> (&'a Rect, &'a Rect) {
+        if rect1.min_x() <= rect2.min_x() {
+            (rect1, rect2)
+        } else {
+            (rect2, rect1)
+        }
+    }
+
+    /// Find which the rect has an origin with a lower y value.
+    pub fn min_y_rect<'a>(rect1: &'a Rect, rect2: &'a Rect) -> (&'a Rect, &'a Rect) {
+        if rect1.min_y() <= rect2.min_y() {
+            (rect1, rect2)
+        } else {
+            (rect2, rect1)
+        }
+    }
+
+    /**
+     Check if this rect entirely contains another rect.
+     */
+    pub fn contains(&self, rect: &Rect) -> bool {
+        predicates::contains(self, rect)
+    }
+
+    /**
+     Check if this rect and another rect intersect.
+     */
+    pub fn intersects(&self, rect: &Rect) -> bool {
+        predicates::intersects(self, rect)
+    }
+
+    /**
+     Check if the interiors of this rect and another rect overlap.
+     */
+    pub fn overlaps(&self, rect: &Rect) -> bool {
+        predicates::overlaps(self, rect)
+    }
+
+    /**
+     Check if this rect shares part of its boundary with another rect without their
+     interiors meeting, as `relate` giving `Relation::Touches`.
+     */
+    pub fn touches(&self, rect: &Rect) -> bool {
+        predicates::touches(self, rect)
+    }
+
+    /**
+     Find how this rect is related to another rect, e.g. `Relation::Within` if it lies
+     inside the other rect.
+     */
+    pub fn relate(&self, rect: &Rect) -> Relation {
+        predicates::relate(self, rect)
+    }
+
+    /**
+     Check if this rect contains a point.
+     */
+    pub fn contains_point(&self, point: &Point) -> bool {
+        predicates::contains_point(self, point)
+    }
+
+    /**
+     Check if the rect has no interior, i.e. zero or negative width or height.
+     */
+    pub fn is_empty(&self) -> bool {
+        !(self.size.width > 0. && self.size.height > 0.)
+    }
+
+    /**
+     Check if the rect is valid but has zero width or height, making it a point or
+     a line segment.
+     */
+    pub fn is_degenerate(&self) -> bool {
+        self.is_valid() && (self.size.width == 0. || self.size.height == 0.)
+    }
+
+    /**
+     Check if the rect is valid and has zero width and height, making it a point.
+     */
+    pub fn is_point(&self) -> bool {
+        self.is_valid() && self.size.width == 0. && self.size.height == 0.
+    }
+
+    /**
+     Check if the rect has a finite origin and a finite, non-negative size.
+     */
+    pub fn is_valid(&self) -> bool {
+        self.origin.x.is_finite() && self.origin.y.is_finite()
+        && self.size.width.is_finite() && self.size.height.is_finite()
+        && self.size.width >= 0. && self.size.height >= 0.
+    }
+
+    /**
+     Get the intersection with another rect.
+     */
+    pub fn intersect(&self, rect: &Rect) -> Option<Rect> {
+        if !self.intersects(rect) {
+            return None;
+        }
+
+        let (min_x_rect, other_x_rect) = Rect::min_x_rect(self, rect);
+        let (min_y_rect, other_y_rect) = Rect::min_y_rect(self, rect);
+        let common_x_start = other_x_rect.min_x();
+        let common_y_start = other_y_rect.min_y();
+
+        let common_x_end = min_x_rect.max_x().min(other_x_rect.max_x());
+        let common_y_end = min_y_rect.max_y().min(other_y_rect.max_y());
+
+        let width = common_x_end - common_x_start;
+        let height = common_y_end - common_y_start;
+
+        Some(Rect::new(Point::new(common_x_start, common_y_start), Size::new(width, height)))
+    }
+
+    /**
+     Get this rect moved by `delta`, keeping its size.
+     */
+    pub fn translate(&self, delta: Point) -> Rect {
+        Rect::new(self.origin.add(delta), self.size)
+    }
+
+    /**
+     Get the smallest rect containing both this rect and another rect.
+     */
+    pub fn union(&self, rect: &Rect) -> Rect {
+        let min_x = self.min_x().min(rect.min_x());
+        let min_y = self.min_y().min(rect.min_y());
+        let max_x = self.max_x().max(rect.max_x());
+        let max_y = self.max_y().max(rect.max_y());
+
+        Rect::new(Point::new(min_x, min_y), Size::new(max_x - min_x, max_y - min_y))
+    }
+
+    /**
+     Grow this rect by `margin` on every side. A negative margin shrinks it.
+     */
+    pub fn inflate(&self, margin: f64) -> Rect {
+        Rect::new(Point::new(self.origin.x - margin, self.origin.y - margin),
+                  Size::new(self.size.width + margin * 2., self.size.height + margin * 2.))
+    }
+
+    /**
+     Find the shortest distance between any point of this rect and any point of
+     another rect. Rects that intersect are at distance zero.
+     */
+    pub fn distance_to(&self, rect: &Rect) -> f64 {
+        let dx = (self.min_x() - rect.max_x()).max(rect.min_x() - self.max_x()).max(0.);
+        let dy = (self.min_y() - rect.max_y()).max(rect.min_y() - self.max_y()).max(0.);
+
+        (dx * dx + dy * dy).sqrt()
+    }
+
+    /**
+     Find the parameters at which `ray` enters and exits this rect, if it hits it at all.
+     Only the part of the ray at or after its origin is considered, so a ray starting
+     inside the rect enters it at `t = 0`.
+     */
+    pub fn ray_intersection(&self, ray: &Ray) -> Option<(f64, f64)> {
+        let x_interval = slab_interval(ray.origin.x, ray.direction.x, self.min_x(), self.max_x());
+        let y_interval = slab_interval(ray.origin.y, ray.direction.y, self.min_y(), self.max_y());
+
+        match (x_interval, y_interval) {
+            (Some((x_enter, x_exit)), Some((y_enter, y_exit))) => {
+                let enter = x_enter.max(y_enter).max(0.);
+                let exit = x_exit.min(y_exit);
+
+                if enter <= exit {
+                    Some((enter, exit))
+                } else {
+                    None
+                }
+            },
+            _ => None,
+        }
+    }
+
+    /**
+     Find the parameters at which `segment` enters and exits this rect, if it passes
+     through it at all. Parameters run from 0 at the segment's start to 1 at its end,
+     so a segment starting inside the rect enters it at `t = 0`.
+     */
+    pub fn clip_segment(&self, segment: &Segment) -> Option<(f64, f64)> {
+        let direction = segment.end.subtract(segment.start);
+        let x_interval = slab_interval(segment.start.x, direction.x, self.min_x(), self.max_x());
+        let y_interval = slab_interval(segment.start.y, direction.y, self.min_y(), self.max_y());
+
+        match (x_interval, y_interval) {
+            (Some((x_enter, x_exit)), Some((y_enter, y_exit))) => {
+                let enter = x_enter.max(y_enter).max(0.);
+                let exit = x_exit.min(y_exit).min(1.);
+
+                if enter <= exit {
+                    Some((enter, exit))
+                } else {
+                    None
+                }
+            },
+            _ => None,
+        }
+    }
+
+    /**
+     Interpolate the origin and size linearly from this rect at `t = 0` to `other` at `t = 1`,
+     e.g. to animate between two layouts.
+     */
+    pub fn lerp(&self, other: &Rect, t: f64) -> Rect {
+        Rect::new(self.origin.lerp(&other.origin, t),
+                  Size::new(self.size.width + (other.size.width - self.size.width) * t,
+                            self.size.height + (other.size.height - self.size.height) * t))
+    }
+
+    /**
+     Find the earliest `t` in `[0, 1]` at which `self.lerp(to, t)` intersects `rect`,
+     if it ever does.
+     */
+    pub fn lerp_contact(&self, to: &Rect, rect: &Rect) -> Option<f64> {
+        // Each edge moves linearly with t, so each of the four intersection conditions
+        // holds on an interval of t.
+        let conditions = [(self.min_x(), to.min_x() - self.min_x(), rect.max_x()),
+                          (-self.max_x(), self.max_x() - to.max_x(), -rect.min_x()),
+                          (self.min_y(), to.min_y() - self.min_y(), rect.max_y()),
+                          (-self.max_y(), self.max_y() - to.max_y(), -rect.min_y())];
+
+        let mut start = 0f64;
+        let mut end = 1f64;
+        for &(value, rate, limit) in conditions.iter() {
+            // value + rate * t <= limit
+            if rate == 0. {
+                if value > limit {
+                    return None
+                }
+            } else if rate > 0. {
+                end = end.min((limit - value) / rate);
+            } else {
+                start = start.max((limit - value) / rate);
+            }
+        }
+
+        if start <= end {
+            Some(start)
+        } else {
+            None
+        }
+    }
+
+    pub fn center(&self) -> Point {
+        Point::new(self.origin.x + self.size.width / 2., self.origin.y + self.size.height / 2.)
+    }
+
+    pub fn max_x(&self) -> f64 {
+        self.origin.x + self.size.width
+    }
+
+    pub fn max_y(&self) -> f64 {
+        self.origin.y + self.size.height
+    }
+
+    pub fn min_x(&self) -> f64 {
+        self.origin.x
+    }
+
+    pub fn min_y(&self) -> f64 {
+        self.origin.y
+    }
+
+    pub fn height(&self) -> f64 {
+        self.size.height
+    }
+
+    pub fn width(&self) -> f64 {
+        self.size.width
+    }
+}
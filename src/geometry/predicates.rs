@@ -0,0 +1,333 @@
+/*!
+ Intersection and containment predicates for axis-aligned rectangles.
+
+ All rects are treated as closed regions, so rects that only share an edge or a
+ corner intersect, and a rect contains itself. Degenerate rects (zero width or
+ height) are handled the same way as any other rect: a zero-sized rect is a point,
+ and it intersects every rect that covers that point.
+ */
+
+use crate::geometry::Point;
+use crate::geometry::Rect;
+
+/**
+ Check if the closed intervals `[a_min, a_max]` and `[b_min, b_max]` have any point in common.
+ */
+pub fn intervals_intersect<T: PartialOrd>(a_min: T, a_max: T, b_min: T, b_max: T) -> bool {
+    a_min <= b_max && b_min <= a_max
+}
+
+/**
+ Check if the closed interval `[outer_min, outer_max]` covers all of `[inner_min, inner_max]`.
+ */
+pub fn interval_contains<T: PartialOrd>(outer_min: T, outer_max: T, inner_min: T, inner_max: T) -> bool {
+    outer_min <= inner_min && inner_max <= outer_max
+}
+
+/**
+ Check if two rects have any point in common, including points on their edges.
+ */
+pub fn intersects(a: &Rect, b: &Rect) -> bool {
+    intervals_intersect(a.min_x(), a.max_x(), b.min_x(), b.max_x())
+    && intervals_intersect(a.min_y(), a.max_y(), b.min_y(), b.max_y())
+}
+
+/**
+ Check if the interiors of two rects overlap, i.e. they intersect in a region with
+ positive area. Rects that only touch along an edge or at a corner do not overlap,
+ and a degenerate rect never overlaps anything.
+ */
+pub fn overlaps(a: &Rect, b: &Rect) -> bool {
+    a.min_x().max(b.min_x()) < a.max_x().min(b.max_x())
+    && a.min_y().max(b.min_y()) < a.max_y().min(b.max_y())
+}
+
+/**
+ Check if two rects share part of their boundary without their interiors meeting,
+ e.g. neighboring tiles that meet along an edge or at a corner. The same as `relate`
+ giving `Relation::Touches`, so equal rects, and a point inside a segment, don't touch.
+ */
+pub fn touches(a: &Rect, b: &Rect) -> bool {
+    relate(a, b) == Relation::Touches
+}
+
+/**
+ Check if `outer` covers `inner` along the x axis, ignoring the y axis.
+ */
+pub fn contains_x(outer: &Rect, inner: &Rect) -> bool {
+    interval_contains(outer.min_x(), outer.max_x(), inner.min_x(), inner.max_x())
+}
+
+/**
+ Check if `outer` covers `inner` along the y axis, ignoring the x axis.
+ */
+pub fn contains_y(outer: &Rect, inner: &Rect) -> bool {
+    interval_contains(outer.min_y(), outer.max_y(), inner.min_y(), inner.max_y())
+}
+
+/**
+ Check if `outer` entirely contains `inner`. Shared edges are allowed.
+ */
+pub fn contains(outer: &Rect, inner: &Rect) -> bool {
+    contains_x(outer, inner) && contains_y(outer, inner)
+}
+
+/**
+ How two rects are related, in the spirit of the DE-9IM predicates used by GIS tools.
+ Exactly one relation holds for any pair of rects.
+ */
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Relation {
+    /// The rects have no point in common.
+    Disjoint,
+    /// The rects share boundary points, but their interiors don't meet.
+    Touches,
+    /// The interiors meet, but neither rect contains the other.
+    Overlaps,
+    /// The first rect contains the second, and their interiors meet.
+    Contains,
+    /// The first rect lies within the second, and their interiors meet.
+    Within,
+    /// The rects are the same.
+    Equals,
+}
+
+/**
+ Check if the interiors of the intervals `[a_min, a_max]` and `[b_min, b_max]` meet.
+ The interior of a zero-length interval is its single point.
+ */
+pub(crate) fn interval_interiors_meet<T: PartialOrd>(a_min: T, a_max: T, b_min: T, b_max: T) -> bool {
+    match (a_min < a_max, b_min < b_max) {
+        (true, true) => a_min < b_max && b_min < a_max,
+        (true, false) => a_min < b_min && b_min < a_max,
+        (false, true) => b_min < a_min && a_min < b_max,
+        (false, false) => a_min == b_min,
+    }
+}
+
+/**
+ Check if the interiors of two rects meet. Unlike `overlaps`, degenerate rects have
+ an interior too: a segment's is the segment without its ends, and a point's is the point.
+ */
+pub fn interiors_meet(a: &Rect, b: &Rect) -> bool {
+    interval_interiors_meet(a.min_x(), a.max_x(), b.min_x(), b.max_x())
+    && interval_interiors_meet(a.min_y(), a.max_y(), b.min_y(), b.max_y())
+}
+
+/**
+ Find how `a` is related to `b`. A rect contains another only if their interiors meet,
+ so e.g. a point on the boundary of a rect touches it rather than lying within it.
+ */
+pub fn relate(a: &Rect, b: &Rect) -> Relation {
+    if a == b {
+        Relation::Equals
+    } else if !intersects(a, b) {
+        Relation::Disjoint
+    } else if !interiors_meet(a, b) {
+        Relation::Touches
+    } else if contains(a, b) {
+        Relation::Contains
+    } else if contains(b, a) {
+        Relation::Within
+    } else {
+        Relation::Overlaps
+    }
+}
+
+/**
+ Check if `point` lies within `rect` or on its boundary.
+ */
+pub fn contains_point(rect: &Rect, point: &Point) -> bool {
+    interval_contains(rect.min_x(), rect.max_x(), point.x, point.x)
+    && interval_contains(rect.min_y(), rect.max_y(), point.y, point.y)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::geometry::Point;
+    use crate::geometry::Rect;
+    use crate::geometry::Size;
+    use super::{intervals_intersect, interval_contains, intersects, overlaps, touches};
+    use super::{contains_x, contains_y, contains, contains_point};
+    use super::{relate, Relation};
+
+    fn rect(x: f64, y: f64, width: f64, height: f64) -> Rect {
+        Rect::new(Point::new(x, y), Size::new(width, height))
+    }
+
+    /// The interval `[1, 3]` compared against intervals in every relative position.
+    #[test]
+    fn test_intervals() {
+        // Disjoint, before and after.
+        assert!(!intervals_intersect(1., 3., -1., 0.));
+        assert!(!intervals_intersect(1., 3., 4., 5.));
+        // Touching at either end.
+        assert!(intervals_intersect(1., 3., 0., 1.));
+        assert!(intervals_intersect(1., 3., 3., 4.));
+        // Partially overlapping.
+        assert!(intervals_intersect(1., 3., 0., 2.));
+        assert!(intervals_intersect(1., 3., 2., 4.));
+        // Nested either way, and equal.
+        assert!(intervals_intersect(1., 3., 1.5, 2.5));
+        assert!(intervals_intersect(1., 3., 0., 4.));
+        assert!(intervals_intersect(1., 3., 1., 3.));
+        // Degenerate intervals.
+        assert!(intervals_intersect(1., 3., 2., 2.));
+        assert!(intervals_intersect(1., 3., 3., 3.));
+        assert!(!intervals_intersect(1., 3., 3.5, 3.5));
+
+        assert!(interval_contains(1., 3., 1., 3.));
+        assert!(interval_contains(1., 3., 1.5, 2.5));
+        assert!(interval_contains(1., 3., 1., 1.));
+        assert!(!interval_contains(1., 3., 0., 2.));
+        assert!(!interval_contains(1., 3., 2., 4.));
+        assert!(!interval_contains(1., 3., 0., 4.));
+        assert!(!interval_contains(1., 3., 4., 5.));
+    }
+
+    /// Compare a fixed rect against rects placed at every combination of relative
+    /// positions along each axis, and check the predicates agree with the
+    /// per-axis interval tests.
+    #[test]
+    fn test_all_relative_positions() {
+        let base = rect(2., 2., 2., 2.);
+        // Intervals relative to [2, 4]: before, touching before, overlapping start,
+        // inside, equal, spanning, overlapping end, touching after, after, and points.
+        let intervals = [(0., 1.), (0., 2.), (1., 3.), (2.5, 3.5), (2., 4.), (1., 5.),
+                         (3., 5.), (4., 5.), (5., 6.), (3., 3.), (2., 2.), (6., 6.)];
+
+        for &(x_min, x_max) in intervals.iter() {
+            for &(y_min, y_max) in intervals.iter() {
+                let other = rect(x_min, y_min, x_max - x_min, y_max - y_min);
+
+                let x_intersects = intervals_intersect(2., 4., x_min, x_max);
+                let y_intersects = intervals_intersect(2., 4., y_min, y_max);
+                assert_eq!(intersects(&base, &other), x_intersects && y_intersects);
+                assert_eq!(intersects(&other, &base), x_intersects && y_intersects);
+
+                let x_overlaps = x_min.max(2.) < x_max.min(4.);
+                let y_overlaps = y_min.max(2.) < y_max.min(4.);
+                assert_eq!(overlaps(&base, &other), x_overlaps && y_overlaps);
+                assert_eq!(overlaps(&other, &base), x_overlaps && y_overlaps);
+
+                let x_contains = interval_contains(2., 4., x_min, x_max);
+                let y_contains = interval_contains(2., 4., y_min, y_max);
+                assert_eq!(contains_x(&base, &other), x_contains);
+                assert_eq!(contains_y(&base, &other), y_contains);
+                assert_eq!(contains(&base, &other), x_contains && y_contains);
+
+                let x_contained = interval_contains(x_min, x_max, 2., 4.);
+                let y_contained = interval_contains(y_min, y_max, 2., 4.);
+                assert_eq!(contains(&other, &base), x_contained && y_contained);
+            }
+        }
+    }
+
+    /// A tall rect crossing a wide one, neither containing the other's corners.
+    #[test]
+    fn test_crossing_rects() {
+        let tall = rect(2., 0., 1., 5.);
+        let wide = rect(0., 2., 5., 1.);
+
+        assert!(intersects(&tall, &wide));
+        assert!(overlaps(&tall, &wide));
+        assert!(!contains(&tall, &wide));
+        assert!(!contains(&wide, &tall));
+        assert!(contains_x(&wide, &tall));
+        assert!(contains_y(&tall, &wide));
+    }
+
+    /// One rect spanning the other vertically while starting to its left.
+    #[test]
+    fn test_vertical_span() {
+        let outer = rect(0., 0., 4., 10.);
+        let inner = rect(1., 2., 2., 2.);
+        let spanning = rect(1., -1., 2., 12.);
+
+        assert!(contains(&outer, &inner));
+        assert!(!contains(&inner, &outer));
+        assert!(!contains(&outer, &spanning));
+        assert!(contains(&spanning, &inner));
+        assert!(intersects(&outer, &spanning));
+    }
+
+    #[test]
+    fn test_degenerate_rects() {
+        let base = rect(0., 0., 2., 2.);
+        let point = rect(1., 1., 0., 0.);
+        let corner = rect(2., 2., 0., 0.);
+        let segment = rect(0., 2., 2., 0.);
+
+        assert!(contains(&base, &point));
+        assert!(contains(&base, &corner));
+        assert!(contains(&base, &segment));
+        assert!(intersects(&base, &corner));
+        assert!(!overlaps(&base, &point));
+        assert!(!overlaps(&base, &segment));
+        assert!(contains(&point, &point));
+        assert!(!contains(&point, &base));
+    }
+
+    #[test]
+    fn test_touches() {
+        let base = rect(0., 0., 2., 2.);
+
+        // Sharing an edge, part of an edge, or a corner.
+        assert!(touches(&base, &rect(2., 0., 2., 2.)));
+        assert!(touches(&base, &rect(1., 2., 2., 2.)));
+        assert!(touches(&base, &rect(-2., -2., 2., 2.)));
+        // A segment lying on the boundary.
+        assert!(touches(&base, &rect(2., 0.5, 0., 1.)));
+        // Disjoint, overlapping, nested, and equal rects don't touch.
+        assert!(!touches(&base, &rect(3., 0., 1., 1.)));
+        assert!(!touches(&base, &rect(1., 1., 2., 2.)));
+        assert!(!touches(&base, &rect(0., 0., 1., 1.)));
+        assert!(!touches(&base, &base));
+        // A point in the interior doesn't touch.
+        assert!(!touches(&base, &rect(1., 1., 0., 0.)));
+
+        // Equal points, and a point inside a segment, are equal or within rather than touching.
+        let point = rect(1., 1., 0., 0.);
+        let segment = rect(0., 1., 2., 0.);
+        assert!(!touches(&point, &point));
+        assert!(!touches(&point, &segment));
+        assert!(!touches(&segment, &point));
+        assert!(!touches(&segment, &segment));
+        // A segment's end point does touch it, as does a segment meeting it end to end.
+        assert!(touches(&rect(2., 1., 0., 0.), &segment));
+        assert!(touches(&segment, &rect(2., 1., 1., 0.)));
+    }
+
+    #[test]
+    fn test_contains_point() {
+        let base = rect(0., 0., 2., 2.);
+
+        assert!(contains_point(&base, &Point::new(1., 1.)));
+        assert!(contains_point(&base, &Point::new(0., 2.)));
+        assert!(!contains_point(&base, &Point::new(2.5, 1.)));
+        assert!(!contains_point(&base, &Point::new(1., -0.5)));
+    }
+
+    #[test]
+    fn test_relate() {
+        let base = rect(0., 0., 2., 2.);
+
+        assert_eq!(relate(&base, &base), Relation::Equals);
+        assert_eq!(relate(&base, &rect(3., 0., 1., 1.)), Relation::Disjoint);
+        assert_eq!(relate(&base, &rect(2., 0., 2., 2.)), Relation::Touches);
+        assert_eq!(relate(&base, &rect(1., 1., 2., 2.)), Relation::Overlaps);
+        assert_eq!(relate(&base, &rect(0., 0., 1., 1.)), Relation::Contains);
+        assert_eq!(relate(&rect(0., 0., 1., 1.), &base), Relation::Within);
+        // Degenerate rects: an interior point is within, a boundary point only touches.
+        assert_eq!(relate(&rect(1., 1., 0., 0.), &base), Relation::Within);
+        assert_eq!(relate(&rect(2., 1., 0., 0.), &base), Relation::Touches);
+        // A segment along an edge touches, one crossing the interior is contained.
+        assert_eq!(relate(&base, &rect(2., 0.5, 0., 1.)), Relation::Touches);
+        assert_eq!(relate(&base, &rect(0., 1., 2., 0.)), Relation::Contains);
+        // A segment poking out of the rect overlaps it.
+        assert_eq!(relate(&base, &rect(1., 1., 0., 3.)), Relation::Overlaps);
+        // Equal points, and a point inside a segment.
+        assert_eq!(relate(&rect(1., 1., 0., 0.), &rect(1., 1., 0., 0.)), Relation::Equals);
+        assert_eq!(relate(&rect(1., 1., 0., 0.), &rect(0., 1., 2., 0.)), Relation::Within);
+    }
+}
@@ -0,0 +1,191 @@
+use crate::error::Error;
+use crate::geometry::Point;
+use crate::geometry::Rect;
+use crate::geometry::Size;
+use crate::quadtree::{Config, QuadTree};
+
+/**
+ A static tree of geometry that rarely changes, such as a game level's walls, paired
+ with a small dynamic overlay of rects that change every frame, such as moving
+ entities. Updates only touch the overlay, so they stay cheap however large the static
+ tree is, while queries search both.
+
+ Each tier checks conflicts only against its own members, so an entity may overlap the
+ level geometry, and a rect may be a member of both tiers, in which case it is found
+ once per tier.
+ */
+#[derive(Debug)]
+pub struct HybridIndex {
+    level: QuadTree,
+    overlay: QuadTree,
+}
+
+impl HybridIndex {
+    /**
+     Pair the static tree `level` with the dynamic tree `overlay`.
+     */
+    pub fn new(level: QuadTree, overlay: QuadTree) -> HybridIndex {
+        HybridIndex { level, overlay }
+    }
+
+    /**
+     Bulk-load `rects` into a static tree with the given bounds and config, and pair it
+     with an empty overlay set up the same way. Fails if any rect is rejected, with the
+     index of each rejected rect and the reason.
+     */
+    pub fn bulk_load(bounds: Rect, config: Config, rects: &[Rect]) -> Result<HybridIndex, Vec<(usize, Error)>> {
+        let mut level = QuadTree::builder().bounds(bounds).config(config).build();
+        level.insert_all_or_nothing(rects)?;
+        let overlay = QuadTree::builder().bounds(bounds).config(config).build();
+
+        Ok(HybridIndex::new(level, overlay))
+    }
+
+    /// Get the static tree.
+    pub fn level(&self) -> &QuadTree {
+        &self.level
+    }
+
+    /// Get the dynamic overlay tree.
+    pub fn overlay(&self) -> &QuadTree {
+        &self.overlay
+    }
+
+    /// Unwrap the static tree and the overlay.
+    pub fn into_parts(self) -> (QuadTree, QuadTree) {
+        (self.level, self.overlay)
+    }
+
+    /**
+     Add `rect` to the overlay. Fails if it conflicts with another overlay member.
+     */
+    pub fn insert(&mut self, rect: Rect, priority: i32) -> Result<(), Error> {
+        self.overlay.try_insert(rect, priority)
+    }
+
+    /**
+     Remove `rect` from the overlay, returning false if it wasn't an overlay member.
+     The static tree is never changed.
+     */
+    pub fn remove(&mut self, rect: &Rect) -> bool {
+        self.overlay.remove_rect(rect)
+    }
+
+    /**
+     Move the overlay member `from` to `to`, keeping its priority, e.g. to update an
+     entity's bounds each frame, and its place in insertion order. If `to` is rejected,
+     `from` is left where it was. Fails with `NotFound` if `from` isn't an overlay member.
+     */
+    pub fn move_rect(&mut self, from: &Rect, to: Rect) -> Result<(), Error> {
+        if !self.overlay.contains(from) {
+            return Err(Error::NotFound(*from))
+        }
+
+        self.overlay.try_replace(from, to, None)
+    }
+
+    /**
+     Remove every overlay member, e.g. when a level restarts. The static tree is kept.
+     */
+    pub fn clear_overlay(&mut self) {
+        self.overlay.clear();
+    }
+
+    /**
+     Find the members of either tier, static members first.
+     */
+    pub fn members(&self) -> Vec<Rect> {
+        let mut members = self.level.members();
+        members.extend(self.overlay.members());
+        members
+    }
+
+    /**
+     Find the members of either tier intersecting `region`, static members first.
+     */
+    pub fn query(&self, region: &Rect) -> Vec<Rect> {
+        let mut found = self.level.query(region);
+        found.extend(self.overlay.query(region));
+        found
+    }
+
+    /**
+     Find the members of either tier lying entirely inside `region`, static members first.
+     */
+    pub fn query_contained(&self, region: &Rect) -> Vec<Rect> {
+        let mut found = self.level.query_contained(region);
+        found.extend(self.overlay.query_contained(region));
+        found
+    }
+
+    /**
+     Find the member of either tier closest to `point`, or `None` if both are empty.
+     Of a static and an overlay member at the same distance, the static one is returned.
+     */
+    pub fn nearest(&self, point: Point) -> Option<Rect> {
+        let target = Rect::new(point, Size::new(0., 0.));
+        match (self.level.nearest_to_rect(&target), self.overlay.nearest_to_rect(&target)) {
+            (Some(fixed), Some(moving)) if moving.distance_to(&target) < fixed.distance_to(&target) => Some(moving),
+            (Some(fixed), _) => Some(fixed),
+            (None, moving) => moving,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::error::Error;
+    use crate::geometry::{Point, Rect, Size};
+    use crate::quadtree::Config;
+    use super::HybridIndex;
+
+    fn rect(x: f64, y: f64, width: f64, height: f64) -> Rect {
+        Rect::new(Point::new(x, y), Size::new(width, height))
+    }
+
+    #[test]
+    fn test_hybrid_index() {
+        let walls: Vec<Rect> = (0..16).map(|i| rect(i as f64 * 4., 0., 4., 1.)).collect();
+        let mut index = HybridIndex::bulk_load(rect(0., 0., 64., 64.), Config::default(), &walls).unwrap();
+
+        let player = rect(1., 0.5, 1., 1.);
+        // The player may overlap a wall, but not another entity.
+        assert!(index.insert(player, 0).is_ok());
+        assert!(index.insert(rect(1.5, 1., 1., 1.), 0).is_err());
+        assert_eq!(index.query(&rect(0., 0., 2., 2.)), vec![walls[0], player]);
+
+        let moved = rect(30., 30., 1., 1.);
+        assert!(index.move_rect(&player, moved).is_ok());
+        assert!(index.move_rect(&player, moved).is_err());
+        assert_eq!(index.query(&rect(0., 0., 2., 2.)), vec![walls[0]]);
+        assert_eq!(index.nearest(Point::new(31., 29.)), Some(moved));
+        assert_eq!(index.nearest(Point::new(31., 3.)), Some(walls[7]));
+        assert_eq!(index.members().len(), 17);
+
+        index.clear_overlay();
+        assert!(index.overlay().members().is_empty());
+        assert_eq!(index.level().members().len(), 16);
+        assert!(!index.remove(&walls[0]));
+    }
+
+    #[test]
+    fn test_move_rect_keeps_rejected_member_as_it_was() {
+        let mut index = HybridIndex::bulk_load(rect(0., 0., 64., 64.), Config::default(), &[]).unwrap();
+        let (first, second) = (rect(1., 1., 2., 2.), rect(10., 10., 2., 2.));
+        index.insert(first, 3).unwrap();
+        index.insert(second, 4).unwrap();
+
+        assert!(matches!(index.move_rect(&rect(40., 40., 1., 1.), rect(50., 50., 1., 1.)), Err(Error::NotFound(_))));
+        assert!(matches!(index.move_rect(&first, rect(9., 9., 2., 2.)), Err(Error::Overlap(_))));
+        assert!(matches!(index.move_rect(&first, rect(f64::NAN, 0., 1., 1.)), Err(Error::InvalidRect(_))));
+
+        let item = index.overlay().get(&first).unwrap();
+        assert_eq!((item.priority, item.sequence), (3, 0));
+
+        let moved = rect(20., 20., 2., 2.);
+        index.move_rect(&first, moved).unwrap();
+        let order: Vec<Rect> = index.overlay().members_in_insertion_order().iter().map(|item| item.rect).collect();
+        assert_eq!(order, vec![moved, second]);
+        assert_eq!(index.overlay().get(&moved).unwrap().priority, 3);
+    }
+}
@@ -0,0 +1,83 @@
+use crate::geometry::{Point, Rect, Size};
+use crate::quadtree::QuadTree;
+use crate::split::SplitPolicy;
+
+/**
+ The queries shared by every spatial index in this crate, so an index can be swapped
+ for another, or checked against one, without changing calling code.
+ */
+pub trait SpatialIndex {
+    /**
+     Add `rect` to the index, returning false if it was rejected, e.g. because it
+     overlaps an existing member.
+     */
+    fn insert(&mut self, rect: Rect) -> bool;
+
+    /// Find every member.
+    fn members(&self) -> Vec<Rect>;
+
+    /// Find the members intersecting `region`, including those only touching its edges.
+    fn query(&self, region: &Rect) -> Vec<Rect>;
+
+    /**
+     Check if `rect` itself was inserted and is still a member, unlike `query`, which
+     also finds members merely intersecting it.
+     */
+    fn contains_member(&self, rect: &Rect) -> bool;
+
+    /// Find the members lying entirely inside `region`.
+    fn query_contained(&self, region: &Rect) -> Vec<Rect>;
+
+    /// Find the members sharing a boundary with `rect` without overlapping its interior.
+    fn query_touching(&self, rect: &Rect) -> Vec<Rect>;
+
+    /// Remove and return every member intersecting `region`.
+    fn remove_in(&mut self, region: &Rect) -> Vec<Rect>;
+
+    /// Remove the member `rect`, returning false if it wasn't a member.
+    fn remove(&mut self, rect: &Rect) -> bool;
+
+    /**
+     Find the member closest to `point`, or `None` if the index is empty. Of several
+     members at the same distance, any may be returned.
+     */
+    fn nearest(&self, point: Point) -> Option<Rect>;
+}
+
+impl<T, P: SplitPolicy> SpatialIndex for QuadTree<T, P> {
+    fn insert(&mut self, rect: Rect) -> bool {
+        QuadTree::insert(self, rect)
+    }
+
+    fn members(&self) -> Vec<Rect> {
+        self.members()
+    }
+
+    fn query(&self, region: &Rect) -> Vec<Rect> {
+        self.query(region)
+    }
+
+    fn contains_member(&self, rect: &Rect) -> bool {
+        self.contains(rect)
+    }
+
+    fn query_contained(&self, region: &Rect) -> Vec<Rect> {
+        self.query_contained(region)
+    }
+
+    fn query_touching(&self, rect: &Rect) -> Vec<Rect> {
+        self.query_touching(rect)
+    }
+
+    fn remove_in(&mut self, region: &Rect) -> Vec<Rect> {
+        self.remove_in(region)
+    }
+
+    fn remove(&mut self, rect: &Rect) -> bool {
+        self.remove_rect(rect)
+    }
+
+    fn nearest(&self, point: Point) -> Option<Rect> {
+        self.nearest_to_rect(&Rect::new(point, Size::new(0., 0.)))
+    }
+}
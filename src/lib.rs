@@ -0,0 +1,41 @@
+//! A basic quadtree library, storing rectangles rather than points.
+//!
+//! The common types are re-exported from the crate root and from `prelude`.
+
+pub mod allocator;
+pub mod atlas;
+pub mod checked;
+pub mod cursor;
+pub mod error;
+#[cfg(feature = "exact")]
+pub mod exact;
+pub mod external;
+pub mod fixed;
+pub mod flat;
+pub mod geometry;
+pub mod hybrid;
+pub mod index;
+pub mod naive;
+pub mod orthtree;
+pub mod persist;
+#[cfg(feature = "plotters")]
+pub mod plot;
+#[cfg(feature = "rayon")]
+pub mod par;
+pub mod prelude;
+pub mod quadtree;
+pub mod region;
+#[cfg(feature = "png")]
+pub mod render;
+pub mod report;
+pub mod split;
+#[cfg(feature = "wkb")]
+pub mod wkb;
+#[cfg(feature = "shapefile")]
+pub mod shapefile;
+
+pub use crate::error::Error;
+pub use crate::geometry::{Point, Ray, Rect, Relation, Segment, Size};
+pub use crate::index::SpatialIndex;
+pub use crate::quadtree::{Config, QuadTree, QuadTreeBuilder};
+pub use crate::region::Region;
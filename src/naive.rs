@@ -0,0 +1,556 @@
+use crate::geometry::{Point, Rect, Size};
+use crate::index::SpatialIndex;
+
+use std::cmp::Ordering;
+
+/**
+ A spatial index that keeps its members in a flat list and answers every query with
+ a linear scan. Obviously correct, so it serves as a baseline for checking and
+ benchmarking `QuadTree`.
+ */
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct NaiveIndex {
+    rects: Vec<Rect>,
+    allow_overlap: bool,
+}
+
+impl NaiveIndex {
+    /**
+     Create an empty index that, like a default `QuadTree`, rejects overlapping members.
+     */
+    pub fn new() -> NaiveIndex {
+        NaiveIndex::default()
+    }
+
+    /**
+     Create an empty index that accepts overlapping members, but not duplicates,
+     matching a `QuadTree` built with `allow_overlap(true)`.
+     */
+    pub fn new_allowing_overlap() -> NaiveIndex {
+        NaiveIndex { rects: Vec::new(), allow_overlap: true }
+    }
+
+    fn matching(&self, accept: impl Fn(&Rect) -> bool) -> Vec<Rect> {
+        self.rects.iter().filter(|rect| accept(rect)).copied().collect()
+    }
+}
+
+impl SpatialIndex for NaiveIndex {
+    fn insert(&mut self, rect: Rect) -> bool {
+        let conflicts = self.rects.contains(&rect)
+            || (!self.allow_overlap && self.rects.iter().any(|member| member.overlaps(&rect)));
+
+        if conflicts {
+            false
+        } else {
+            self.rects.push(rect);
+            true
+        }
+    }
+
+    fn members(&self) -> Vec<Rect> {
+        self.rects.clone()
+    }
+
+    fn query(&self, region: &Rect) -> Vec<Rect> {
+        self.matching(|rect| region.intersects(rect))
+    }
+
+    fn contains_member(&self, rect: &Rect) -> bool {
+        self.rects.contains(rect)
+    }
+
+    fn query_contained(&self, region: &Rect) -> Vec<Rect> {
+        self.matching(|rect| region.contains(rect))
+    }
+
+    fn query_touching(&self, rect: &Rect) -> Vec<Rect> {
+        self.matching(|member| rect.touches(member))
+    }
+
+    fn remove_in(&mut self, region: &Rect) -> Vec<Rect> {
+        let removed = self.matching(|rect| region.intersects(rect));
+        self.rects.retain(|rect| !region.intersects(rect));
+
+        removed
+    }
+
+    fn remove(&mut self, rect: &Rect) -> bool {
+        match self.rects.iter().position(|member| member == rect) {
+            Some(index) => {
+                self.rects.remove(index);
+                true
+            },
+            None => false,
+        }
+    }
+
+    fn nearest(&self, point: Point) -> Option<Rect> {
+        let point = Rect::new(point, Size::new(0., 0.));
+        self.rects.iter().copied()
+            .min_by(|a, b| a.distance_to(&point).partial_cmp(&b.distance_to(&point)).unwrap_or(Ordering::Equal))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::checked::CheckedQuadTree;
+    use crate::error::Error;
+    use crate::geometry::Point;
+    use crate::geometry::Rect;
+    use crate::geometry::Relation;
+    use crate::geometry::Size;
+    use crate::index::SpatialIndex;
+    use crate::quadtree::{Config, Growth, Item, Placement, QuadTree, QueryCtx, QueryMode};
+    use super::NaiveIndex;
+
+    /// A small xorshift generator, so the tests are repeatable without a dependency.
+    struct XorShift(u32);
+
+    impl XorShift {
+        fn gen_range(&mut self, low: u32, high: u32) -> u32 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 17;
+            self.0 ^= self.0 << 5;
+            low + self.0 % (high - low)
+        }
+    }
+
+    fn random_rect(rng: &mut XorShift, max_side: u32) -> Rect {
+        let x = rng.gen_range(0, 256) as f64;
+        let y = rng.gen_range(0, 256) as f64;
+        let width = rng.gen_range(1, max_side) as f64;
+        let height = rng.gen_range(1, max_side) as f64;
+
+        Rect::new(Point::new(x, y), Size::new(width, height))
+    }
+
+    fn new_tree() -> QuadTree {
+        QuadTree::builder().bounds(Rect::new(Point::new(0., 0.), Size::new(256., 256.))).build()
+    }
+
+    fn assert_same_rects(actual: Vec<Rect>, expected: Vec<Rect>) {
+        assert_eq!(actual.len(), expected.len());
+        for rect in expected.iter() {
+            assert!(actual.contains(rect), "{:?} is missing from {:?}", rect, actual);
+        }
+    }
+
+    /// Apply the same random inserts, queries, and removals to both indexes and
+    /// check they always agree. With `with_points`, every third member is a point.
+    fn cross_check<T: SpatialIndex>(tree: &mut T, seed: u32, with_points: bool) {
+        let mut rng = XorShift(seed.wrapping_mul(2654435761));
+        let mut naive = NaiveIndex::new();
+
+        for i in 0..300 {
+            let mut rect = random_rect(&mut rng, 24);
+            if with_points && i % 3 == 0 {
+                rect.size = Size::new(0., 0.);
+            }
+            assert_eq!(tree.insert(rect), naive.insert(rect));
+        }
+
+        assert_same_rects(tree.members(), naive.members());
+
+        for _ in 0..200 {
+            let region = random_rect(&mut rng, 64);
+            assert_same_rects(tree.query(&region), naive.query(&region));
+            assert_same_rects(tree.query_contained(&region), naive.query_contained(&region));
+            assert_same_rects(tree.query_touching(&region), naive.query_touching(&region));
+            assert_eq!(tree.contains_member(&region), naive.contains_member(&region));
+        }
+
+        for rect in naive.members().iter() {
+            assert!(tree.contains_member(rect));
+        }
+
+        for _ in 0..20 {
+            let region = random_rect(&mut rng, 32);
+            assert_same_rects(tree.remove_in(&region), naive.remove_in(&region));
+            assert_same_rects(tree.members(), naive.members());
+        }
+
+        for i in 0..50 {
+            let point = Point::new(rng.gen_range(0, 256) as f64, rng.gen_range(0, 256) as f64);
+            let target = Rect::new(point, Size::new(0., 0.));
+            let nearest = tree.nearest(point).map(|rect| rect.distance_to(&target));
+            assert_eq!(nearest, naive.nearest(point).map(|rect| rect.distance_to(&target)));
+
+            let rect = if i % 2 == 0 { naive.nearest(point).unwrap() } else { random_rect(&mut rng, 24) };
+            assert_eq!(tree.remove(&rect), naive.remove(&rect));
+        }
+        assert_same_rects(tree.members(), naive.members());
+    }
+
+    #[test]
+    fn test_quadtree_matches_naive() {
+        for seed in 1..6 {
+            cross_check(&mut new_tree(), seed, false);
+        }
+    }
+
+    #[test]
+    fn test_configured_quadtree_matches_naive() {
+        for seed in 1..6 {
+            let mut tree = QuadTree::builder()
+                .bounds(Rect::new(Point::new(0., 0.), Size::new(256., 256.)))
+                .leaf_capacity(8)
+                .max_depth(4)
+                .build();
+            cross_check(&mut tree, seed, false);
+        }
+    }
+
+    #[test]
+    fn test_autosized_quadtree_matches_naive() {
+        cross_check(&mut QuadTree::new_empty(), 7, false);
+    }
+
+    #[test]
+    fn test_growth_strategies_match_naive() {
+        for growth in [Growth::Centered, Growth::PowerOfTwo, Growth::Fit] {
+            let mut tree = QuadTree::builder().leaf_capacity(4).growth(growth).build();
+            cross_check(&mut tree, 11, true);
+
+            let bounds = tree.bounds();
+            assert!(bounds.contains(&tree.content_bounds().unwrap()));
+            if growth == Growth::PowerOfTwo {
+                assert_eq!(bounds.width().log2().fract(), 0.);
+                assert_eq!((bounds.origin.x / bounds.width()).fract(), 0.);
+            }
+        }
+    }
+
+    #[test]
+    fn test_checked_quadtree_matches_naive() {
+        cross_check(&mut CheckedQuadTree::new(new_tree()), 8, false);
+    }
+
+    #[test]
+    fn test_quadtree_with_points_matches_naive() {
+        for seed in 1..6 {
+            cross_check(&mut new_tree(), seed, true);
+            cross_check(&mut CheckedQuadTree::new(new_tree()), seed, true);
+        }
+    }
+
+    #[test]
+    fn test_paged_query_matches_naive() {
+        let mut rng = XorShift(12345);
+        let mut tree = new_tree();
+        let mut naive = NaiveIndex::new_allowing_overlap();
+        tree.set_config(Config { allow_overlap: true, leaf_capacity: 4, ..tree.config() });
+
+        for i in 0..300 {
+            let mut rect = random_rect(&mut rng, 48);
+            if i % 5 == 0 {
+                rect.size = Size::new(0., 0.);
+            }
+            assert_eq!(tree.insert(rect), naive.insert(rect));
+        }
+
+        for page_size in [1, 7, 64] {
+            for _ in 0..20 {
+                let region = random_rect(&mut rng, 128);
+                let mut page = tree.query_page(&region, page_size);
+                let mut rects = Vec::new();
+                loop {
+                    assert!(page.rects.len() <= page_size);
+                    rects.extend(page.rects);
+                    match page.next {
+                        Some(token) => page = tree.query_page_after(&token, page_size),
+                        None => break,
+                    }
+                }
+
+                assert_same_rects(rects, naive.query(&region));
+            }
+        }
+    }
+
+    #[test]
+    fn test_query_into_matches_naive() {
+        let mut rng = XorShift(24680);
+        for placement in [Placement::Duplicate, Placement::Reject] {
+            let mut tree = new_tree();
+            let mut naive = NaiveIndex::new_allowing_overlap();
+            tree.set_config(Config { allow_overlap: true, leaf_capacity: 4, placement, ..tree.config() });
+
+            for i in 0..300 {
+                let mut rect = random_rect(&mut rng, 48);
+                if i % 5 == 0 {
+                    rect.size = Size::new(0., 0.);
+                }
+                if tree.insert(rect) {
+                    naive.insert(rect);
+                }
+            }
+
+            let mut rects = Vec::new();
+            let mut ctx = QueryCtx::new();
+            for _ in 0..50 {
+                let region = random_rect(&mut rng, 128);
+                rects.clear();
+                tree.query_into(&region, &mut rects);
+                assert_same_rects(rects.clone(), naive.query(&region));
+
+                let items = tree.query_with(&mut ctx, &region, QueryMode::Exact);
+                assert_same_rects(items.iter().map(|item| item.rect).collect(), naive.query(&region));
+            }
+        }
+    }
+
+    #[test]
+    fn test_filtered_quadtree_matches_naive() {
+        let mut rng = XorShift(1357);
+        let mut tree = new_tree();
+        let mut naive = NaiveIndex::new();
+
+        for _ in 0..300 {
+            let rect = random_rect(&mut rng, 24);
+            assert_eq!(tree.insert(rect), naive.insert(rect));
+        }
+
+        let keep = |rect: &Rect| rect.width() > rect.height();
+        let mut kept = NaiveIndex::new();
+        for rect in naive.members().into_iter().filter(keep) {
+            kept.insert(rect);
+        }
+
+        let mut retained = new_tree();
+        for rect in naive.members().into_iter() {
+            assert!(retained.insert(rect));
+        }
+        let mut calls = 0;
+        retained.retain(|rect| {
+            calls += 1;
+            keep(rect)
+        });
+        assert_eq!(calls, naive.members().len());
+        assert!(retained.validate().is_ok());
+        assert_same_rects(retained.members(), kept.members());
+
+        let tree = tree.filter(|item| keep(&item.rect));
+        assert!(tree.validate().is_ok());
+        assert_same_rects(tree.members(), kept.members());
+
+        for _ in 0..50 {
+            let region = random_rect(&mut rng, 64);
+            assert_same_rects(tree.query(&region), kept.query(&region));
+        }
+    }
+
+    #[test]
+    fn test_related_queries_match_naive() {
+        let mut rng = XorShift(8642);
+        let mut tree = new_tree();
+        let mut naive = NaiveIndex::new();
+
+        for _ in 0..300 {
+            let rect = random_rect(&mut rng, 16);
+            assert_eq!(tree.insert(rect), naive.insert(rect));
+        }
+
+        let relations = [Relation::Disjoint, Relation::Touches, Relation::Overlaps,
+                         Relation::Contains, Relation::Within, Relation::Equals];
+        for (i, member) in naive.members().into_iter().take(50).enumerate() {
+            // Query with members now and then, so equal rects are found too.
+            let region = if i % 5 == 0 { member } else { random_rect(&mut rng, 64) };
+            for relation in relations {
+                let expected = naive.matching(|rect| rect.relate(&region) == relation);
+                assert_same_rects(tree.query_related(&region, relation), expected);
+            }
+        }
+    }
+
+    #[test]
+    fn test_removals_coalesce_leaves() {
+        let mut rng = XorShift(97531);
+        let mut tree = QuadTree::builder().bounds(Rect::new(Point::new(0., 0.), Size::new(256., 256.))).leaf_capacity(4).build();
+        let mut naive = NaiveIndex::new();
+
+        for _ in 0..300 {
+            let rect = random_rect(&mut rng, 24);
+            assert_eq!(tree.insert(rect), naive.insert(rect));
+        }
+
+        let kept: Vec<Rect> = naive.members().into_iter().take(3).collect();
+        for rect in naive.members().iter().filter(|rect| !kept.contains(rect)) {
+            assert_eq!(tree.remove_many(&[*rect]), vec![*rect]);
+        }
+
+        assert!(tree.validate().is_ok());
+        assert_eq!(tree.len(), kept.len());
+        assert_same_rects(tree.members(), kept);
+        assert_eq!(tree.leaves().count(), 1);
+
+        // Even without coalescing, emptied subtrees collapse.
+        let mut tree = QuadTree::builder().bounds(Rect::new(Point::new(0., 0.), Size::new(256., 256.))).coalesce(false).build();
+        for rect in naive.members().iter() {
+            assert!(tree.insert(*rect));
+        }
+        for rect in naive.members().iter() {
+            assert!(tree.remove_rect(rect));
+            assert!(!tree.remove_rect(rect));
+        }
+        assert!(tree.validate().is_ok());
+        assert_eq!(tree.leaves().count(), 1);
+
+        for rect in naive.members().iter() {
+            assert!(tree.insert(*rect));
+        }
+        assert_eq!(tree.len(), naive.members().len());
+        assert_same_rects(tree.drain().collect(), naive.members());
+        assert!(tree.members().is_empty());
+        assert!(tree.is_empty());
+
+        for rect in naive.members().iter() {
+            assert!(tree.insert(*rect));
+        }
+        let bounds = tree.bounds();
+        tree.clear();
+        assert!(tree.validate().is_ok());
+        assert!(tree.members().is_empty());
+        assert_eq!(tree.bounds(), bounds);
+    }
+
+    #[test]
+    fn test_reject_placement_matches_naive() {
+        let mut rng = XorShift(8642);
+        let mut tree = QuadTree::builder()
+            .bounds(Rect::new(Point::new(0., 0.), Size::new(256., 256.)))
+            .leaf_capacity(2)
+            .allow_overlap(true)
+            .placement(Placement::Reject)
+            .build();
+        let mut naive = NaiveIndex::new_allowing_overlap();
+
+        for i in 0..300 {
+            let mut rect = random_rect(&mut rng, 16);
+            if i % 4 == 0 {
+                rect.size = Size::new(0., 0.);
+            }
+            if tree.insert(rect) {
+                assert!(naive.insert(rect));
+            }
+        }
+
+        assert!(naive.members().len() > 100);
+        assert!(tree.validate().is_ok());
+        assert_eq!(tree.report().duplicated_members, 0);
+        let (report, stats) = (tree.report(), tree.stats());
+        assert_eq!((stats.nodes, stats.leaves, stats.members), (report.nodes, report.leaves, report.members));
+        assert_eq!(stats.max_depth, report.depth_histogram.len() - 1);
+        assert_eq!(stats.average_occupancy * stats.leaves as f64, stats.members as f64);
+        assert_same_rects(tree.members(), naive.members());
+
+        for _ in 0..50 {
+            let region = random_rect(&mut rng, 64);
+            assert_same_rects(tree.query(&region), naive.query(&region));
+
+            let mut page = tree.query_page(&region, 5);
+            let mut rects = page.rects.clone();
+            while let Some(token) = page.next {
+                page = tree.query_page_after(&token, 5);
+                rects.extend(page.rects.iter().copied());
+            }
+            assert_same_rects(rects, naive.query(&region));
+        }
+    }
+
+    #[test]
+    fn test_insertion_order_matches_naive() {
+        let mut rng = XorShift(2468);
+        let mut tree = new_tree();
+        let mut naive = NaiveIndex::new();
+
+        for round in 0..4 {
+            for _ in 0..100 {
+                let rect = random_rect(&mut rng, 24);
+                assert_eq!(tree.insert(rect), naive.insert(rect));
+            }
+
+            if round % 2 == 0 {
+                let region = random_rect(&mut rng, 64);
+                assert_same_rects(tree.remove_in(&region), naive.remove_in(&region));
+            }
+        }
+
+        let rects = |items: Vec<Item>| items.into_iter().map(|item| item.rect).collect::<Vec<_>>();
+        assert_eq!(rects(tree.members_in_insertion_order()), naive.members());
+
+        for _ in 0..20 {
+            let region = random_rect(&mut rng, 64);
+            assert_eq!(rects(tree.query_in_insertion_order(&region)), naive.query(&region));
+        }
+    }
+
+    #[test]
+    fn test_overlap_errors_report_every_conflict() {
+        let mut rng = XorShift(1357);
+        let mut tree = new_tree();
+        let mut naive = NaiveIndex::new();
+
+        for _ in 0..300 {
+            let rect = random_rect(&mut rng, 24);
+            match tree.try_insert(rect, 0) {
+                Ok(()) => assert!(naive.insert(rect)),
+                Err(Error::Overlap(conflicts)) => {
+                    let expected: Vec<Rect> = naive.members().into_iter().filter(|member| member.overlaps(&rect)).collect();
+                    assert!(!expected.is_empty());
+                    assert_same_rects(conflicts, expected);
+                },
+                Err(err) => panic!("unexpected error {:?}", err),
+            }
+        }
+    }
+
+    #[test]
+    fn test_min_cell_size_stops_subdivision() {
+        let mut rng = XorShift(4321);
+        let mut tree = QuadTree::builder()
+            .bounds(Rect::new(Point::new(0., 0.), Size::new(256., 64.)))
+            .min_cell_size(4.)
+            .build();
+        let mut naive = NaiveIndex::new();
+
+        // Tiny members packed closely enough to keep splitting without a floor.
+        for _ in 0..400 {
+            let origin = Point::new(rng.gen_range(0, 4096) as f64 / 16., rng.gen_range(0, 1024) as f64 / 16.);
+            let rect = Rect::new(origin, Size::new(0.05, 0.05));
+            assert_eq!(tree.insert(rect), naive.insert(rect));
+        }
+
+        assert!(tree.validate().is_ok());
+        assert_same_rects(tree.members(), naive.members());
+        assert!(tree.leaves().all(|leaf| leaf.rect.size.width >= 4. && leaf.rect.size.height >= 4.));
+        assert_eq!(tree.stats().max_depth, 4);
+    }
+
+    #[test]
+    fn test_grown_paged_queries_match_naive() {
+        for seed in 1u32..6 {
+            let mut rng = XorShift(seed.wrapping_mul(2654435761));
+            let mut tree = QuadTree::builder().bounds(Rect::new(Point::new(0., 0.), Size::new(32., 32.))).build();
+            let mut naive = NaiveIndex::new();
+
+            // Members are spread over 256 by 256, so the root grows several times.
+            for _ in 0..200 {
+                let rect = random_rect(&mut rng, 24);
+                assert_eq!(SpatialIndex::insert(&mut tree, rect), naive.insert(rect));
+            }
+            assert_eq!(tree.validate(), Ok(()));
+
+            for _ in 0..100 {
+                let region = random_rect(&mut rng, 64);
+                let mut page = tree.query_page(&region, 3);
+                let mut rects = page.rects;
+                while let Some(token) = page.next {
+                    page = tree.query_page_after(&token, 3);
+                    rects.extend(page.rects);
+                }
+                assert_same_rects(rects, naive.query(&region));
+            }
+        }
+    }
+}
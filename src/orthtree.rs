@@ -0,0 +1,407 @@
+/*!
+ A tree over boxes in any number of dimensions `D`, splitting each node into `2^D`
+ children: a tree of intervals for `D = 1`, a quadtree for 2, and an octree for 3.
+
+ `OrthTree` is a smaller tree than `QuadTree`: members may overlap, are stored in
+ every leaf they intersect, and must intersect the root, which never grows. The two
+ share the subdivision of a node into its children, `Bounds::child`.
+ */
+
+use crate::geometry::predicates::{interval_contains, intervals_intersect};
+
+use std::collections::HashSet;
+use std::mem;
+
+/**
+ An axis-aligned box in `D` dimensions, with an origin at its smallest corner and a
+ size along each axis, as `Rect` is in two.
+ */
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Bounds<const D: usize> {
+    pub origin: [f64; D],
+    pub size: [f64; D],
+}
+
+impl<const D: usize> Bounds<D> {
+    /// The number of children a node is split into.
+    pub const CHILDREN: usize = 1 << D;
+
+    pub fn new(origin: [f64; D], size: [f64; D]) -> Bounds<D> {
+        Bounds { origin, size }
+    }
+
+    pub fn min(&self, axis: usize) -> f64 {
+        self.origin[axis]
+    }
+
+    pub fn max(&self, axis: usize) -> f64 {
+        self.origin[axis] + self.size[axis]
+    }
+
+    /**
+     Check that every coordinate is finite and no size is negative.
+     */
+    pub fn is_valid(&self) -> bool {
+        self.origin.iter().all(|start| start.is_finite())
+        && self.size.iter().all(|length| length.is_finite() && *length >= 0.)
+    }
+
+    /**
+     Check if two boxes have any point in common, including points on their boundaries.
+     */
+    pub fn intersects(&self, other: &Bounds<D>) -> bool {
+        (0..D).all(|axis| intervals_intersect(self.min(axis), self.max(axis), other.min(axis), other.max(axis)))
+    }
+
+    /**
+     Check if `self` entirely contains `inner`. Shared boundaries are allowed.
+     */
+    pub fn contains(&self, inner: &Bounds<D>) -> bool {
+        (0..D).all(|axis| interval_contains(self.min(axis), self.max(axis), inner.min(axis), inner.max(axis)))
+    }
+
+    /**
+     The bounds of child `index` of a node with these bounds, less than `CHILDREN`.
+     Bit `axis` of `index` picks the upper half along that axis, so for `D = 2`, index 1
+     is the top right quadrant and index 2 the bottom left.
+     */
+    pub fn child(&self, index: usize) -> Bounds<D> {
+        let size = self.size.map(|length| length * 0.5);
+        let mut origin = self.origin;
+        for (axis, start) in origin.iter_mut().enumerate() {
+            *start += if index >> axis & 1 == 1 { size[axis] } else { 0. };
+        }
+
+        Bounds { origin, size }
+    }
+
+    /// A hashable key for a member's bounds. Every copy of a member has the same key.
+    fn key(&self) -> ([u64; D], [u64; D]) {
+        (self.origin.map(f64::to_bits), self.size.map(f64::to_bits))
+    }
+}
+
+enum Elements<const D: usize> {
+    Members(Vec<Bounds<D>>),
+    Children(Vec<Node<D>>),
+}
+
+struct Node<const D: usize> {
+    bounds: Bounds<D>,
+    elements: Elements<D>,
+}
+
+impl<const D: usize> Node<D> {
+    fn new_empty(bounds: Bounds<D>) -> Node<D> {
+        Node { bounds, elements: Elements::Members(Vec::new()) }
+    }
+
+    fn insert(&mut self, member: Bounds<D>, depth: usize, limits: &Limits) {
+        match self.elements {
+            Elements::Children(ref mut children) => {
+                for child in children.iter_mut().filter(|child| child.bounds.intersects(&member)) {
+                    child.insert(member, depth + 1, limits);
+                }
+            },
+            Elements::Members(ref mut members) => {
+                members.push(member);
+                if members.len() > limits.leaf_capacity && depth < limits.max_depth && self.split_separates() {
+                    self.split(depth, limits);
+                }
+            },
+        }
+    }
+
+    /**
+     Check if some child of this leaf would hold fewer of its members, so that
+     members covering the whole leaf don't split it down to the depth limit.
+     */
+    fn split_separates(&self) -> bool {
+        match self.elements {
+            Elements::Members(ref members) => (0..Bounds::<D>::CHILDREN).any(|index| {
+                let child = self.bounds.child(index);
+                members.iter().any(|member| !child.intersects(member))
+            }),
+            Elements::Children(_) => false,
+        }
+    }
+
+    fn split(&mut self, depth: usize, limits: &Limits) {
+        let members = match mem::replace(&mut self.elements, Elements::Children(Vec::new())) {
+            Elements::Members(members) => members,
+            Elements::Children(_) => unreachable!("only leaves are split"),
+        };
+
+        let mut children: Vec<Node<D>> = (0..Bounds::<D>::CHILDREN).map(|index| Node::new_empty(self.bounds.child(index))).collect();
+        for member in members {
+            for child in children.iter_mut().filter(|child| child.bounds.intersects(&member)) {
+                child.insert(member, depth + 1, limits);
+            }
+        }
+
+        self.elements = Elements::Children(children);
+    }
+
+    fn remove(&mut self, member: &Bounds<D>, limits: &Limits) -> bool {
+        let removed = match self.elements {
+            Elements::Children(ref mut children) => {
+                // A member is stored in every child it intersects, so remove it from each.
+                let mut removed = false;
+                for child in children.iter_mut().filter(|child| child.bounds.intersects(member)) {
+                    removed |= child.remove(member, limits);
+                }
+                removed
+            },
+            Elements::Members(ref mut members) => {
+                let count = members.len();
+                members.retain(|other| other != member);
+                members.len() != count
+            },
+        };
+
+        if removed {
+            self.coalesce(limits);
+        }
+
+        removed
+    }
+
+    /**
+     Merge this node's children back into it if they are all leaves, and hold no more
+     members between them than a leaf's capacity.
+     */
+    fn coalesce(&mut self, limits: &Limits) {
+        let members = match self.elements {
+            Elements::Children(ref children) => {
+                let mut members = Vec::new();
+                let mut seen = HashSet::new();
+                for child in children.iter() {
+                    match child.elements {
+                        Elements::Members(ref child_members) => {
+                            members.extend(child_members.iter().filter(|member| seen.insert(member.key())));
+                        },
+                        Elements::Children(_) => return,
+                    }
+                }
+                members
+            },
+            Elements::Members(_) => return,
+        };
+
+        if members.len() <= limits.leaf_capacity {
+            self.elements = Elements::Members(members);
+        }
+    }
+}
+
+/**
+ The limits on splitting a tree's leaves.
+ */
+struct Limits {
+    leaf_capacity: usize,
+    max_depth: usize,
+}
+
+/**
+ A tree of boxes in `D` dimensions, each node split into `2^D` children.
+ */
+pub struct OrthTree<const D: usize> {
+    root: Node<D>,
+    limits: Limits,
+    len: usize,
+}
+
+/// A tree of intervals, each node split in two.
+pub type IntervalTree = OrthTree<1>;
+
+/// A tree of boxes in three dimensions, each node split into eight octants.
+pub type Octree = OrthTree<3>;
+
+impl<const D: usize> OrthTree<D> {
+    /**
+     Create an empty tree over `bounds`, with the same leaf capacity and depth limit
+     as a `QuadTree` with the default `Config`.
+     */
+    pub fn new(bounds: Bounds<D>) -> OrthTree<D> {
+        OrthTree::with_limits(bounds, 1, 32)
+    }
+
+    /**
+     Create an empty tree over `bounds` whose leaves are split once they hold more than
+     `leaf_capacity` members, until they are `max_depth` levels below the root.
+     */
+    pub fn with_limits(bounds: Bounds<D>, leaf_capacity: usize, max_depth: usize) -> OrthTree<D> {
+        OrthTree { root: Node::new_empty(bounds), limits: Limits { leaf_capacity: leaf_capacity.max(1), max_depth }, len: 0 }
+    }
+
+    pub fn bounds(&self) -> Bounds<D> {
+        self.root.bounds
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /**
+     Insert `member`, returning false if it is invalid, doesn't intersect the root,
+     or is already a member.
+     */
+    pub fn insert(&mut self, member: Bounds<D>) -> bool {
+        if !member.is_valid() || !self.root.bounds.intersects(&member) || self.query(&member).contains(&member) {
+            return false
+        }
+
+        self.root.insert(member, 0, &self.limits);
+        self.len += 1;
+        true
+    }
+
+    /**
+     Remove `member`, returning false if it isn't a member.
+     */
+    pub fn remove(&mut self, member: &Bounds<D>) -> bool {
+        let removed = self.root.remove(member, &self.limits);
+        if removed {
+            self.len -= 1;
+        }
+
+        removed
+    }
+
+    /**
+     Find the members intersecting `region`. Each is returned once, even if it is
+     stored in several leaves.
+     */
+    pub fn query(&self, region: &Bounds<D>) -> Vec<Bounds<D>> {
+        let mut found = Vec::new();
+        let mut seen = HashSet::new();
+        let mut nodes_to_check = vec![&self.root];
+
+        while let Some(node) = nodes_to_check.pop() {
+            if !node.bounds.intersects(region) {
+                continue
+            }
+
+            match node.elements {
+                Elements::Children(ref children) => nodes_to_check.extend(children),
+                Elements::Members(ref members) => {
+                    found.extend(members.iter().filter(|member| member.intersects(region) && seen.insert(member.key())));
+                },
+            }
+        }
+
+        found
+    }
+
+    /**
+     Get every member, each once.
+     */
+    pub fn members(&self) -> Vec<Bounds<D>> {
+        self.query(&self.root.bounds)
+    }
+
+    /**
+     Get the bounds of every leaf, e.g. to draw the tree's cells.
+     */
+    pub fn leaf_bounds(&self) -> Vec<Bounds<D>> {
+        let mut leaves = Vec::new();
+        let mut nodes_to_check = vec![&self.root];
+
+        while let Some(node) = nodes_to_check.pop() {
+            match node.elements {
+                Elements::Children(ref children) => nodes_to_check.extend(children),
+                Elements::Members(_) => leaves.push(node.bounds),
+            }
+        }
+
+        leaves
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::geometry::Point;
+    use crate::geometry::Rect;
+    use crate::geometry::Size;
+    use crate::quadtree::QuadTree;
+    use super::{Bounds, IntervalTree, Octree, OrthTree};
+
+    fn sorted<const D: usize>(mut members: Vec<Bounds<D>>) -> Vec<Bounds<D>> {
+        members.sort_by(|a, b| a.origin.partial_cmp(&b.origin).unwrap().then(a.size.partial_cmp(&b.size).unwrap()));
+        members
+    }
+
+    #[test]
+    fn test_children_match_quadtree() {
+        let rect = Rect::new(Point::new(0.1, -3.), Size::new(0.7, 5.));
+        let bounds = Bounds::new([0.1, -3.], [0.7, 5.]);
+        let quadrants: Vec<Bounds<2>> = QuadTree::child_rects(&rect).iter()
+            .map(|child| Bounds::new([child.origin.x, child.origin.y], [child.size.width, child.size.height]))
+            .collect();
+
+        assert_eq!(quadrants, [0, 1, 3, 2].map(|index| bounds.child(index)));
+        assert_eq!(Bounds::<3>::CHILDREN, 8);
+        assert!(bounds.contains(&bounds.child(3)));
+    }
+
+    #[test]
+    fn test_interval_tree() {
+        let mut tree = IntervalTree::new(Bounds::new([0.], [16.]));
+        for &(start, length) in [(1., 2.), (5., 0.), (9., 6.), (2., 10.)].iter() {
+            assert!(tree.insert(Bounds::new([start], [length])));
+        }
+
+        assert_eq!(tree.len(), 4);
+        assert!(!tree.insert(Bounds::new([5.], [0.])));
+        assert!(!tree.insert(Bounds::new([20.], [1.])));
+        assert_eq!(sorted(tree.query(&Bounds::new([4.], [1.]))), vec![Bounds::new([2.], [10.]), Bounds::new([5.], [0.])]);
+        assert_eq!(tree.query(&Bounds::new([13.], [0.])), vec![Bounds::new([9.], [6.])]);
+    }
+
+    #[test]
+    fn test_octree_splits_into_octants() {
+        let mut tree = Octree::new(Bounds::new([0.; 3], [8.; 3]));
+        tree.insert(Bounds::new([1.; 3], [1.; 3]));
+        tree.insert(Bounds::new([5.; 3], [1.; 3]));
+
+        assert_eq!(tree.leaf_bounds().len(), 8);
+        // A member spanning every octant is returned once.
+        let spanning = Bounds::new([3.; 3], [2.; 3]);
+        tree.insert(spanning);
+        assert_eq!(tree.members().len(), 3);
+        assert_eq!(sorted(tree.query(&Bounds::new([4.5; 3], [0.; 3]))), vec![spanning]);
+        assert_eq!(tree.query(&Bounds::new([0., 6., 0.], [1.; 3])), vec![]);
+    }
+
+    #[test]
+    fn test_covering_members_dont_split() {
+        let mut tree = OrthTree::<2>::with_limits(Bounds::new([0.; 2], [4.; 2]), 1, 32);
+        tree.insert(Bounds::new([0.; 2], [4.; 2]));
+        tree.insert(Bounds::new([-1.; 2], [6.; 2]));
+
+        assert_eq!(tree.leaf_bounds(), vec![tree.bounds()]);
+        assert_eq!(tree.len(), 2);
+    }
+
+    #[test]
+    fn test_remove_coalesces() {
+        let mut tree = OrthTree::<2>::new(Bounds::new([0.; 2], [4.; 2]));
+        let members = [Bounds::new([0.; 2], [1.; 2]), Bounds::new([3.; 2], [1.; 2]), Bounds::new([1., 1.], [2., 2.])];
+        for member in members.iter() {
+            tree.insert(*member);
+        }
+        assert!(tree.leaf_bounds().len() > 1);
+
+        assert!(tree.remove(&members[0]));
+        assert!(!tree.remove(&members[0]));
+        assert!(tree.remove(&members[2]));
+        assert_eq!(tree.leaf_bounds(), vec![tree.bounds()]);
+        assert_eq!(tree.members(), vec![members[1]]);
+        assert_eq!(tree.len(), 1);
+        assert!(!tree.is_empty());
+    }
+}
@@ -0,0 +1,175 @@
+use crate::geometry::Rect;
+use crate::quadtree::{Item, Node, Placement, QuadTree};
+use crate::split::SplitPolicy;
+
+use rayon::iter::plumbing::{bridge_unindexed, Folder, UnindexedConsumer, UnindexedProducer};
+use rayon::iter::{IntoParallelIterator, ParallelIterator};
+
+/**
+ A parallel iterator over the items stored in a quadtree, each yielded once.
+ Work is split at quadrant boundaries, so each thread walks whole subtrees.
+ */
+pub struct ParItems<'a> {
+    root: &'a Node,
+    placement: Placement,
+}
+
+impl<T, P: SplitPolicy> QuadTree<T, P> {
+    /**
+     Iterate over every stored item in parallel, e.g. to score or simplify members
+     with rayon. Members spanning several leaves are only yielded once.
+     */
+    pub fn par_iter(&self) -> ParItems<'_> {
+        ParItems { root: self.root(), placement: self.config().placement }
+    }
+
+    /**
+     Run `worker` over each of the root's four subtrees in parallel, one rayon task per
+     quadrant, then combine their outputs, given in top left, top right, bottom right,
+     bottom left order, with `merge`. A root without children is processed whole on
+     the current thread. A member spanning quadrants is seen by the worker of each
+     quadrant it lies in.
+     */
+    pub fn par_process<R, U, W, M>(&self, worker: W, merge: M) -> U
+        where R: Send, W: Fn(&Node) -> R + Sync, M: FnOnce(Vec<R>) -> U {
+        let children = match self.root().children() {
+            Some(children) => children,
+            None => return merge(vec![worker(self.root())]),
+        };
+
+        let worker = &worker;
+        let mut outputs: [Option<R>; 4] = [None, None, None, None];
+        rayon::scope(|scope| {
+            for (child, output) in children.into_iter().zip(outputs.iter_mut()) {
+                scope.spawn(move |_| *output = Some(worker(child)));
+            }
+        });
+
+        // The scope waits for every task, so each output is set.
+        merge(outputs.into_iter().flatten().collect())
+    }
+}
+
+impl<'a, T, P: SplitPolicy> IntoParallelIterator for &'a QuadTree<T, P> {
+    type Iter = ParItems<'a>;
+    type Item = Item;
+
+    fn into_par_iter(self) -> ParItems<'a> {
+        self.par_iter()
+    }
+}
+
+impl ParallelIterator for ParItems<'_> {
+    type Item = Item;
+
+    fn drive_unindexed<C: UnindexedConsumer<Item>>(self, consumer: C) -> C::Result {
+        let producer = ItemProducer { root: self.root.bounds(), placement: self.placement, nodes: vec![self.root] };
+        bridge_unindexed(producer, consumer)
+    }
+}
+
+/**
+ The nodes left for one thread to walk.
+ */
+struct ItemProducer<'a> {
+    /// The bounds of the tree's root.
+    root: Rect,
+    placement: Placement,
+    nodes: Vec<&'a Node>,
+}
+
+impl UnindexedProducer for ItemProducer<'_> {
+    type Item = Item;
+
+    fn split(mut self) -> (Self, Option<Self>) {
+        if self.nodes.len() == 1 {
+            if let Some(children) = self.nodes[0].children() {
+                self.nodes = children.to_vec();
+            }
+        }
+
+        if self.nodes.len() < 2 {
+            return (self, None)
+        }
+
+        let rest = self.nodes.split_off(self.nodes.len() / 2);
+        let (root, placement) = (self.root, self.placement);
+        (self, Some(ItemProducer { root, placement, nodes: rest }))
+    }
+
+    fn fold_with<F: Folder<Item>>(self, mut folder: F) -> F {
+        let (root, placement) = (self.root, self.placement);
+        let mut nodes_to_visit = self.nodes;
+
+        while let Some(node) = nodes_to_visit.pop() {
+            if folder.full() {
+                break;
+            }
+
+            match node.children() {
+                Some(children) => nodes_to_visit.extend(children.iter().rev()),
+                None => {
+                    // Only the leaf owning a spanning member yields it.
+                    let owned = node.leaf_items().iter().filter(|item| node.reports_in_page(&root, &root, item, placement));
+                    folder = folder.consume_iter(owned.copied());
+                },
+            }
+        }
+
+        folder
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::geometry::{Point, Rect, Size};
+    use crate::quadtree::QuadTree;
+
+    use rayon::iter::ParallelIterator;
+
+    #[test]
+    fn test_par_iter_yields_each_member_once() {
+        let mut tree = QuadTree::builder().bounds(Rect::new(Point::new(0., 0.), Size::new(64., 64.))).leaf_capacity(2).build();
+        for i in 0..16 {
+            let offset = i as f64 * 4.;
+            tree.try_insert(Rect::new(Point::new(offset, offset), Size::new(6., 3.)), 0).unwrap();
+            tree.insert_point(Point::new(offset + 2., 60. - offset), 0).unwrap();
+        }
+
+        let mut rects: Vec<Rect> = tree.par_iter().map(|item| item.rect).collect();
+        let members = tree.members();
+        assert_eq!(rects.len(), members.len());
+
+        rects.retain(|rect| !members.contains(rect));
+        assert!(rects.is_empty());
+    }
+
+    #[test]
+    fn test_par_process_runs_a_worker_per_quadrant() {
+        let bounds = Rect::new(Point::new(0., 0.), Size::new(64., 64.));
+        let mut tree = QuadTree::builder().bounds(bounds).allow_overlap(true).build();
+        for &(x, y) in [(1., 1.), (61., 1.), (61., 61.), (1., 61.)].iter() {
+            tree.try_insert(Rect::new(Point::new(x, y), Size::new(1., 1.)), 0).unwrap();
+        }
+        tree.try_insert(bounds, 0).unwrap();
+
+        let counts = tree.par_process(|node| node.members().len(), |counts| counts);
+        assert_eq!(counts, vec![2, 2, 2, 2]);
+
+        // Without children, the root is processed whole.
+        let empty = QuadTree::builder().bounds(bounds).build();
+        assert_eq!(empty.par_process(|node| node.bounds(), |bounds| bounds), vec![bounds]);
+    }
+
+    #[test]
+    fn test_par_iter_yields_each_member_once_after_growth() {
+        let mut tree = QuadTree::builder().bounds(Rect::new(Point::new(0., 0.), Size::new(64., 64.))).build();
+        // The first two lie on the right and bottom edges of the root before it grows.
+        tree.try_insert(Rect::new(Point::new(64., 10.), Size::new(0., 10.)), 0).unwrap();
+        tree.try_insert(Rect::new(Point::new(10., 54.), Size::new(10., 10.)), 0).unwrap();
+        tree.try_insert(Rect::new(Point::new(100., 10.), Size::new(4., 4.)), 0).unwrap();
+        tree.try_insert(Rect::new(Point::new(10., 100.), Size::new(4., 4.)), 0).unwrap();
+
+        assert_eq!(tree.par_iter().count(), tree.len());
+    }
+}
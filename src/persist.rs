@@ -0,0 +1,291 @@
+/*!
+ Saving a quadtree's members and settings, and loading them back, in a versioned format.
+
+ Both the binary format and `Snapshot`, the form serialized with serde, carry a format
+ version. Loading migrates data written by any earlier version of the format to the
+ current one, one revision at a time, so indexes persisted by older releases of this
+ crate keep loading after an upgrade. Data from a newer version than this release
+ understands is rejected rather than misread.
+
+ A `Snapshot` carries the tree's split policy, so a policy with settings of its own is
+ serialized along with the tree. The binary format stores trees using the default policy.
+
+ Format revisions:
+ - Version 1: the first versioned format.
+ */
+
+use crate::error::Error;
+use crate::geometry::Point;
+use crate::geometry::Rect;
+use crate::geometry::Size;
+use crate::quadtree::{Config, Growth, Item, Placement, QuadTree};
+use crate::split::{CapacitySplit, SplitPolicy};
+
+use std::io::Read;
+use std::io::Write;
+
+/// The format version written by this release.
+pub const FORMAT_VERSION: u32 = 1;
+
+/// The bytes every saved tree starts with.
+const MAGIC: &[u8; 4] = b"QTRE";
+
+/**
+ A tree's members, settings and split policy, tagged with the format version they were
+ written in. Serializable with serde when the `serde` feature is enabled.
+ */
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Snapshot<P = CapacitySplit> {
+    pub version: u32,
+    pub bounds: Rect,
+    pub leaf_capacity: usize,
+    pub max_depth: usize,
+    pub min_cell_size: f64,
+    pub allow_overlap: bool,
+    pub coalesce: bool,
+    pub placement: Placement,
+    pub growth: Growth,
+    pub policy: P,
+    /// The sequence number the next inserted member will be given.
+    pub next_sequence: u64,
+    /// The members, oldest first.
+    pub items: Vec<Item>,
+}
+
+impl<P> Snapshot<P> {
+    /**
+     Migrate a snapshot written by any version of the format to the current version.
+     Fails with `Serialization` if it was written by a newer version.
+     */
+    pub fn migrate(self) -> Result<Snapshot<P>, Error> {
+        match self.version {
+            FORMAT_VERSION => Ok(self),
+            // Each revision of the format adds a step here, upgrading a snapshot from
+            // the previous version and migrating the result again.
+            version => Err(Error::Serialization(format!("unsupported format version {}", version))),
+        }
+    }
+}
+
+impl<P: SplitPolicy + Clone> QuadTree<(), P> {
+    /**
+     Capture the tree's members, settings and split policy in the current format version.
+     */
+    pub fn snapshot(&self) -> Snapshot<P> {
+        let config = self.config();
+        Snapshot {
+            version: FORMAT_VERSION,
+            bounds: self.bounds(),
+            leaf_capacity: config.leaf_capacity,
+            max_depth: config.max_depth,
+            min_cell_size: config.min_cell_size,
+            allow_overlap: config.allow_overlap,
+            coalesce: config.coalesce,
+            placement: config.placement,
+            growth: config.growth,
+            policy: self.policy().clone(),
+            next_sequence: self.next_sequence(),
+            items: self.members_in_insertion_order(),
+        }
+    }
+
+    /**
+     Rebuild a tree from a snapshot written by any version of the format, keeping each
+     member's priority and insertion order. Fails with `Serialization` if the snapshot
+     can't be migrated, or with the insert error of a member that can't be restored.
+     */
+    pub fn from_snapshot(snapshot: Snapshot<P>) -> Result<QuadTree<(), P>, Error> {
+        let snapshot = snapshot.migrate()?;
+        let config = Config {
+            leaf_capacity: snapshot.leaf_capacity.max(1),
+            max_depth: snapshot.max_depth,
+            min_cell_size: snapshot.min_cell_size,
+            allow_overlap: snapshot.allow_overlap,
+            coalesce: snapshot.coalesce,
+            placement: snapshot.placement,
+            growth: snapshot.growth,
+        };
+
+        QuadTree::from_items(snapshot.bounds, config, snapshot.policy, &snapshot.items, snapshot.next_sequence)
+    }
+}
+
+impl QuadTree {
+    /**
+     Write the tree in the current version of the binary format.
+     */
+    pub fn save<W: Write>(&self, mut writer: W) -> Result<(), Error> {
+        let snapshot = self.snapshot();
+        let mut bytes = MAGIC.to_vec();
+        bytes.extend_from_slice(&snapshot.version.to_le_bytes());
+
+        push_rect(&mut bytes, &snapshot.bounds);
+        bytes.extend_from_slice(&(snapshot.leaf_capacity as u64).to_le_bytes());
+        bytes.extend_from_slice(&(snapshot.max_depth as u64).to_le_bytes());
+        bytes.extend_from_slice(&snapshot.min_cell_size.to_le_bytes());
+        bytes.push(snapshot.allow_overlap as u8 | (snapshot.coalesce as u8) << 1);
+        bytes.push(match snapshot.placement { Placement::Duplicate => 0, Placement::Reject => 1 });
+        bytes.push(match snapshot.growth { Growth::Doubling => 0, Growth::Centered => 1, Growth::PowerOfTwo => 2, Growth::Fit => 3 });
+        bytes.extend_from_slice(&snapshot.next_sequence.to_le_bytes());
+
+        bytes.extend_from_slice(&(snapshot.items.len() as u64).to_le_bytes());
+        for item in snapshot.items.iter() {
+            push_rect(&mut bytes, &item.rect);
+            bytes.extend_from_slice(&item.priority.to_le_bytes());
+            bytes.extend_from_slice(&item.sequence.to_le_bytes());
+        }
+
+        writer.write_all(&bytes)?;
+        Ok(())
+    }
+}
+
+/**
+ Load a tree saved in any version of the binary format, migrating it to the current
+ version. Fails with `Serialization` if the data isn't a saved tree, is malformed, or
+ was written by a newer version of the format.
+ */
+pub fn load_any_version<R: Read>(mut reader: R) -> Result<QuadTree, Error> {
+    let mut magic = [0; 4];
+    read_exact(&mut reader, &mut magic)?;
+    if &magic != MAGIC {
+        return Err(Error::Serialization("not a saved quadtree".to_string()))
+    }
+
+    let version = u32::from_le_bytes(read_bytes(&mut reader)?);
+    let snapshot = match version {
+        1 => read_version_1(&mut reader)?,
+        _ => return Err(Error::Serialization(format!("unsupported format version {}", version))),
+    };
+
+    QuadTree::from_snapshot(snapshot)
+}
+
+fn read_version_1<R: Read>(reader: &mut R) -> Result<Snapshot, Error> {
+    let bounds = read_rect(reader)?;
+    let leaf_capacity = u64::from_le_bytes(read_bytes(reader)?) as usize;
+    let max_depth = u64::from_le_bytes(read_bytes(reader)?) as usize;
+    let min_cell_size = f64::from_le_bytes(read_bytes(reader)?);
+    let [flags, placement, growth] = read_bytes(reader)?;
+    let placement = match placement {
+        0 => Placement::Duplicate,
+        1 => Placement::Reject,
+        _ => return Err(Error::Serialization(format!("unknown placement {}", placement))),
+    };
+    let growth = match growth {
+        0 => Growth::Doubling,
+        1 => Growth::Centered,
+        2 => Growth::PowerOfTwo,
+        3 => Growth::Fit,
+        _ => return Err(Error::Serialization(format!("unknown growth strategy {}", growth))),
+    };
+    let next_sequence = u64::from_le_bytes(read_bytes(reader)?);
+
+    let count = u64::from_le_bytes(read_bytes(reader)?);
+    let mut items = Vec::new();
+    for _ in 0..count {
+        let rect = read_rect(reader)?;
+        let priority = i32::from_le_bytes(read_bytes(reader)?);
+        let sequence = u64::from_le_bytes(read_bytes(reader)?);
+        items.push(Item { rect, priority, sequence });
+    }
+
+    Ok(Snapshot {
+        version: 1,
+        bounds,
+        leaf_capacity,
+        max_depth,
+        min_cell_size,
+        allow_overlap: flags & 1 != 0,
+        coalesce: flags & 2 != 0,
+        placement,
+        growth,
+        policy: CapacitySplit,
+        next_sequence,
+        items,
+    })
+}
+
+/// Append a rect as its little-endian x, y, width, and height.
+fn push_rect(bytes: &mut Vec<u8>, rect: &Rect) {
+    for value in [rect.origin.x, rect.origin.y, rect.size.width, rect.size.height] {
+        bytes.extend_from_slice(&value.to_le_bytes());
+    }
+}
+
+fn read_rect<R: Read>(reader: &mut R) -> Result<Rect, Error> {
+    let mut values = [0.; 4];
+    for value in values.iter_mut() {
+        *value = f64::from_le_bytes(read_bytes(reader)?);
+    }
+    Ok(Rect::new(Point::new(values[0], values[1]), Size::new(values[2], values[3])))
+}
+
+fn read_bytes<R: Read, const N: usize>(reader: &mut R) -> Result<[u8; N], Error> {
+    let mut bytes = [0; N];
+    read_exact(reader, &mut bytes)?;
+    Ok(bytes)
+}
+
+fn read_exact<R: Read>(reader: &mut R, bytes: &mut [u8]) -> Result<(), Error> {
+    reader.read_exact(bytes).map_err(|_| Error::Serialization("saved quadtree is truncated".to_string()))
+}
+
+#[cfg(test)]
+mod test {
+    use crate::error::Error;
+    use crate::geometry::{Point, Rect, Size};
+    use crate::quadtree::{Growth, Placement, QuadTree};
+    use crate::split::WasteSplit;
+    use super::{load_any_version, FORMAT_VERSION};
+
+    #[test]
+    fn test_save_and_load() {
+        let mut tree = QuadTree::builder()
+            .bounds(Rect::new(Point::new(0., 0.), Size::new(64., 64.)))
+            .leaf_capacity(2)
+            .placement(Placement::Reject)
+            .growth(Growth::Fit)
+            .build();
+        for i in 0..10 {
+            tree.try_insert(Rect::new(Point::new(i as f64 * 6., 1.), Size::new(2., 2.)), i).unwrap();
+        }
+        tree.remove_many(&[Rect::new(Point::new(0., 1.), Size::new(2., 2.))]);
+
+        let mut bytes = Vec::new();
+        tree.save(&mut bytes).unwrap();
+        let mut loaded = load_any_version(&bytes[..]).unwrap();
+        assert!(loaded.validate().is_ok());
+        assert_eq!(loaded.config(), tree.config());
+        assert_eq!(loaded.bounds(), tree.bounds());
+        assert_eq!(loaded.members_in_insertion_order(), tree.members_in_insertion_order());
+
+        // New members are numbered after the saved ones.
+        let rect = Rect::new(Point::new(1., 40.), Size::new(2., 2.));
+        loaded.try_insert(rect, 0).unwrap();
+        assert_eq!(loaded.get(&rect).unwrap().sequence, 10);
+
+        bytes[4..8].copy_from_slice(&(FORMAT_VERSION + 1).to_le_bytes());
+        assert!(matches!(load_any_version(&bytes[..]), Err(Error::Serialization(_))));
+        assert!(load_any_version(&bytes[..20]).is_err());
+    }
+    #[test]
+    fn test_snapshot_keeps_split_policy() {
+        let policy = WasteSplit { max_waste: 0.6 };
+        let mut tree = QuadTree::builder()
+            .bounds(Rect::new(Point::new(0., 0.), Size::new(100., 100.)))
+            .split_policy(policy)
+            .build();
+        for &(x, y) in [(0., 0.), (55., 0.), (55., 55.), (0., 55.)].iter() {
+            tree.try_insert(Rect::new(Point::new(x, y), Size::new(45., 45.)), 0).unwrap();
+        }
+
+        let snapshot = tree.snapshot();
+        assert_eq!(snapshot.policy, policy);
+        let loaded = QuadTree::from_snapshot(snapshot).unwrap();
+        assert_eq!(*loaded.policy(), policy);
+        assert_eq!(loaded.leaves().count(), 1);
+        assert_eq!(loaded.members_in_insertion_order(), tree.members_in_insertion_order());
+    }
+}
@@ -0,0 +1,172 @@
+/*!
+ Drawing a quadtree with plotters, e.g. to overlay the index on a chart of the data
+ it holds. Rects are drawn in the coordinates of the chart or drawing area, so the
+ tree lines up with anything else plotted there.
+ */
+
+use crate::geometry::Rect;
+use crate::quadtree::{Node, QuadTree};
+use crate::split::SplitPolicy;
+
+use plotters::coord::CoordTranslate;
+use plotters::drawing::{DrawingArea, DrawingAreaErrorKind};
+use plotters::element::Rectangle;
+use plotters::prelude::DrawingBackend;
+use plotters::style::{Color, ShapeStyle, BLACK, BLUE};
+
+/**
+ The styles used to draw a tree: one for the borders of its nodes, and one for its members.
+ */
+#[derive(Clone, Copy, Debug)]
+pub struct PlotStyle {
+    pub node: ShapeStyle,
+    pub member: ShapeStyle,
+}
+
+impl Default for PlotStyle {
+    fn default() -> PlotStyle {
+        PlotStyle { node: BLACK.mix(0.3).stroke_width(1), member: BLUE.mix(0.4).filled() }
+    }
+}
+
+fn rectangle(rect: &Rect, style: ShapeStyle) -> Rectangle<(f64, f64)> {
+    Rectangle::new([(rect.min_x(), rect.min_y()), (rect.max_x(), rect.max_y())], style)
+}
+
+impl<T, P: SplitPolicy> QuadTree<T, P> {
+    /**
+     The rectangles drawing the tree's nodes, then its members, e.g. to pass to
+     `ChartContext::draw_series` to overlay the tree on a chart.
+     */
+    pub fn plot_elements(&self, style: &PlotStyle) -> Vec<Rectangle<(f64, f64)>> {
+        let mut elements = Vec::new();
+        let mut nodes_to_draw: Vec<&Node> = vec![self.root()];
+        while let Some(node) = nodes_to_draw.pop() {
+            elements.push(rectangle(&node.bounds(), style.node));
+            if let Some(children) = node.children() {
+                nodes_to_draw.extend(children);
+            }
+        }
+
+        elements.extend(self.members().iter().map(|rect| rectangle(rect, style.member)));
+        elements
+    }
+
+    /**
+     Draw the tree onto `area`, whose coordinates are those of the tree. Fails with
+     the backend's error if drawing any rectangle fails.
+     */
+    pub fn draw_on<DB, CT>(&self, area: &DrawingArea<DB, CT>, style: &PlotStyle) -> Result<(), DrawingAreaErrorKind<DB::ErrorType>>
+        where DB: DrawingBackend, CT: CoordTranslate<From = (f64, f64)> {
+        for element in self.plot_elements(style).iter() {
+            area.draw(element)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::geometry::Point;
+    use crate::geometry::Rect;
+    use crate::geometry::Size;
+    use crate::quadtree::QuadTree;
+    use super::PlotStyle;
+
+    use plotters::coord::types::RangedCoordf64;
+    use plotters::coord::Shift;
+    use plotters::drawing::{DrawingArea, DrawingAreaErrorKind, IntoDrawingArea};
+    use plotters::element::PointCollection;
+    use plotters::prelude::{Cartesian2d, DrawingBackend};
+    use plotters_backend::{BackendColor, BackendCoord, BackendStyle, DrawingErrorKind};
+
+    use std::cell::RefCell;
+    use std::io;
+    use std::rc::Rc;
+
+    /// A backend recording the corners of each rectangle drawn, and whether it was filled.
+    struct Recorder {
+        rects: Rc<RefCell<Vec<(BackendCoord, BackendCoord, bool)>>>,
+        fail: bool,
+    }
+
+    impl DrawingBackend for Recorder {
+        type ErrorType = io::Error;
+
+        fn get_size(&self) -> (u32, u32) {
+            (64, 64)
+        }
+
+        fn ensure_prepared(&mut self) -> Result<(), DrawingErrorKind<io::Error>> {
+            Ok(())
+        }
+
+        fn present(&mut self) -> Result<(), DrawingErrorKind<io::Error>> {
+            Ok(())
+        }
+
+        fn draw_pixel(&mut self, _point: BackendCoord, _color: BackendColor) -> Result<(), DrawingErrorKind<io::Error>> {
+            Ok(())
+        }
+
+        fn draw_rect<S: BackendStyle>(&mut self, upper_left: BackendCoord, bottom_right: BackendCoord, _style: &S, fill: bool)
+            -> Result<(), DrawingErrorKind<io::Error>> {
+            if self.fail {
+                return Err(DrawingErrorKind::DrawingError(io::Error::other("backend failed")))
+            }
+
+            self.rects.borrow_mut().push((upper_left, bottom_right, fill));
+            Ok(())
+        }
+    }
+
+    fn rect(x: f64, y: f64, width: f64, height: f64) -> Rect {
+        Rect::new(Point::new(x, y), Size::new(width, height))
+    }
+
+    /// A 64 pixel square area showing the tree's coordinates from 0 to 64.
+    fn area(recorder: Recorder) -> DrawingArea<Recorder, Cartesian2d<RangedCoordf64, RangedCoordf64>> {
+        let area: DrawingArea<Recorder, Shift> = recorder.into_drawing_area();
+        area.apply_coord_spec(Cartesian2d::<RangedCoordf64, RangedCoordf64>::new(0f64..64f64, 0f64..64f64, (0..64, 0..64)))
+    }
+
+    fn tree() -> QuadTree {
+        let mut tree = QuadTree::builder().bounds(rect(0., 0., 64., 64.)).build();
+        tree.try_insert(rect(4., 4., 16., 8.), 0).unwrap();
+        tree.try_insert(rect(48., 48., 8., 8.), 0).unwrap();
+        tree
+    }
+
+    #[test]
+    fn test_plot_elements_cover_nodes_then_members() {
+        let elements = tree().plot_elements(&PlotStyle::default());
+        let corners: Vec<Vec<(f64, f64)>> = elements.iter().map(|element| element.point_iter().to_vec()).collect();
+
+        // The root and its four children, then the two members.
+        assert_eq!(corners.len(), 7);
+        assert_eq!(corners[0], vec![(0., 0.), (64., 64.)]);
+        assert_eq!(corners[5..], [vec![(4., 4.), (20., 12.)], vec![(48., 48.), (56., 56.)]]);
+        assert_eq!(QuadTree::builder().bounds(rect(0., 0., 64., 64.)).build().plot_elements(&PlotStyle::default()).len(), 1);
+    }
+
+    #[test]
+    fn test_draw_on_fills_members_only() {
+        let rects = Rc::new(RefCell::new(Vec::new()));
+        tree().draw_on(&area(Recorder { rects: rects.clone(), fail: false }), &PlotStyle::default()).unwrap();
+
+        let rects = rects.borrow();
+        assert_eq!(rects.len(), 7);
+        assert_eq!(rects.iter().filter(|(_, _, fill)| *fill).count(), 2);
+        assert!(rects.iter().any(|&(upper_left, bottom_right, _)| upper_left == (0, 0) && bottom_right == (64, 64)));
+    }
+
+    #[test]
+    fn test_draw_on_reports_backend_errors() {
+        let rects = Rc::new(RefCell::new(Vec::new()));
+        let result = tree().draw_on(&area(Recorder { rects: rects.clone(), fail: true }), &PlotStyle::default());
+
+        assert!(matches!(result, Err(DrawingAreaErrorKind::BackendError(_))));
+        assert!(rects.borrow().is_empty());
+    }
+}
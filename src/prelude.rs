@@ -0,0 +1,11 @@
+/*!
+ The types most code using a quadtree needs, for importing in one line:
+ `use quadtree::prelude::*;`.
+ */
+
+pub use crate::error::Error;
+pub use crate::geometry::{Point, Ray, Rect, Relation, Segment, Size};
+pub use crate::index::SpatialIndex;
+pub use crate::quadtree::{Config, Growth, Item, JoinPredicate, Node, Placement, QuadTree, QuadTreeBuilder, Quadrant, QueryCtx, QueryMode};
+pub use crate::region::Region;
+pub use crate::split::SplitPolicy;
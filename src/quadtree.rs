@@ -0,0 +1,4291 @@
+use crate::cursor::{Cursor, CursorMut};
+use crate::error::Error;
+use crate::flat::FlatTree;
+use crate::geometry::geohash;
+use crate::geometry::Point;
+use crate::geometry::Ray;
+use crate::geometry::Relation;
+use crate::geometry::Rect;
+use crate::geometry::Segment;
+use crate::geometry::Size;
+use crate::orthtree::Bounds;
+use crate::report::{TreeReport, TreeStats};
+use crate::split::{CapacitySplit, SplitPolicy};
+
+use std::cmp;
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::mem;
+use std::vec;
+
+/// The most times `grow_to_contain` doubles the root to reach a far-away rect.
+const MAX_ROOT_DOUBLINGS: i32 = 64;
+
+/// A hashable key for a member's rect. Every copy of a member has the same key.
+pub(crate) fn rect_key(rect: &Rect) -> [u64; 4] {
+    [rect.origin.x.to_bits(), rect.origin.y.to_bits(), rect.size.width.to_bits(), rect.size.height.to_bits()]
+}
+
+/**
+ Elements that may be contained by a quadtree node.
+ Either child nodes, one or more rects, or nothing.
+ */
+#[derive(Debug)]
+enum Elements {
+    /// Children are top left, top right, bottom right, and bottom left, respectively.
+    Children(Box<Node>, Box<Node>, Box<Node>, Box<Node>),
+    /// One or more rectangles. Never empty.
+    Members(Vec<Item>),
+    /// Nothing.
+    Empty,
+}
+
+/**
+ A rectangle stored in a quadtree, along with its ordering key.
+ */
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Item {
+    pub rect: Rect,
+    /// The item's z-index or priority. Higher values are on top.
+    pub priority: i32,
+    /// The order in which the item was inserted into its tree, starting from 0.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub sequence: u64,
+}
+
+impl Item {
+    pub fn new(rect: Rect, priority: i32) -> Item {
+        Item { rect, priority, sequence: 0 }
+    }
+
+    /// Check if the item is a point rather than a rect.
+    pub fn is_point(&self) -> bool {
+        self.rect.is_point()
+    }
+}
+
+/**
+ One of the four children of a node, named by its position within the node.
+ */
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Quadrant {
+    TopLeft,
+    TopRight,
+    BottomRight,
+    BottomLeft,
+}
+
+impl Quadrant {
+    /// Every quadrant, in the order a node stores its children.
+    pub const ALL: [Quadrant; 4] = [Quadrant::TopLeft, Quadrant::TopRight, Quadrant::BottomRight, Quadrant::BottomLeft];
+
+    /**
+     The position of this quadrant in `ALL`, and so of its child within a node.
+     */
+    pub fn index(&self) -> usize {
+        match *self {
+            Quadrant::TopLeft => 0,
+            Quadrant::TopRight => 1,
+            Quadrant::BottomRight => 2,
+            Quadrant::BottomLeft => 3,
+        }
+    }
+
+    /**
+     The quadkey digit for this quadrant: 0 for top left, 1 for top right,
+     2 for bottom left, and 3 for bottom right.
+     */
+    pub fn quadkey_digit(&self) -> char {
+        match *self {
+            Quadrant::TopLeft => '0',
+            Quadrant::TopRight => '1',
+            Quadrant::BottomLeft => '2',
+            Quadrant::BottomRight => '3',
+        }
+    }
+}
+
+/**
+ Where a ray first hits a member: the member's item, the ray parameter `t` and point
+ of the hit, and the unit normal of the edge the ray entered through. The normal is
+ zero if the ray starts inside the member.
+ */
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RayHit {
+    pub item: Item,
+    pub t: f64,
+    pub point: Point,
+    pub normal: Point,
+}
+
+/**
+ A member crossed by a segment, with the segment parameters at which it enters and
+ exits the member.
+ */
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SegmentCrossing {
+    pub rect: Rect,
+    pub enter: f64,
+    pub exit: f64,
+}
+
+/**
+ Two moving members that come into contact during a timestep, and the time after the
+ start of the step at which they first touch.
+ */
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SweptPair {
+    pub rect: Rect,
+    pub other_rect: Rect,
+    pub time_of_impact: f64,
+}
+
+/**
+ The sequence of quadrants leading from the root of a tree down to one of its nodes.
+ */
+#[derive(Clone, Debug, PartialEq)]
+pub struct NodePath {
+    pub quadrants: Vec<Quadrant>,
+}
+
+impl NodePath {
+    /**
+     Render the path as a quadkey string, one digit per level. The root's path is empty.
+     */
+    pub fn to_quadkey(&self) -> String {
+        self.quadrants.iter().map(|quadrant| quadrant.quadkey_digit()).collect()
+    }
+}
+
+/**
+ Where a paged query left off: the query region, the path to the leaf holding the
+ next member, and that member's position in the leaf. Holds no borrow of the tree,
+ so it can be kept, or sent to a client, between requests.
+ */
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct QueryToken {
+    region: Rect,
+    path: Vec<Quadrant>,
+    offset: usize,
+}
+
+/**
+ One page of a paged query's results, and the token for fetching the next page,
+ or `None` if this is the last page.
+ */
+#[derive(Clone, Debug, PartialEq)]
+pub struct QueryPage {
+    pub rects: Vec<Rect>,
+    pub next: Option<QueryToken>,
+}
+
+/**
+ Scratch space for `QuadTree::query_with`, kept between queries so their results
+ don't need a new allocation each time. A context can be used with any tree.
+ */
+#[derive(Clone, Debug, Default)]
+pub struct QueryCtx {
+    found: Vec<Item>,
+    /// The keys of the rects in `found`.
+    seen: HashSet<[u64; 4]>,
+}
+
+impl QueryCtx {
+    pub fn new() -> QueryCtx {
+        Default::default()
+    }
+
+    /**
+     Create a context with room for `capacity` results before it needs to allocate.
+     */
+    pub fn with_capacity(capacity: usize) -> QueryCtx {
+        QueryCtx { found: Vec::with_capacity(capacity), seen: HashSet::with_capacity(capacity) }
+    }
+}
+
+/**
+ A group of nearby members found by `QuadTree::cluster`: the tree cell they were
+ grouped by, the bounds and centroid of their centers, and the members themselves.
+ */
+#[derive(Clone, Debug, PartialEq)]
+pub struct Cluster {
+    pub cell: Rect,
+    pub bounds: Rect,
+    pub centroid: Point,
+    pub members: Vec<Rect>,
+}
+
+/**
+ A tree cell's aggregate statistics found by `QuadTree::summaries_at_depth`: its bounds,
+ how many members it holds, and the bounds and highest priority of those members, if any.
+ */
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CellSummary {
+    pub cell: Rect,
+    pub count: usize,
+    pub content_bounds: Option<Rect>,
+    pub max_priority: Option<i32>,
+}
+
+/**
+ The spatial relationship used to pair members of two trees in `QuadTree::join`.
+ */
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum JoinPredicate {
+    /// The members have at least one point in common.
+    Intersects,
+    /// The members are no farther apart than the given distance.
+    WithinDistance(f64),
+    /// The member from the first tree entirely contains the member from the second.
+    Contains,
+}
+
+impl JoinPredicate {
+    /**
+     Check if a member from the first tree and a member from the second satisfy the predicate.
+     */
+    pub fn matches(&self, rect: &Rect, other_rect: &Rect) -> bool {
+        match *self {
+            JoinPredicate::Intersects => rect.intersects(other_rect),
+            JoinPredicate::WithinDistance(distance) => rect.distance_to(other_rect) <= distance,
+            JoinPredicate::Contains => rect.contains(other_rect),
+        }
+    }
+
+    /**
+     Check if members stored in two nodes with the given bounds could satisfy the predicate.
+     */
+    fn may_match_within(&self, node_rect: &Rect, other_node_rect: &Rect) -> bool {
+        match *self {
+            JoinPredicate::WithinDistance(distance) => node_rect.distance_to(other_node_rect) <= distance,
+            JoinPredicate::Intersects | JoinPredicate::Contains => node_rect.intersects(other_node_rect),
+        }
+    }
+}
+
+/**
+ How closely a region query checks members against the query region.
+ */
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum QueryMode {
+    /// Return every member stored in a node intersected by the region, even if the
+    /// member itself doesn't intersect the region. Cheaper, but only a candidate set.
+    Broad,
+    /// Return only members that intersect the region.
+    Exact,
+}
+
+/**
+ How a quadtree stores members that span the boundaries between its leaves.
+ */
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Placement {
+    /// Store a member in every leaf it intersects. Suits rendering and collision,
+    /// where any rect may be inserted.
+    Duplicate,
+    /// Store each member in a single leaf containing it, e.g. so GIS features are never
+    /// split between cells. Inserting a rect that would span the children of an inner
+    /// node fails, and a leaf isn't split while one of its members would span its children.
+    Reject,
+}
+
+/**
+ How a quadtree's root grows to reach a rect inserted outside it.
+ */
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Growth {
+    /// Double the root toward the rect until it is reached, keeping the old root as a
+    /// descendant of the new one. Cheap, but can leave the data far off-center.
+    Doubling,
+    /// Rebuild the tree with a square root twice the size of the members and the rect
+    /// together, centered on them.
+    Centered,
+    /// Rebuild the tree with the smallest square root whose side is a power of two and
+    /// whose origin is a multiple of its side, so bounds stay on a fixed grid.
+    PowerOfTwo,
+    /// Rebuild the tree with the smallest root containing the old root and the rect,
+    /// in one step, e.g. when growing to known final extents.
+    Fit,
+}
+
+/**
+ Settings controlling how a quadtree subdivides and what it accepts.
+ */
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Config {
+    /// The number of members a leaf holds before it is split.
+    pub leaf_capacity: usize,
+    /// The depth below the root at which leaves are no longer split.
+    pub max_depth: usize,
+    /// The smallest width or height a node may have. Leaves whose children would be
+    /// smaller in either dimension are not split.
+    pub min_cell_size: f64,
+    /// Whether members may overlap each other.
+    pub allow_overlap: bool,
+    /// Whether removals merge four leaf siblings back into their parent once they hold
+    /// no more members than a leaf's capacity.
+    pub coalesce: bool,
+    /// How members spanning several leaves are stored.
+    pub placement: Placement,
+    /// How the root grows to reach rects inserted outside it.
+    pub growth: Growth,
+}
+
+impl Default for Config {
+    fn default() -> Config {
+        Config { leaf_capacity: 1, max_depth: 32, min_cell_size: 0., allow_overlap: false, coalesce: true, placement: Placement::Duplicate, growth: Growth::Doubling }
+    }
+}
+
+impl Config {
+    /**
+     Check if a node with bounds `cell`, `depth` levels below the root, is above both
+     the depth limit and the minimum cell size, so it may be split.
+     */
+    pub(crate) fn can_split(&self, cell: &Rect, depth: usize) -> bool {
+        depth < self.max_depth && cell.size.width.min(cell.size.height) / 2. >= self.min_cell_size
+    }
+}
+
+/**
+ Configures and creates a quadtree, e.g.
+ `QuadTree::builder().bounds(rect).leaf_capacity(8).max_depth(12).allow_overlap(true).build()`.
+ */
+#[derive(Default)]
+pub struct QuadTreeBuilder<P = CapacitySplit> {
+    bounds: Option<Rect>,
+    config: Config,
+    policy: P,
+}
+
+impl QuadTreeBuilder {
+    /**
+     Start configuring a quadtree with the default settings. The same as `QuadTree::builder()`.
+     */
+    pub fn new() -> QuadTreeBuilder {
+        QuadTreeBuilder { bounds: None, config: Default::default(), policy: CapacitySplit }
+    }
+}
+
+impl<P: SplitPolicy> QuadTreeBuilder<P> {
+    /**
+     Set the bounds of the root node. Without bounds, the root starts zero-sized
+     and is sized to fit the first rect inserted.
+     */
+    pub fn bounds(self, bounds: Rect) -> QuadTreeBuilder<P> {
+        QuadTreeBuilder { bounds: Some(bounds), ..self }
+    }
+
+    /**
+     Set the number of members a leaf holds before it is split. Defaults to 1.
+     Members that touch or overlap each other may share a leaf beyond its capacity,
+     since no split could separate them.
+     */
+    pub fn leaf_capacity(self, capacity: usize) -> QuadTreeBuilder<P> {
+        QuadTreeBuilder { config: Config { leaf_capacity: cmp::max(capacity, 1), ..self.config }, ..self }
+    }
+
+    /**
+     Set the depth below the root at which leaves stop being split, however many
+     members they hold. Defaults to 32.
+     */
+    pub fn max_depth(self, depth: usize) -> QuadTreeBuilder<P> {
+        QuadTreeBuilder { config: Config { max_depth: depth, ..self.config }, ..self }
+    }
+
+    /**
+     Set the smallest width or height a node may have, so members that are tiny or
+     nearly coincident don't split leaves indefinitely. Defaults to 0, i.e. only
+     `max_depth` limits subdivision.
+     */
+    pub fn min_cell_size(self, size: f64) -> QuadTreeBuilder<P> {
+        QuadTreeBuilder { config: Config { min_cell_size: size, ..self.config }, ..self }
+    }
+
+    /**
+     Set whether members may overlap each other. Defaults to false, in which case
+     inserting a rect overlapping an existing member fails.
+     */
+    pub fn allow_overlap(self, allow: bool) -> QuadTreeBuilder<P> {
+        QuadTreeBuilder { config: Config { allow_overlap: allow, ..self.config }, ..self }
+    }
+
+    /**
+     Set whether removals merge four leaf siblings back into their parent once they hold
+     no more members than a leaf's capacity. Defaults to true, so trees with a lot of
+     churn don't keep a deep skeleton of nearly empty nodes.
+     */
+    pub fn coalesce(self, coalesce: bool) -> QuadTreeBuilder<P> {
+        QuadTreeBuilder { config: Config { coalesce, ..self.config }, ..self }
+    }
+
+    /**
+     Set the policy deciding when a leaf is split. Defaults to `CapacitySplit`, which
+     splits leaves holding more than their capacity.
+     */
+    pub fn split_policy<Q: SplitPolicy>(self, policy: Q) -> QuadTreeBuilder<Q> {
+        QuadTreeBuilder { bounds: self.bounds, config: self.config, policy }
+    }
+
+    /**
+     Set how members spanning several leaves are stored. Defaults to `Duplicate`.
+     */
+    pub fn placement(self, placement: Placement) -> QuadTreeBuilder<P> {
+        QuadTreeBuilder { config: Config { placement, ..self.config }, ..self }
+    }
+
+    /**
+     Set how the root grows to reach rects inserted outside it. Defaults to `Doubling`.
+     */
+    pub fn growth(self, growth: Growth) -> QuadTreeBuilder<P> {
+        QuadTreeBuilder { config: Config { growth, ..self.config }, ..self }
+    }
+
+    /**
+     Replace all settings at once.
+     */
+    pub fn config(self, config: Config) -> QuadTreeBuilder<P> {
+        QuadTreeBuilder { config: Config { leaf_capacity: cmp::max(config.leaf_capacity, 1), ..config }, ..self }
+    }
+
+    /**
+     Create an empty quadtree with the configured bounds and settings.
+     */
+    pub fn build(self) -> QuadTree<(), P> {
+        self.build_with_values()
+    }
+
+    /**
+     Create an empty quadtree with the configured bounds and settings, whose members
+     may be given values of type `T` with `QuadTree::try_insert_with`.
+     */
+    pub fn build_with_values<T>(self) -> QuadTree<T, P> {
+        let bounds = self.bounds.unwrap_or(Rect::new(Point::new(0., 0.), Size::new(0., 0.)));
+
+        QuadTree::new_with_size(bounds.origin, bounds.size, self.config, self.policy)
+    }
+}
+
+/**
+ A quadtree: its root node, along with the settings and bookkeeping that apply to the
+ tree as a whole.
+
+ No public method panics when given finite coordinates. Invalid input is reported
+ with an `Error` or a false return value instead, and internal consistency checks
+ only run in debug builds.
+
+ Points and rects can be stored in the same tree, and are found by the same queries.
+ A point is a rect with zero width and height, and can be inserted with `insert_point`.
+ Degenerate rects, with zero width or height, are stored as points or line segments.
+ They intersect the nodes and members they touch, but never overlap anything, so
+ they can be inserted on top of other members. A rect spanning several leaves is
+ stored in each of them, but a point on the edge between leaves is only stored in one.
+ Rects that aren't valid, with a non-finite coordinate or a negative size, are rejected.
+
+ Members may carry a value of type `T`, e.g. the record a rect bounds. Values are kept
+ apart from the nodes, once per member, so they are never copied between leaves.
+ */
+#[derive(Debug)]
+pub struct QuadTree<T = (), P = CapacitySplit> {
+    root: Node,
+    config: Config,
+    /// Decides when a leaf is split, within the limits set by the config.
+    policy: P,
+    /// Set when the config has changed since the tree was last restructured.
+    restructure_pending: bool,
+    /// The sequence number given to the next inserted item.
+    next_sequence: u64,
+    /// The number of distinct members in the tree.
+    len: usize,
+    /// The values given to members with `try_insert_with`, by member key.
+    values: HashMap<[u64; 4], T>,
+}
+
+/**
+ A quadtree node that can contain either one or more rectangles,
+ or exactly four child nodes.
+ */
+#[derive(Debug)]
+pub struct Node {
+    rect: Rect,
+    elements: Elements,
+    /// The highest priority of any member stored in this node or its descendants.
+    max_priority: i32,
+}
+
+impl QuadTree {
+    /**
+     Create a quadtree with a root node with the given origin and size, holding `items`
+     as a single leaf, or empty if there are none. Fails with `InvalidNode` if an item
+     doesn't intersect the root.
+     */
+    pub fn new(origin: Point, size: Size, items: Vec<Item>) -> Result<QuadTree, Error> {
+        let node_rect = Rect::new(origin, size);
+
+        if items.is_empty() {
+            Ok(QuadTree::new_with_size(origin, size, Default::default(), CapacitySplit))
+        } else if items.iter().all(|item| node_rect.intersects(&item.rect)) {
+            let len = items.len();
+            Ok(QuadTree { len, ..QuadTree::with_root(Node::new_with_members(node_rect, items), Default::default(), CapacitySplit) })
+        } else {
+            Err(Error::InvalidNode(node_rect))
+        }
+    }
+
+    /**
+     Create a quadtree with a root node with the given origin and size, whose children are
+     the roots of `children` in `Quadrant::ALL` order. The new tree has the default settings.
+     Fails with `InvalidNode` if the children don't tile the root.
+     */
+    pub fn from_children(origin: Point, size: Size, children: [QuadTree; 4]) -> Result<QuadTree, Error> {
+        let node_rect = Rect::new(origin, size);
+        let config = Config::default();
+        // The children were built apart, so members on their shared edges are stored in
+        // the neighboring children afterwards.
+        let edge_items: Vec<Item> = children.iter().flat_map(|child| child.root.items_on_boundary(config.placement)).collect();
+        let [tl, tr, br, bl] = children.map(|child| Box::new(child.root));
+        if !Node::children_tile(&node_rect, &tl.rect, &tr.rect, &br.rect, &bl.rect) {
+            return Err(Error::InvalidNode(node_rect))
+        }
+
+        let root = edge_items.into_iter()
+            .fold(Node::new_with_children(node_rect, tl, tr, br, bl), |root, item| root.fill_in_item(item, 0, &config, &CapacitySplit));
+        let mut tree = QuadTree::with_root(root, config, CapacitySplit);
+        tree.len = tree.members().len();
+        Ok(tree)
+    }
+
+    /**
+     Start configuring a quadtree with a builder.
+     */
+    pub fn builder() -> QuadTreeBuilder {
+        QuadTreeBuilder::new()
+    }
+
+    /**
+     Create a quadtree with a root node with the same origin and a square
+     size with side length matching the longer dimension of `rect`.
+     A degenerate `rect` gets a root with side length 1.
+     */
+    pub fn new_autosized(rect: Rect) -> QuadTree {
+        let root_rect = Rect::new(rect.origin, QuadTree::root_size_for(&rect));
+
+        QuadTree { len: 1, ..QuadTree::with_root(Node::new_with_members(root_rect, vec![Item::new(rect, 0)]), Default::default(), CapacitySplit) }
+    }
+
+    /**
+     Create an empty quadtree with a zero-sized root node.
+     */
+    pub fn new_empty() -> QuadTree {
+        let origin = Point::new(0., 0.);
+        let size = Size::new(0., 0.);
+
+        QuadTree::new_with_size(origin, size, Default::default(), CapacitySplit)
+    }
+
+    /**
+     The size of a square root sized to fit `rect`. Degenerate rects get a unit root
+     rather than a zero-sized one, which could never grow.
+     */
+    fn root_size_for(rect: &Rect) -> Size {
+        let side = rect.width().max(rect.height());
+        if side > 0. {
+            Size::new(side, side)
+        } else {
+            Size::new(1., 1.)
+        }
+    }
+
+    /**
+     The bounds of the children of a node with bounds `rect`, in `Quadrant::ALL` order.
+     */
+    pub(crate) fn child_rects(rect: &Rect) -> [Rect; 4] {
+        let bounds = Bounds::new([rect.origin.x, rect.origin.y], [rect.size.width, rect.size.height]);
+
+        // Child indices pick the upper half along x with bit 0, and along y with bit 1.
+        [0, 1, 3, 2].map(|index| {
+            let child = bounds.child(index);
+            Rect::new(Point::new(child.origin[0], child.origin[1]), Size::new(child.size[0], child.size[1]))
+        })
+    }
+
+    /**
+     Get the smallest rect containing every rect in `rects`, or `None` if there are none.
+     */
+    fn bounds_of(rects: &[Rect]) -> Option<Rect> {
+        let (first, rest) = rects.split_first()?;
+
+        Some(rest.iter().fold(*first, |bounds, rect| bounds.union(rect)))
+    }
+}
+
+impl<T, P: SplitPolicy> QuadTree<T, P> {
+
+    /**
+     Count the members of the tree. Each member counts once, however many leaves hold it.
+     The count is kept up to date as members are inserted and removed, so this is cheap.
+     */
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /**
+     Check if the tree has no members.
+     */
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /**
+     Get the bounds of the root node.
+     */
+    pub fn bounds(&self) -> Rect {
+        self.root.rect
+    }
+
+    /**
+     Get the root node, e.g. to walk the tree's structure.
+     */
+    pub fn root(&self) -> &Node {
+        &self.root
+    }
+
+    /**
+     Create a cursor positioned at the root node, for walking the tree explicitly.
+     */
+    pub fn cursor(&self) -> Cursor<'_> {
+        Cursor::new(self)
+    }
+
+    /**
+     Create a cursor positioned at the root node that can also change the members
+     of the node it is at.
+     */
+    pub fn cursor_mut(&mut self) -> CursorMut<'_, T, P> {
+        CursorMut::new(self)
+    }
+
+    /**
+     Create a cursor positioned at the smallest node that entirely contains `rect`,
+     from which its ancestors can be visited with `Cursor::ascend`.
+     Returns `None` if `rect` is not within the root node.
+     */
+    pub fn cursor_enclosing(&self, rect: &Rect) -> Option<Cursor<'_>> {
+        let mut cursor = Cursor::new(self);
+        if cursor.descend_to_enclosing(rect) {
+            Some(cursor)
+        } else {
+            None
+        }
+    }
+
+    /**
+     Create a cursor positioned at the smallest node that contains both `rect1` and `rect2`,
+     i.e. their lowest common ancestor. Returns `None` if either is not within the root node.
+     */
+    pub fn cursor_enclosing_both(&self, rect1: &Rect, rect2: &Rect) -> Option<Cursor<'_>> {
+        self.cursor_enclosing(&rect1.union(rect2))
+    }
+
+    /**
+     Summarize the tree's depth, leaf occupancy, and overlaps, e.g. for index health monitoring.
+     */
+    pub fn report(&self) -> TreeReport {
+        TreeReport::new(self)
+    }
+
+    /**
+     Count the tree's nodes, leaves, and members, and find its depth and mean leaf
+     occupancy. Much cheaper than `report`, which also looks for overlaps.
+     */
+    pub fn stats(&self) -> TreeStats {
+        TreeStats::new(self)
+    }
+
+    /**
+     Flatten the tree into packed, index-linked node and item arrays for a GPU.
+     */
+    pub fn flatten(&self) -> FlatTree {
+        FlatTree::new(self)
+    }
+
+    /**
+     Check the tree's structural invariants, returning a description of the first
+     violation found, e.g. to catch index corruption close to where it happens.
+     */
+    pub fn validate(&self) -> Result<(), String> {
+        self.root.validate_node()?;
+        self.root.validate_spanning_members(self.config.placement)?;
+
+        let members = self.members().len();
+        if self.len != members {
+            return Err(format!("tree caches {} members, but holds {}", self.len, members))
+        }
+
+        if !self.config.allow_overlap {
+            for rect in self.members().iter() {
+                if let Some(other) = self.query(rect).iter().find(|other| *other != rect && other.overlaps(rect)) { return Err(format!("members {:?} and {:?} overlap", rect, other)) }
+            }
+        }
+
+        Ok(())
+    }
+
+    /**
+     Create a tree with the given root node and settings, and no members counted.
+     */
+    fn with_root(root: Node, config: Config, policy: P) -> QuadTree<T, P> {
+        QuadTree { root, config, policy, restructure_pending: false, next_sequence: 0, len: 0, values: HashMap::new() }
+    }
+
+    /**
+     Create a quadtree with only a specified size and position.
+     */
+    fn new_with_size(origin: Point, size: Size, config: Config, policy: P) -> QuadTree<T, P> {
+        QuadTree::with_root(Node::new_empty(Rect::new(origin, size)), config, policy)
+    }
+
+    /**
+     Insert a rectangle into the quadtree. If `to_insert` overlaps another rectangle
+     already in the tree, the return value will be (false, self).
+     If the root node is zero-sized, the resulting tree will have a square root node
+     large enough to hold `to_insert`.
+     */
+    pub fn insert_rect(self, to_insert: Rect) -> (bool, QuadTree<T, P>) {
+        self.insert_rect_with_priority(to_insert, 0)
+    }
+
+    /**
+     Insert a rectangle with the given priority, e.g. its z-index, into the quadtree.
+     Otherwise the same as `insert_rect`.
+     */
+    pub fn insert_rect_with_priority(self, to_insert_rect: Rect, priority: i32) -> (bool, QuadTree<T, P>) {
+        let mut tree = self;
+        let inserted = tree.try_insert(to_insert_rect, priority).is_ok();
+
+        (inserted, tree)
+    }
+
+    /**
+     Insert a rectangle in place, e.g. into a tree kept inside another struct, returning
+     false if it was rejected. Otherwise the same as `insert_rect`.
+     */
+    pub fn insert(&mut self, rect: Rect) -> bool {
+        self.try_insert(rect, 0).is_ok()
+    }
+
+    /**
+     Insert a rectangle with the given priority in place, reporting why it was
+     rejected if it could not be inserted. Otherwise the same as `insert_rect_with_priority`.
+     */
+    pub fn try_insert(&mut self, rect: Rect, priority: i32) -> Result<(), Error> {
+        let sequence = self.next_sequence;
+        let result = self.insert_item(Item { rect, priority, sequence });
+
+        self.next_sequence = if result.is_ok() { sequence + 1 } else { sequence };
+        result
+    }
+
+    /**
+     Insert a rect that intersects the root without growing the root to contain it, as
+     when building one cell of a larger tree. Otherwise the same as `try_insert`.
+     */
+    pub(crate) fn try_insert_in_root(&mut self, rect: Rect, priority: i32) -> Result<(), Error> {
+        if !rect.is_valid() {
+            return Err(Error::InvalidRect(rect))
+        }
+        if !self.root.rect.intersects(&rect) {
+            return Err(Error::CannotGrow(rect))
+        }
+
+        self.restructure();
+        if let Some(err) = self.find_conflict(&rect) { return Err(err) }
+
+        let sequence = self.next_sequence;
+        self.place_item(Item { rect, priority, sequence });
+        self.next_sequence = sequence + 1;
+
+        Ok(())
+    }
+
+    /**
+     Create a tree with a root with bounds `bounds` holding `items`, keeping their
+     sequence numbers, and numbering later inserts from `next_sequence`, e.g. to restore
+     a saved tree. Fails if an item can't be inserted.
+     */
+    pub(crate) fn from_items(bounds: Rect, config: Config, policy: P, items: &[Item], next_sequence: u64) -> Result<QuadTree<T, P>, Error> {
+        let mut tree = QuadTree::new_with_size(bounds.origin, bounds.size, config, policy);
+        for item in items.iter() {
+            tree.insert_item(*item)?;
+        }
+
+        tree.next_sequence = items.iter().fold(next_sequence, |next, item| cmp::max(next, item.sequence + 1));
+        Ok(tree)
+    }
+
+    /**
+     Get the sequence number the next inserted member will be given.
+     */
+    pub(crate) fn next_sequence(&self) -> u64 {
+        self.next_sequence
+    }
+
+    /**
+     Insert `item` as is, keeping its sequence number.
+     */
+    fn insert_item(&mut self, item: Item) -> Result<(), Error> {
+        let rect = item.rect;
+        if !rect.is_valid() {
+            return Err(Error::InvalidRect(rect))
+        }
+
+        self.restructure();
+        if let Some(err) = self.find_conflict(&rect) { return Err(err) }
+
+        self.grow_to_contain(&rect)?;
+
+        if self.config.placement == Placement::Reject && self.root.spans_children(&rect) {
+            return Err(Error::SpansLeaves(rect))
+        }
+
+        self.place_item(item);
+
+        Ok(())
+    }
+
+    /**
+     Store `item` in the leaves it belongs in, without checking that it may be inserted.
+     */
+    fn place_item(&mut self, item: Item) {
+        let bounds = self.root.rect;
+        let root = mem::replace(&mut self.root, Node::new_empty(bounds));
+        self.root = root.insert_rect_if_intersects(item, 0, &self.config, &self.policy);
+        self.len += 1;
+    }
+
+    /**
+     Insert a point with the given priority, e.g. a map marker alongside the extents
+     of other features. Otherwise the same as `try_insert`.
+     */
+    pub fn insert_point(&mut self, point: Point, priority: i32) -> Result<(), Error> {
+        self.try_insert(Rect::new(point, Size::new(0., 0.)), priority)
+    }
+
+    /**
+     Insert each rect from `rects`, returning a result per rect in the same order:
+     the rect itself, which serves as its handle, or why it was rejected. Rejected
+     rects don't stop the rest of the batch. The root is grown once to fit the whole
+     batch up front, rather than once per far-away rect, so it may end up larger than
+     the rects that were actually inserted need.
+     */
+    pub fn insert_many<I: IntoIterator<Item = Rect>>(&mut self, rects: I) -> Vec<Result<Rect, Error>> {
+        let rects: Vec<Rect> = rects.into_iter().collect();
+        self.restructure();
+
+        let mut valid = rects.iter().filter(|rect| rect.is_valid());
+        if let Some(first) = valid.next() {
+            let bounds = valid.fold(*first, |bounds, rect| bounds.union(rect));
+            // If the whole batch can't be reached, each rect is grown to, or
+            // rejected, individually below.
+            let _ = self.grow_to_contain(&bounds);
+        }
+
+        rects.iter().map(|rect| self.try_insert(*rect, 0).map(|()| *rect)).collect()
+    }
+
+    /**
+     Insert every rect in `rects`, or none of them. Each rect is checked as `try_insert`
+     would, against the tree's members, its settings, and the rest of the batch. If any
+     rect is rejected, the rects already inserted are removed again, the root keeps its
+     bounds, and the index of each rejected rect is returned with the reason, e.g. so a
+     document can be ingested atomically.
+     */
+    pub fn insert_all_or_nothing(&mut self, rects: &[Rect]) -> Result<(), Vec<(usize, Error)>> {
+        let (bounds, next_sequence) = (self.root.rect, self.next_sequence);
+        let mut inserted = Vec::new();
+        let mut failures = Vec::new();
+        for (i, rect) in rects.iter().enumerate() {
+            match self.try_insert(*rect, 0) {
+                Ok(()) => inserted.push(*rect),
+                Err(err) => failures.push((i, err)),
+            }
+        }
+
+        if failures.is_empty() {
+            return Ok(())
+        }
+
+        self.remove_many(&inserted);
+        if self.root.rect != bounds {
+            self.rebuild_with_bounds(bounds);
+        }
+        self.next_sequence = next_sequence;
+
+        Err(failures)
+    }
+
+    /**
+     Find the reason `rect` can't be inserted, if any: it is already a member, or
+     it overlaps members and the tree doesn't allow overlap, in which case every
+     member it overlaps is reported.
+     */
+    fn find_conflict(&self, rect: &Rect) -> Option<Error> {
+        let rects_in_children = self.rects_in_child_nodes_intersected_by_rect(rect);
+        if rects_in_children.contains(rect) {
+            return Some(Error::Duplicate(*rect))
+        }
+
+        if self.config.allow_overlap {
+            return None
+        }
+
+        let overlapping: Vec<Rect> = rects_in_children.into_iter().filter(|member| member.overlaps(rect)).collect();
+        if overlapping.is_empty() { None } else { Some(Error::Overlap(overlapping)) }
+    }
+
+    /**
+     Get the tree's current settings.
+     */
+    pub fn config(&self) -> Config {
+        self.config
+    }
+
+    /**
+     Get the policy deciding when the tree's leaves are split.
+     */
+    pub fn policy(&self) -> &P {
+        &self.policy
+    }
+
+    /**
+     Change the tree's settings, e.g. to retune a long-lived index as its data changes.
+     The tree is restructured to match the new settings lazily, on the next insert or
+     call to `restructure`. Queries are answered correctly in the meantime.
+     Changing the overlap policy never removes existing members.
+     */
+    pub fn set_config(&mut self, config: Config) {
+        let config = Config { leaf_capacity: cmp::max(config.leaf_capacity, 1), ..config };
+        if config != self.config {
+            self.config = config;
+            self.restructure_pending = true;
+        }
+    }
+
+    /**
+     Rebuild the tree to match its current settings, if they have changed since the
+     tree was last built. The root keeps its bounds.
+     */
+    pub fn restructure(&mut self) {
+        if !self.restructure_pending {
+            return
+        }
+
+        self.rebuild_with_bounds(self.root.rect);
+    }
+
+    /**
+     Rebuild the tree with a root with bounds `bounds`, which must contain every member.
+     */
+    fn rebuild_with_bounds(&mut self, bounds: Rect) {
+        let len = self.len;
+        let items = self.remove_where(|_| true);
+        let mut root = Node::new_empty(bounds);
+        for item in items.into_iter() {
+            root = root.insert_rect_if_intersects(item, 0, &self.config, &self.policy);
+        }
+
+        self.root = root;
+        self.restructure_pending = false;
+        self.len = len;
+    }
+
+    /**
+     Grow the root node until it contains `rect`, e.g. to pre-grow the tree to known
+     future extents in one step rather than on the first far-away insert.
+     An empty zero-sized root is replaced by a square root starting at `rect`'s origin.
+     Fails with `CannotGrow` if the root can't be grown to reach `rect`.
+     */
+    pub fn expand_to_include(&mut self, rect: &Rect) -> Result<(), Error> {
+        self.grow_to_contain(rect)
+    }
+
+    /**
+     Grow the root node until it contains `rect`, as the tree's `Growth` setting says.
+     With `Doubling`, the existing root becomes the descendant of the new root that puts
+     `rect` within its bounds, and the number of doublings is computed up front rather
+     than searched for. Fails with `CannotGrow`, leaving the tree unchanged, if `rect`
+     isn't finite, the root is zero-sized but not empty, or the root would have to grow
+     more than `MAX_ROOT_DOUBLINGS` times over.
+     */
+    fn grow_to_contain(&mut self, rect: &Rect) -> Result<(), Error> {
+        if self.root.rect.contains(rect) {
+            return Ok(())
+        }
+
+        if self.root.rect.width() == 0.0 && self.root.is_empty_leaf() {
+            self.root = Node::new_empty(Rect::new(rect.origin, QuadTree::root_size_for(rect)));
+            return Ok(())
+        }
+
+        let width = self.root.rect.width();
+        let height = self.root.rect.height();
+        if !(width > 0. && height > 0.) {
+            return Err(Error::CannotGrow(*rect))
+        }
+
+        // Count the root-sized cells needed before and after the root's origin along
+        // each axis. The new root must span that many cells along its wider axis,
+        // and the old root sits at column `cells_left` and row `cells_above` within it.
+        let bounds = self.root.rect.union(rect);
+        let origin = self.root.rect.origin;
+        let cells_left = ((origin.x - bounds.min_x()) / width).ceil();
+        let cells_right = ((bounds.max_x() - origin.x) / width).ceil();
+        let cells_above = ((origin.y - bounds.min_y()) / height).ceil();
+        let cells_below = ((bounds.max_y() - origin.y) / height).ceil();
+        let cells = (cells_left + cells_right).max(cells_above + cells_below);
+
+        if !cells.is_finite() || cells > 2f64.powi(MAX_ROOT_DOUBLINGS) {
+            return Err(Error::CannotGrow(*rect))
+        }
+
+        if self.config.growth != Growth::Doubling {
+            let bounds = self.grown_bounds(rect);
+            let limit = width.max(height) * 2f64.powi(MAX_ROOT_DOUBLINGS);
+            if !(bounds.width().is_finite() && bounds.height().is_finite()) || bounds.width().max(bounds.height()) > limit {
+                return Err(Error::CannotGrow(*rect))
+            }
+
+            self.rebuild_with_bounds(bounds);
+            return Ok(())
+        }
+
+        let mut doublings = 0;
+        while 2f64.powi(doublings) < cells {
+            doublings += 1;
+        }
+
+        // The old root's new siblings start out empty, so the members on its edges are
+        // stored in them afterwards.
+        let edge_items = self.root.items_on_boundary(self.config.placement);
+        let mut node = mem::replace(&mut self.root, Node::new_empty(bounds));
+        for level in 0..doublings {
+            // Bit `level` of the old root's column and row says which half of the
+            // new node at this level the current node lies in.
+            let scale = 2f64.powi(level);
+            let column = (cells_left / scale).floor() % 2.;
+            let row = (cells_above / scale).floor() % 2.;
+
+            let size = node.rect.size;
+            let node_origin = node.rect.origin;
+            let empty = |c: f64, r: f64| {
+                let cell_origin = Point::new(node_origin.x + (c - column) * size.width,
+                                            node_origin.y + (r - row) * size.height);
+                Node::new_empty(Rect::new(cell_origin, size))
+            };
+
+            let (tl, tr, br, bl) =
+            match (column == 1., row == 1.) {
+                (false, false) => (node, empty(1., 0.), empty(1., 1.), empty(0., 1.)),
+                (true, false) => (empty(0., 0.), node, empty(1., 1.), empty(0., 1.)),
+                (true, true) => (empty(0., 0.), empty(1., 0.), node, empty(0., 1.)),
+                (false, true) => (empty(0., 0.), empty(1., 0.), empty(1., 1.), node),
+            };
+
+            node = Node::new_with_children(Rect::new(tl.rect.origin, size * 2.),
+                Box::new(tl), Box::new(tr), Box::new(br), Box::new(bl));
+        }
+
+        self.root = edge_items.into_iter().fold(node, |node, item| node.fill_in_item(item, 0, &self.config, &self.policy));
+        Ok(())
+    }
+
+    /**
+     The bounds of a root grown to contain `rect` by one of the rebuilding growth
+     strategies. Contains the current root or its members, so no member is lost.
+     */
+    fn grown_bounds(&self, rect: &Rect) -> Rect {
+        match self.config.growth {
+            Growth::Doubling | Growth::Fit => self.root.rect.union(rect),
+            Growth::Centered => {
+                let content = self.content_bounds().map_or(*rect, |content| content.union(rect));
+                let side = content.width().max(content.height()).max(self.root.rect.width().max(self.root.rect.height()) / 2.) * 2.;
+                let center = content.center();
+
+                Rect::new(Point::new(center.x - side / 2., center.y - side / 2.), Size::new(side, side))
+            },
+            Growth::PowerOfTwo => {
+                let content = self.root.rect.union(rect);
+                let mut side = 2f64.powi(content.width().max(content.height()).log2().ceil() as i32);
+                loop {
+                    let origin = Point::new((content.min_x() / side).floor() * side, (content.min_y() / side).floor() * side);
+                    let bounds = Rect::new(origin, Size::new(side, side));
+                    if bounds.contains(&content) || !side.is_finite() {
+                        return bounds
+                    }
+                    side *= 2.;
+                }
+            },
+        }
+    }
+
+    /**
+     Replace the member `key` with `rect`, or insert `rect` if `key` is not a member,
+     e.g. for data feeds that re-send updated bounding boxes for known objects.
+     The replacement keeps `key`'s place in insertion order. If `rect` can't be
+     inserted for any reason `try_insert` gives, the return value will be
+     (false, self) and `key` is left in place, as it was.
+     */
+    pub fn insert_or_replace(self, key: &Rect, rect: Rect, priority: i32) -> (bool, QuadTree<T, P>) {
+        let mut tree = self;
+        let inserted = tree.try_replace(key, rect, Some(priority)).is_ok();
+
+        (inserted, tree)
+    }
+
+    /**
+     Replace the member `key` with `rect`, keeping `key`'s place in insertion order, and
+     its priority unless `priority` is given. Inserts `rect` if `key` is not a member.
+     If `rect` is rejected, `key` is left in place, as it was.
+     */
+    pub(crate) fn try_replace(&mut self, key: &Rect, rect: Rect, priority: Option<i32>) -> Result<(), Error> {
+        let original = self.take_item(key);
+        let sequence = original.map_or(self.next_sequence, |item| item.sequence);
+        let priority = priority.or(original.map(|item| item.priority)).unwrap_or(0);
+
+        let result = self.insert_item(Item { rect, priority, sequence });
+        match original {
+            // Nothing was placed, so the original's leaves are still free.
+            Some(item) if result.is_err() => self.place_item(item),
+            Some(_) => {
+                if let Some(value) = self.values.remove(&rect_key(key)) {
+                    self.values.insert(rect_key(&rect), value);
+                }
+            },
+            None if result.is_ok() => self.next_sequence += 1,
+            None => (),
+        }
+
+        result
+    }
+
+    /**
+     Remove every member and child, keeping the root's bounds and the tree's settings,
+     e.g. to reuse a tree across simulation frames. Insertion order starts over.
+     */
+    pub fn clear(&mut self) {
+        self.root = Node::new_empty(self.root.rect);
+        self.restructure_pending = false;
+        self.next_sequence = 0;
+        self.len = 0;
+        self.values.clear();
+    }
+
+    /**
+     Remove every member, yielding each once, e.g. to move them into another structure.
+     The tree is emptied as by `clear` when this is called, so members the iterator
+     hasn't yielded when it is dropped are dropped too, as are all the members' values.
+     */
+    pub fn drain(&mut self) -> Drain {
+        let bounds = self.root.rect;
+        let root = mem::replace(&mut self.root, Node::new_empty(bounds));
+        let placement = self.config.placement;
+        self.clear();
+
+        Drain { root: root.rect, placement, nodes: vec![root], items: Vec::new().into_iter() }
+    }
+
+    /**
+     Remove and return every member intersecting `region`, e.g. to delete everything
+     in an editor selection. Subtrees left empty are collapsed.
+     */
+    pub fn remove_in(&mut self, region: &Rect) -> Vec<Rect> {
+        let removed: Vec<Rect> = self.remove_where(|item| item.rect.intersects(region)).into_iter().map(|item| item.rect).collect();
+        self.forget_values(removed.iter());
+
+        removed
+    }
+
+    /**
+     Remove and return every member lying entirely inside `region`.
+     Subtrees left empty are collapsed.
+     */
+    pub fn remove_contained(&mut self, region: &Rect) -> Vec<Rect> {
+        let removed: Vec<Rect> = self.remove_where(|item| region.contains(&item.rect)).into_iter().map(|item| item.rect).collect();
+        self.forget_values(removed.iter());
+
+        removed
+    }
+
+    /**
+     Keep only the members for which `keep` returns true, like `Vec::retain`, calling it
+     once for each member however many leaves hold it. Members are visited leaf by leaf
+     rather than in insertion order. Subtrees left empty are collapsed.
+     */
+    pub fn retain(&mut self, mut keep: impl FnMut(&Rect) -> bool) {
+        let mut dropped = HashSet::new();
+        self.query_each(&self.root.rect, |rect| {
+            if !keep(rect) {
+                dropped.insert(rect_key(rect));
+            }
+        });
+
+        if !dropped.is_empty() {
+            self.remove_where(|item| dropped.contains(&rect_key(&item.rect)));
+            self.values.retain(|key, _| !dropped.contains(key));
+        }
+    }
+
+    /**
+     Move every member intersecting `region` by `delta`, e.g. to drag an editor selection.
+     Moved members keep their priority and insertion order. A member that can't be
+     inserted at its new position, e.g. because it would overlap a member outside the
+     selection, or one that is itself blocked, is left where it was, and its rect is
+     returned.
+     */
+    pub fn translate_in(&mut self, region: &Rect, delta: Point) -> Vec<Rect> {
+        let moving = self.remove_where(|item| item.rect.intersects(region));
+        let mut blocked = vec![false; moving.len()];
+
+        loop {
+            // Put back each member blocked at its new position, until putting one back
+            // blocks no other.
+            let mut changed = true;
+            while changed {
+                changed = false;
+                for (i, item) in moving.iter().enumerate() {
+                    let rect = item.rect.translate(delta);
+                    if !blocked[i] && (!rect.is_valid() || self.find_conflict(&rect).is_some()) {
+                        blocked[i] = true;
+                        self.place_item(*item);
+                        changed = true;
+                    }
+                }
+            }
+
+            let mut moved = Vec::new();
+            let failed = moving.iter().enumerate().filter(|&(i, _)| !blocked[i]).find(|(_, item)| {
+                let rect = item.rect.translate(delta);
+                let inserted = self.insert_item(Item { rect, ..**item }).is_ok();
+                if inserted {
+                    moved.push(rect);
+                }
+                !inserted
+            });
+
+            match failed {
+                None => break,
+                Some((i, item)) => {
+                    // Undo the moves so far, which may have taken the member's old place.
+                    self.remove_many(&moved);
+                    blocked[i] = true;
+                    self.place_item(*item);
+                },
+            }
+        }
+
+        // Values are taken from every moved member before any is put back, as a member
+        // may have moved to another's old position.
+        let moved_values: Vec<([u64; 4], T)> = moving.iter().zip(blocked.iter()).filter(|&(_, blocked)| !blocked)
+            .filter_map(|(item, _)| self.values.remove(&rect_key(&item.rect)).map(|value| (rect_key(&item.rect.translate(delta)), value)))
+            .collect();
+        self.values.extend(moved_values);
+
+        moving.iter().zip(blocked).filter(|&(_, blocked)| blocked).map(|(item, _)| item.rect).collect()
+    }
+
+    /**
+     Remove every member for which `should_remove` returns true, returning the removed
+     items. Nodes whose children all end up empty are collapsed into empty leaves.
+     */
+    fn remove_where(&mut self, should_remove: impl FnMut(&Item) -> bool) -> Vec<Item> {
+        self.remove_in_nodes(|_| true, should_remove)
+    }
+
+    /**
+     Remove the members for which `should_remove` returns true from the nodes for which
+     `visit` returns true, returning each removed item once. Children are only visited
+     if `visit` accepts them too.
+     */
+    fn remove_in_nodes(&mut self, mut visit: impl FnMut(&Rect) -> bool, mut should_remove: impl FnMut(&Item) -> bool) -> Vec<Item> {
+        let mut removed = Vec::new();
+        self.root.remove_where_into(&self.config, &mut visit, &mut should_remove, &mut removed);
+
+        // A member spanning several leaves is removed from each of them.
+        let mut seen = HashSet::new();
+        removed.retain(|item| seen.insert(rect_key(&item.rect)));
+        self.len -= removed.len();
+
+        removed
+    }
+
+    /**
+     Remove the member `rect` from every leaf holding it, returning false if it wasn't
+     a member. Only nodes intersecting `rect` are visited, and subtrees left empty are
+     collapsed, as are siblings that fit in one leaf if the tree coalesces.
+     */
+    pub fn remove_rect(&mut self, rect: &Rect) -> bool {
+        let removed = self.take_item(rect).is_some();
+        if removed {
+            self.values.remove(&rect_key(rect));
+        }
+
+        removed
+    }
+
+    /**
+     Remove the member `rect` as `remove_rect` does, returning its item.
+     */
+    fn take_item(&mut self, rect: &Rect) -> Option<Item> {
+        self.remove_in_nodes(|node_rect| node_rect.intersects(rect), |item| item.rect == *rect).pop()
+    }
+
+    /**
+     Drop the values of the removed members `rects`.
+     */
+    fn forget_values<'r>(&mut self, rects: impl Iterator<Item = &'r Rect>) {
+        if !self.values.is_empty() {
+            for rect in rects {
+                self.values.remove(&rect_key(rect));
+            }
+        }
+    }
+
+    /**
+     Remove every member listed in `rects`, returning the ones that were members, e.g.
+     to expire a batch of items. Only nodes intersecting some listed rect are visited,
+     all in one pass, and emptied nodes are collapsed once as the pass unwinds rather
+     than after each removal.
+     */
+    pub fn remove_many(&mut self, rects: &[Rect]) -> Vec<Rect> {
+        let keys: HashSet<[u64; 4]> = rects.iter().map(rect_key).collect();
+        let removed: Vec<Rect> = self.remove_in_nodes(|node_rect| rects.iter().any(|rect| node_rect.intersects(rect)),
+                                                      |item| keys.contains(&rect_key(&item.rect)))
+            .into_iter().map(|item| item.rect).collect();
+        self.forget_values(removed.iter());
+
+        removed
+    }
+
+    /**
+     Find all of the rects in `self`, or its children, that are in nodes
+     intersected by the given rect. A rect stored in several of those nodes
+     is only returned once.
+     */
+    pub fn rects_in_child_nodes_intersected_by_rect(&self, test_rect: &Rect) -> Vec<Rect> {
+        self.members_matching(test_rect, |_| true)
+    }
+
+    /**
+     Find the leaf nodes that `ray` passes through, in the order the ray visits them.
+     Leaves holding members are occupied cells and empty leaves are free,
+     so this walks a sensor beam through the occupancy decomposition.
+     */
+    pub fn leaves_along_ray(&self, ray: &Ray) -> Vec<&Node> {
+        let mut leaves = Vec::new();
+        if self.root.rect.ray_intersection(ray).is_some() {
+            self.root.push_leaves_along_ray(ray, &mut leaves);
+        }
+
+        leaves
+    }
+
+    /**
+     Find the members `segment` passes through, ordered by where it enters them,
+     e.g. to fade out whatever blocks a sight line.
+     */
+    pub fn segment_crossings(&self, segment: &Segment) -> Vec<SegmentCrossing> {
+        let mut crossings = Vec::new();
+        let crossed = self.members_in_nodes(|node_rect| node_rect.clip_segment(segment).is_some(),
+                                            |rect| rect.clip_segment(segment).is_some());
+        for rect in crossed.iter() {
+            if let Some((enter, exit)) = rect.clip_segment(segment) { crossings.push(SegmentCrossing { rect: *rect, enter, exit }) }
+        }
+
+        crossings.sort_by(|a, b| a.enter.partial_cmp(&b.enter).unwrap_or(Ordering::Equal));
+        crossings
+    }
+
+    /**
+     Find the first member `ray` hits, e.g. for a bullet or a line-of-sight check.
+     Nodes are visited in the order the ray enters them, and the search stops at the
+     first node entered beyond the nearest hit found, rather than enumerating every hit.
+     */
+    pub fn raycast_first(&self, ray: &Ray) -> Option<RayHit> {
+        let mut best = None;
+        if self.root.rect.ray_intersection(ray).is_some() {
+            self.root.find_first_ray_hit(ray, &mut best);
+        }
+
+        best
+    }
+
+    /**
+     Iterate over every leaf node, with its bounds and whether it holds a member.
+     */
+    pub fn leaves(&self) -> Leaves<'_> {
+        Leaves { nodes_to_visit: vec![&self.root] }
+    }
+
+    /**
+     Find a large empty rect within `region`, for placing new objects, labels, or spawn points.
+     This is approximate: the result is the largest empty leaf clipped to `region`, so a
+     larger empty area spanning several leaves may exist. Only the part of `region` within
+     the root node is considered. Returns `None` if no empty leaf overlaps `region`.
+     */
+    pub fn largest_empty_rect_in(&self, region: &Rect) -> Option<Rect> {
+        let mut largest: Option<Rect> = None;
+
+        for leaf in self.leaves().filter(|leaf| !leaf.is_occupied()) {
+            let clipped = match leaf.rect.intersect(region) {
+                Some(clipped) => clipped,
+                None => continue,
+            };
+
+            let area = clipped.size.area();
+            let is_larger = match largest {
+                Some(rect) => area > rect.size.area(),
+                None => area > 0.,
+            };
+
+            if is_larger {
+                largest = Some(clipped);
+            }
+        }
+
+        largest
+    }
+
+    /**
+     Find every member stored in the tree. Members spanning several leaves are only
+     returned once.
+     */
+    pub fn members(&self) -> Vec<Rect> {
+        self.root.members()
+    }
+
+    /**
+     Get the smallest rect containing every member, or `None` if the tree is empty.
+     Unlike the root's bounds, which grow by doubling, this fits the contents tightly.
+     */
+    pub fn content_bounds(&self) -> Option<Rect> {
+        QuadTree::bounds_of(&self.members())
+    }
+
+    /**
+     Group the members by the tree cell their center lies in, `depth` levels below the
+     root, e.g. to draw one marker per cluster at a low zoom level. A leaf shallower
+     than `depth` forms a single cell, and empty cells form no cluster. A member's
+     cluster id is the index of the cluster listing it.
+     */
+    pub fn cluster(&self, depth: usize) -> Vec<Cluster> {
+        let mut clusters = Vec::new();
+        let mut assigned = HashSet::new();
+        self.root.push_clusters(depth, &mut clusters, &mut assigned);
+
+        clusters
+    }
+
+    /**
+     Summarize each tree cell `depth` levels below the root, in top left to bottom left
+     order at each level, e.g. for choropleth rendering at a low zoom level. A leaf
+     shallower than `depth` forms a single cell. A member spanning several cells is
+     counted in each of them, and empty cells are included with a count of 0.
+     */
+    pub fn summaries_at_depth(&self, depth: usize) -> Vec<CellSummary> {
+        let mut summaries = Vec::new();
+        let mut nodes_to_visit = vec![(&self.root, depth)];
+
+        while let Some((node, depth)) = nodes_to_visit.pop() {
+            match node.elements {
+                Elements::Children(ref tl, ref tr, ref br, ref bl) if depth > 0 => {
+                    // Push in reverse so the top left child is summarized first.
+                    for child in [&**bl, &**br, &**tr, &**tl].iter() {
+                        nodes_to_visit.push((child, depth - 1));
+                    }
+                },
+                _ => summaries.push(node.summarize(self.config.placement)),
+            }
+        }
+
+        summaries
+    }
+
+    /**
+     Look up the stored item for the member `rect`, which serves as the item's handle,
+     to read its priority. Only nodes intersecting `rect` are visited.
+     */
+    pub fn get(&self, rect: &Rect) -> Option<Item> {
+        self.root.get(rect)
+    }
+
+    /**
+     Check if `rect` is exactly a member of the tree, e.g. to skip duplicates on ingest.
+     */
+    pub fn contains(&self, rect: &Rect) -> bool {
+        self.get(rect).is_some()
+    }
+
+    /**
+     Find the path from the root to the node holding the member `rect`. For a member
+     spanning several leaves this is the smallest node containing all of them.
+     Returns `None` if `rect` is not a member.
+     */
+    pub fn locate(&self, rect: &Rect) -> Option<NodePath> {
+        if !self.contains(rect) {
+            return None;
+        }
+
+        let mut quadrants = Vec::new();
+        let mut node = &self.root;
+
+        loop {
+            let next = Quadrant::ALL.iter()
+                .filter_map(|&quadrant| node.child(quadrant).map(|child| (quadrant, child)))
+                .find(|(_, child)| child.rect.contains(rect));
+
+            match next {
+                Some((quadrant, child)) => {
+                    quadrants.push(quadrant);
+                    node = child;
+                },
+                None => break,
+            }
+        }
+
+        Some(NodePath { quadrants })
+    }
+
+    /**
+     Change the priority of the member `rect`. Returns false if `rect` is not a member.
+     */
+    pub fn set_priority(&mut self, rect: &Rect, priority: i32) -> bool {
+        self.root.set_priority(rect, priority)
+    }
+
+    /**
+     Replace every member's priority with `f` of its item, keeping the exact node
+     structure rather than rebuilding the tree. `f` is called once for each stored copy
+     of a member spanning several leaves, so it should give the same result each time.
+     */
+    pub fn map_priorities(mut self, f: impl Fn(&Item) -> i32) -> QuadTree<T, P> {
+        self.root.map_priorities_in(&f);
+
+        self
+    }
+
+    /**
+     Insert `rect` with the given priority as `try_insert` does, and give it `value`,
+     e.g. the id or record of the feature it bounds. The value is stored once, however
+     many leaves hold the member, and is dropped when the member is removed.
+     */
+    pub fn try_insert_with(&mut self, rect: Rect, priority: i32, value: T) -> Result<(), Error> {
+        self.try_insert(rect, priority)?;
+        self.values.insert(rect_key(&rect), value);
+
+        Ok(())
+    }
+
+    /**
+     Get the value of the member `rect`, or `None` if it isn't a member or was
+     inserted without one.
+     */
+    pub fn value(&self, rect: &Rect) -> Option<&T> {
+        self.values.get(&rect_key(rect))
+    }
+
+    /**
+     Get the value of the member `rect` mutably. Otherwise the same as `value`.
+     */
+    pub fn value_mut(&mut self, rect: &Rect) -> Option<&mut T> {
+        self.values.get_mut(&rect_key(rect))
+    }
+
+    /**
+     Transform every member's value with `f`, keeping the exact node structure rather
+     than rebuilding the tree, e.g. to convert records to another representation.
+     `f` is called once for each member with a value.
+     */
+    pub fn map<U>(self, mut f: impl FnMut(T) -> U) -> QuadTree<U, P> {
+        let QuadTree { root, config, policy, restructure_pending, next_sequence, len, values } = self;
+        let values = values.into_iter().map(|(key, value)| (key, f(value))).collect();
+
+        QuadTree { root, config, policy, restructure_pending, next_sequence, len, values }
+    }
+
+    /**
+     Find the members intersecting `region`.
+     */
+    pub fn query(&self, region: &Rect) -> Vec<Rect> {
+        self.query_with_mode(region, QueryMode::Exact)
+    }
+
+    /**
+     Find the members intersecting `region`, or with `Broad` mode, every member
+     stored in a node that `region` intersects.
+     */
+    pub fn query_with_mode(&self, region: &Rect, mode: QueryMode) -> Vec<Rect> {
+        match mode {
+            QueryMode::Broad => self.rects_in_child_nodes_intersected_by_rect(region),
+            QueryMode::Exact => self.members_matching(region, |rect| region.intersects(rect)),
+        }
+    }
+
+    /**
+     Find the members containing `point`, ordered topmost first by the z value
+     `z_order` gives each member, e.g. for dispatching pointer events in a UI.
+     */
+    pub fn hit_test(&self, point: Point, z_order: impl Fn(&Rect) -> f64) -> Vec<Rect> {
+        let point_rect = Rect::new(point, Size::new(0., 0.));
+        let hits = self.members_matching(&point_rect, |rect| rect.contains_point(&point));
+
+        let mut ordered: Vec<(f64, Rect)> = hits.into_iter().map(|rect| (z_order(&rect), rect)).collect();
+        ordered.sort_by(|&(z1, _), &(z2, _)| z2.partial_cmp(&z1).unwrap_or(Ordering::Equal));
+
+        ordered.into_iter().map(|(_, rect)| rect).collect()
+    }
+
+    /**
+     Find the distance from `point` to the closest point of any member, or `None` if
+     the tree is empty.
+     */
+    pub fn distance_to_nearest(&self, point: Point) -> Option<f64> {
+        self.nearest_item_to(&Rect::new(point, Size::new(0., 0.))).map(|(_, distance)| distance)
+    }
+
+    /**
+     Find the member closest to `rect`, measured between their closest points, e.g. the
+     obstacle nearest an agent's bounds. A member intersecting `rect` is at distance zero.
+     Returns `None` if the tree is empty.
+     */
+    pub fn nearest_to_rect(&self, rect: &Rect) -> Option<Rect> {
+        self.nearest_item_to(rect).map(|(item, _)| item.rect)
+    }
+
+    /**
+     Remove and return the member closest to `point`, with its priority, e.g. to
+     consume targets in a greedy matching loop. Returns `None` if the tree is empty.
+     */
+    pub fn pop_nearest(&mut self, point: Point) -> Option<Item> {
+        let (item, _) = self.nearest_item_to(&Rect::new(point, Size::new(0., 0.)))?;
+        self.remove_rect(&item.rect);
+
+        Some(item)
+    }
+
+    /**
+     Find the member closest to `rect`, and its distance.
+     */
+    fn nearest_item_to(&self, rect: &Rect) -> Option<(Item, f64)> {
+        self.traverse_best_first(|node_rect| Some(node_rect.distance_to(rect)),
+                                 |item| Some(item.rect.distance_to(rect)))
+            .next()
+    }
+
+    /**
+     Visit the members in increasing order of the score `score_item` gives them, e.g. to
+     find the nearest members by a custom cost function. Nodes are visited in increasing
+     order of the score `score_node` gives their bounds, which must never be more than
+     the score of any member stored in them, so that searching can stop as soon as enough
+     members have been taken from the iterator. Returning `None` from either function
+     skips the node or member. Each member is visited once, with its score.
+     */
+    pub fn traverse_best_first<N, I>(&self, mut score_node: N, score_item: I) -> BestFirst<'_, N, I>
+        where N: FnMut(&Rect) -> Option<f64>, I: FnMut(&Item) -> Option<f64> {
+        let mut queue = BinaryHeap::new();
+        if let Some(score) = score_node(&self.root.rect) {
+            queue.push(Scored { score, entry: ScoredEntry::Node(&self.root) });
+        }
+
+        BestFirst { queue, visited: HashSet::new(), score_node, score_item }
+    }
+
+    /**
+     Like `distance_to_nearest`, but negative when `point` lies inside a member, giving
+     how far it is from the edge of the member it lies deepest in, e.g. for sampling
+     a signed distance field.
+     */
+    pub fn signed_distance_to_nearest(&self, point: Point) -> Option<f64> {
+        let point_rect = Rect::new(point, Size::new(0., 0.));
+        let containing = self.members_matching(&point_rect, |rect| rect.contains_point(&point));
+        if containing.is_empty() {
+            return self.distance_to_nearest(point)
+        }
+
+        let depth = containing.iter().fold(0., |depth: f64, rect| {
+            let rect_depth = (point.x - rect.min_x()).min(rect.max_x() - point.x)
+                .min(point.y - rect.min_y()).min(rect.max_y() - point.y);
+            depth.max(rect_depth)
+        });
+
+        Some(-depth)
+    }
+
+    /**
+     Find the highest-priority member containing `point`, e.g. the item under the cursor.
+     Subtrees whose highest priority can't beat the best match found so far are skipped.
+     */
+    pub fn topmost_at(&self, point: Point) -> Option<Rect> {
+        let mut best: Option<Item> = None;
+        if self.root.rect.contains_point(&point) {
+            self.root.find_topmost_at(&point, &mut best);
+        }
+
+        best.map(|item| item.rect)
+    }
+
+    /**
+     Find the members that `from` would intersect at some point while being interpolated
+     to `to` with `Rect::lerp`, ordered by when they are first reached, e.g. to find
+     what an animated element passes over.
+     */
+    pub fn query_along_lerp(&self, from: &Rect, to: &Rect) -> Vec<Rect> {
+        let mut hits: Vec<(f64, Rect)> = self.query(&from.union(to)).into_iter()
+            .filter_map(|rect| from.lerp_contact(to, &rect).map(|t| (t, rect)))
+            .collect();
+        hits.sort_by(|&(t1, _), &(t2, _)| t1.partial_cmp(&t2).unwrap_or(Ordering::Equal));
+
+        hits.into_iter().map(|(_, rect)| rect).collect()
+    }
+
+    /**
+     Find the members lying entirely inside `region`, rather than merely intersecting it.
+     */
+    pub fn query_contained(&self, region: &Rect) -> Vec<Rect> {
+        self.members_matching(region, |rect| region.contains(rect))
+    }
+
+    /**
+     Find the members intersecting `region`, skipping `exclude` if it is a member,
+     e.g. so an entity checking for collisions doesn't find itself.
+     */
+    pub fn query_excluding(&self, region: &Rect, exclude: &Rect) -> Vec<Rect> {
+        self.query_filtered(region, |rect| rect != exclude)
+    }
+
+    /**
+     Find the members intersecting `region` for which `keep` returns true.
+     Members rejected by `keep` are skipped during traversal rather than returned.
+     */
+    pub fn query_filtered(&self, region: &Rect, keep: impl Fn(&Rect) -> bool) -> Vec<Rect> {
+        self.members_matching(region, |rect| region.intersects(rect) && keep(rect))
+    }
+
+    /**
+     Find at most `max_results` of the members intersecting `region`, stopping the
+     search as soon as that many are found, e.g. `query_n(region, 1)` to check if
+     anything is there at all. Which members are found first is unspecified.
+     */
+    pub fn query_n(&self, region: &Rect, max_results: usize) -> Vec<Rect> {
+        let mut rects = Vec::new();
+        if max_results == 0 {
+            return rects;
+        }
+
+        self.query_while(region, |rect| {
+            rects.push(*rect);
+            rects.len() < max_results
+        });
+
+        rects
+    }
+
+    /**
+     Call `visit` with each member intersecting `region` until it returns false, at
+     which point the search stops. Each member is visited once. Returns false if the
+     search was stopped early.
+     */
+    pub fn query_while(&self, region: &Rect, mut visit: impl FnMut(&Rect) -> bool) -> bool {
+        let mut visited = HashSet::new();
+        let mut nodes_to_check = Vec::new();
+        if self.root.rect.intersects(region) {
+            nodes_to_check.push(&self.root);
+        }
+
+        while let Some(node) = nodes_to_check.pop() {
+            match node.elements {
+                Elements::Children(ref tl, ref tr, ref br, ref bl) => {
+                    // Push in reverse so the top left child is visited first.
+                    for child in [&**bl, &**br, &**tr, &**tl] {
+                        if child.rect.intersects(region) {
+                            nodes_to_check.push(child);
+                        }
+                    }
+                },
+                Elements::Members(ref items) => {
+                    for item in items.iter() {
+                        if !region.intersects(&item.rect) || !visited.insert(rect_key(&item.rect)) {
+                            continue;
+                        }
+
+                        if !visit(&item.rect) {
+                            return false;
+                        }
+                    }
+                },
+                Elements::Empty => (),
+            }
+        }
+
+        true
+    }
+
+    /**
+     Append the members intersecting `region` to `out`, e.g. reusing one buffer for a
+     query every frame. No memory is allocated beyond growing `out`.
+     */
+    pub fn query_into(&self, region: &Rect, out: &mut Vec<Rect>) {
+        self.query_each(region, |rect| out.push(*rect));
+    }
+
+    /**
+     Call `visit` with each member intersecting `region`, once each, without allocating.
+     */
+    pub fn query_each(&self, region: &Rect, mut visit: impl FnMut(&Rect)) {
+        if self.root.rect.intersects(region) {
+            self.root.query_each_in(&self.root.rect, region, self.config.placement, &mut visit);
+        }
+    }
+
+    /**
+     Find the first `page_size` members intersecting `region`, with a token for
+     fetching the rest with `query_page_after`, e.g. to serve a large result set over
+     a paginated API. A page size of 0 is treated as 1.
+     */
+    pub fn query_page(&self, region: &Rect, page_size: usize) -> QueryPage {
+        self.query_page_after(&QueryToken { region: *region, path: Vec::new(), offset: 0 }, page_size)
+    }
+
+    /**
+     Find the next `page_size` members of a paged query, continuing where the page
+     that returned `token` left off. Leaves are visited in a fixed order, and a member
+     spanning several leaves is only returned from one of them, so no member is
+     returned twice. If the tree has been changed since `token` was returned, members
+     may be skipped or repeated, but the query still makes progress.
+     */
+    pub fn query_page_after(&self, token: &QueryToken, page_size: usize) -> QueryPage {
+        let mut rects = Vec::new();
+        let mut path = Vec::new();
+        let next = self.root.fill_page(self, token, Some(&token.path), &mut path, &mut rects, cmp::max(page_size, 1));
+
+        QueryPage { rects, next }
+    }
+
+    /**
+     Find the members in the geohash cell named by `prefix`, treating `x` as longitude and
+     `y` as latitude. Points are found if their geohash starts with `prefix`, and other
+     members if they intersect the cell. Fails if `prefix` isn't a valid geohash.
+     */
+    pub fn query_geohash(&self, prefix: &str) -> Result<Vec<Rect>, Error> {
+        let cell = geohash::decode(prefix)?;
+
+        Ok(self.members_matching(&cell, |rect| {
+            if rect.is_point() {
+                geohash::encode(rect.origin, prefix.len()).eq_ignore_ascii_case(prefix)
+            } else {
+                cell.intersects(rect)
+            }
+        }))
+    }
+
+    /**
+     Find the members within `margin` of `region`, i.e. intersecting `region` grown by
+     `margin` with rounded corners, as in a collision query with a skin width.
+     Nodes are pruned by their distance from `region`, so `region` needn't be inflated
+     beforehand. A negative margin shrinks `region` instead, and finds nothing if it
+     shrinks away entirely.
+     */
+    pub fn query_inflated(&self, region: &Rect, margin: f64) -> Vec<Rect> {
+        if margin < 0. {
+            let shrunk = region.inflate(margin);
+            if !shrunk.is_valid() {
+                return Vec::new()
+            }
+
+            return self.query(&shrunk)
+        }
+
+        self.members_in_nodes(|node_rect| node_rect.distance_to(region) <= margin,
+                              |rect| rect.distance_to(region) <= margin)
+    }
+
+    /**
+     Find the members that share a boundary with `rect` without overlapping its interior,
+     e.g. the tiles or rooms adjacent to a given one.
+     */
+    pub fn query_touching(&self, rect: &Rect) -> Vec<Rect> {
+        self.members_matching(rect, |member_rect| rect.touches(member_rect))
+    }
+
+    /**
+     Find the members standing in `relation` to `region`, e.g. `Relation::Within` for
+     the members lying inside it without merely touching its boundary.
+     */
+    pub fn query_related(&self, region: &Rect, relation: Relation) -> Vec<Rect> {
+        if relation == Relation::Disjoint {
+            self.members_in_nodes(|_| true, |rect| rect.relate(region) == relation)
+        } else {
+            self.members_matching(region, |rect| rect.relate(region) == relation)
+        }
+    }
+
+    /**
+     Find the members stored in nodes intersected by `region` for which `accept` returns true.
+     Each member is returned once, even if it is stored in several of those nodes.
+     */
+    fn members_matching(&self, region: &Rect, accept: impl Fn(&Rect) -> bool) -> Vec<Rect> {
+        self.members_in_nodes(|node_rect| node_rect.intersects(region), accept)
+    }
+
+    /**
+     Find the members stored in nodes for which `visit` returns true, and for which
+     `accept` returns true. Children are only visited if `visit` accepts them too.
+     */
+    fn members_in_nodes(&self, visit: impl Fn(&Rect) -> bool, accept: impl Fn(&Rect) -> bool) -> Vec<Rect> {
+        self.items_in_nodes(visit, accept).into_iter().map(|item| item.rect).collect()
+    }
+
+    /**
+     Find the items stored in nodes for which `visit` returns true, and for which
+     `accept` returns true of their rects. Otherwise the same as `members_in_nodes`.
+     */
+    pub(crate) fn items_in_nodes(&self, visit: impl Fn(&Rect) -> bool, accept: impl Fn(&Rect) -> bool) -> Vec<Item> {
+        let mut found: Vec<Item> = Vec::new();
+        if visit(&self.root.rect) {
+            self.root.push_items_in_nodes(&visit, &accept, &mut HashSet::new(), &mut found);
+        }
+
+        found
+    }
+
+    /**
+     Find the members intersecting `region`, or with `Broad` mode, every member stored in
+     a node that `region` intersects, as items in `ctx`'s buffer. Reusing one context for
+     many queries avoids allocating once its buffer has grown to fit the largest result.
+     */
+    pub fn query_with<'c>(&self, ctx: &'c mut QueryCtx, region: &Rect, mode: QueryMode) -> &'c [Item] {
+        ctx.found.clear();
+        ctx.seen.clear();
+        if self.root.rect.intersects(region) {
+            let visit = |node_rect: &Rect| node_rect.intersects(region);
+            match mode {
+                QueryMode::Broad => self.root.push_items_in_nodes(&visit, &|_| true, &mut ctx.seen, &mut ctx.found),
+                QueryMode::Exact => self.root.push_items_in_nodes(&visit, &|rect| region.intersects(rect), &mut ctx.seen, &mut ctx.found),
+            }
+        }
+
+        &ctx.found
+    }
+
+    /**
+     Find every member, ordered by when it was inserted, oldest first. Members are
+     collected in one walk, deduplicated by key, then sorted by sequence.
+     */
+    pub fn members_in_insertion_order(&self) -> Vec<Item> {
+        let mut items = self.items_in_nodes(|_| true, |_| true);
+        items.sort_by_key(|item| item.sequence);
+
+        items
+    }
+
+    /**
+     Find the members intersecting `region`, ordered by when they were inserted,
+     oldest first.
+     */
+    pub fn query_in_insertion_order(&self, region: &Rect) -> Vec<Item> {
+        let mut items = self.items_in_nodes(|node_rect| node_rect.intersects(region), |rect| region.intersects(rect));
+        items.sort_by_key(|item| item.sequence);
+
+        items
+    }
+
+    /**
+     Create a new tree holding only the members for which `keep` returns true, with the
+     same root bounds and settings. `keep` is called once for each member. Emptied
+     subtrees are collapsed, and the kept members keep their priority and insertion order.
+     */
+    pub fn filter(mut self, mut keep: impl FnMut(&Item) -> bool) -> QuadTree<T, P> {
+        let mut kept = HashMap::new();
+        let removed = self.remove_where(|item| !*kept.entry(rect_key(&item.rect)).or_insert_with(|| keep(item)));
+        self.forget_values(removed.iter().map(|item| &item.rect));
+
+        self
+    }
+
+    /**
+     Create a new tree holding the items `f` returns for each member, dropping members
+     for which it returns `None`, with the same root bounds, settings and split policy.
+     Items are inserted in the members' insertion order, and those the new tree rejects,
+     e.g. for overlapping one already inserted, are left out and returned. A member's
+     value moves with it to the item `f` returns.
+     */
+    pub fn filter_map(mut self, mut f: impl FnMut(Item) -> Option<Item>) -> (QuadTree<T, P>, Vec<Item>) {
+        let members = self.members_in_insertion_order();
+        let mut tree = QuadTree::new_with_size(self.root.rect.origin, self.root.rect.size, self.config, self.policy);
+        tree.next_sequence = self.next_sequence;
+
+        let mut rejected = Vec::new();
+        for member in members.into_iter() {
+            if let Some(item) = f(member) {
+                if tree.insert_item(item).is_err() {
+                    rejected.push(item);
+                } else if let Some(value) = self.values.remove(&rect_key(&member.rect)) {
+                    tree.values.insert(rect_key(&item.rect), value);
+                }
+            }
+        }
+
+        (tree, rejected)
+    }
+
+    /**
+     Create a new tree holding only the members intersecting `region`, e.g. to send a
+     viewport's worth of data to a client. With `clip`, members are cut down to the part
+     inside `region`. The new tree has the same settings and split policy, and its root
+     is a square just large enough for its members, which keep their priority and
+     insertion order.
+     Members the new tree rejects, e.g. for spanning its leaves under
+     `Placement::Reject`, are left out, and returned as they would have been inserted.
+     The kept members' values are cloned into the new tree.
+     */
+    pub fn crop(&self, region: &Rect, clip: bool) -> (QuadTree<T, P>, Vec<Rect>) where T: Clone, P: Clone {
+        let members = self.query_in_insertion_order(region);
+        let mut items = members.clone();
+        if clip {
+            for item in items.iter_mut() {
+                item.rect = item.rect.intersect(region).unwrap_or(item.rect);
+            }
+        }
+
+        let bounds = match QuadTree::bounds_of(&items.iter().map(|item| item.rect).collect::<Vec<_>>()) {
+            Some(bounds) => Rect::new(bounds.origin, QuadTree::root_size_for(&bounds)),
+            None => Rect::new(Point::new(0., 0.), Size::new(0., 0.)),
+        };
+        let mut tree = QuadTree::new_with_size(bounds.origin, bounds.size, self.config, self.policy.clone());
+        tree.next_sequence = self.next_sequence;
+
+        let mut rejected = Vec::new();
+        for (member, item) in members.iter().zip(items) {
+            if tree.insert_item(item).is_err() {
+                rejected.push(item.rect);
+            } else if let Some(value) = self.value(&member.rect) {
+                tree.values.insert(rect_key(&item.rect), value.clone());
+            }
+        }
+
+        (tree, rejected)
+    }
+
+    /**
+     Find the members of `self` that don't intersect any member of `other`,
+     e.g. to find the parts of one dataset with no coverage in another.
+     */
+    pub fn not_covered_by<U, Q: SplitPolicy>(&self, other: &QuadTree<U, Q>) -> Vec<Rect> {
+        let covered: HashSet<[u64; 4]> = self.join(other, JoinPredicate::Intersects).iter()
+            .map(|(covered_rect, _)| rect_key(covered_rect))
+            .collect();
+
+        self.members().into_iter().filter(|rect| !covered.contains(&rect_key(rect))).collect()
+    }
+
+    /**
+     Find the pairs of members that come into contact while each moves for `dt` at the
+     velocity `velocities` gives it, ordered by time of impact, so fast-moving members
+     can't tunnel through each other between updates. Candidates are found by their
+     swept bounds, and each is confirmed by sweeping one member against the other
+     with their relative velocity. Members already touching have a time of impact of 0.
+     */
+    pub fn sweep_pairs(&self, velocities: impl Fn(&Rect) -> Point, dt: f64) -> Vec<SweptPair> {
+        let members = self.members();
+        let indices: HashMap<[u64; 4], usize> = members.iter().enumerate().map(|(i, rect)| (rect_key(rect), i)).collect();
+        let mut displacements = Vec::new();
+        let mut max_displacement = 0f64;
+        for rect in members.iter() {
+            let velocity = velocities(rect);
+            let displacement = Point::new(velocity.x * dt, velocity.y * dt);
+            max_displacement = max_displacement.max(displacement.x.abs()).max(displacement.y.abs());
+            displacements.push(displacement);
+        }
+
+        let mut pairs: Vec<SweptPair> = Vec::new();
+        for (i, rect) in members.iter().enumerate() {
+            let displacement = displacements[i];
+            let swept = rect.union(&rect.translate(displacement));
+
+            // Any member whose swept bounds meet ours starts within `max_displacement` of them.
+            for other_rect in self.query(&swept.inflate(max_displacement)).iter() {
+                let j = match indices.get(&rect_key(other_rect)) {
+                    Some(&j) if j > i => j,
+                    _ => continue,
+                };
+
+                let other_displacement = displacements[j];
+                let other_swept = other_rect.union(&other_rect.translate(other_displacement));
+                if !swept.intersects(&other_swept) {
+                    continue;
+                }
+
+                let relative = rect.translate(displacement.subtract(other_displacement));
+                if let Some(t) = rect.lerp_contact(&relative, other_rect) { pairs.push(SweptPair { rect: *rect, other_rect: *other_rect, time_of_impact: t * dt }) }
+            }
+        }
+
+        pairs.sort_by(|a, b| a.time_of_impact.partial_cmp(&b.time_of_impact).unwrap_or(Ordering::Equal));
+        pairs
+    }
+
+    /**
+     Find all pairs of members, one from `self` and one from `other`, that intersect.
+     */
+    pub fn intersections_with<U, Q: SplitPolicy>(&self, other: &QuadTree<U, Q>) -> Vec<(Rect, Rect)> {
+        self.join(other, JoinPredicate::Intersects)
+    }
+
+    /**
+     Find all pairs of members, one from `self` and one from `other`, no farther than
+     `max_distance` apart, e.g. to match detections to known objects. Pairs of nodes
+     farther apart than `max_distance` are pruned, as in `join`.
+     */
+    pub fn pairs_within<U, Q: SplitPolicy>(&self, other: &QuadTree<U, Q>, max_distance: f64) -> Vec<(Rect, Rect)> {
+        self.join(other, JoinPredicate::WithinDistance(max_distance))
+    }
+
+    /**
+     Pair up members of `self` with members of `other` that satisfy `predicate`.
+     Both trees are traversed together, and pairs of nodes that are too far apart
+     for any of their members to match are never descended into.
+     */
+    pub fn join<U, Q: SplitPolicy>(&self, other: &QuadTree<U, Q>, predicate: JoinPredicate) -> Vec<(Rect, Rect)> {
+        let mut pairs = Vec::new();
+        Node::push_joined_pairs(&self.root, &other.root, predicate, &mut HashSet::new(), &mut pairs);
+
+        pairs
+    }
+
+}
+
+impl Node {
+    /**
+     Get the bounds of this node.
+     */
+    pub fn bounds(&self) -> Rect {
+        self.rect
+    }
+
+    /**
+     Check if this node has no children.
+     */
+    pub fn is_leaf(&self) -> bool {
+        !matches!(self.elements, Elements::Children(..))
+    }
+
+    /**
+     Get the children of this node in `Quadrant::ALL` order, or `None` if it is a leaf.
+     */
+    pub fn children(&self) -> Option<[&Node; 4]> {
+        match self.elements {
+            Elements::Children(ref tl, ref tr, ref br, ref bl) => Some([&**tl, &**tr, &**br, &**bl]),
+            _ => None,
+        }
+    }
+
+    /**
+     Get the child of this node in `quadrant`, or `None` if the node has no children.
+     */
+    pub fn child(&self, quadrant: Quadrant) -> Option<&Node> {
+        self.children().map(|children| children[quadrant.index()])
+    }
+
+    /**
+     Get the items stored in this node itself. Only occupied leaves store items, and a
+     member spanning several leaves is stored in each of them.
+     */
+    pub fn leaf_items(&self) -> &[Item] {
+        match self.elements {
+            Elements::Members(ref items) => items,
+            _ => &[],
+        }
+    }
+
+    /**
+     Get the highest priority of any member stored in this node or its descendants,
+     or `i32::MIN` if there are none.
+     */
+    pub fn max_priority(&self) -> i32 {
+        self.max_priority
+    }
+
+    /**
+     Find the quadrant of this node that `point` lies in, whether or not the node has
+     children. A point on an edge between quadrants is in the first of them, as a stored
+     point would be. Returns `None` if `point` is outside the node.
+     */
+    pub fn quadrant_of(&self, point: Point) -> Option<Quadrant> {
+        if !self.rect.contains_point(&point) {
+            return None;
+        }
+
+        let center = self.rect.center();
+        let quadrant = if point.y <= center.y {
+            if point.x <= center.x { Quadrant::TopLeft } else { Quadrant::TopRight }
+        } else if point.x >= center.x {
+            Quadrant::BottomRight
+        } else {
+            Quadrant::BottomLeft
+        };
+
+        Some(quadrant)
+    }
+
+    /**
+     Find every member stored in this node and its descendants. Members spanning
+     several leaves are only returned once.
+     */
+    pub fn members(&self) -> Vec<Rect> {
+        let mut rects = Vec::new();
+        let mut seen = HashSet::new();
+        let mut nodes_to_check = vec![self];
+
+        while !nodes_to_check.is_empty() {
+            let mut new_nodes_to_check: Vec<&Node> = Vec::new();
+
+            for node in nodes_to_check.iter() {
+                match node.elements {
+                    Elements::Children(ref tl, ref tr, ref br, ref bl) => {
+                        new_nodes_to_check.push(tl);
+                        new_nodes_to_check.push(tr);
+                        new_nodes_to_check.push(br);
+                        new_nodes_to_check.push(bl);
+                    }
+                    Elements::Members(ref items) => {
+                        for item in items.iter() {
+                            if seen.insert(rect_key(&item.rect)) {
+                                rects.push(item.rect);
+                            }
+                        }
+                    },
+                    Elements::Empty => ()
+                };
+            }
+
+            nodes_to_check = new_nodes_to_check;
+        }
+
+        rects
+    }
+
+    /**
+     Look up the stored item for the member `rect` in this node or its descendants.
+     Only nodes intersecting `rect` are visited.
+     */
+    pub fn get(&self, rect: &Rect) -> Option<Item> {
+        if !self.rect.intersects(rect) {
+            return None;
+        }
+
+        let mut nodes_to_check = vec![self];
+
+        while !nodes_to_check.is_empty() {
+            let mut new_nodes_to_check: Vec<&Node> = Vec::new();
+
+            for node in nodes_to_check.iter() {
+                match node.elements {
+                    Elements::Children(ref tl, ref tr, ref br, ref bl) => {
+                        for child in [&**tl, &**tr, &**br, &**bl].iter() {
+                            if child.rect.intersects(rect) {
+                                new_nodes_to_check.push(*child);
+                            }
+                        }
+                    }
+                    Elements::Members(ref items) => {
+                        for item in items.iter() {
+                            if item.rect == *rect {
+                                return Some(*item);
+                            }
+                        }
+                    },
+                    Elements::Empty => ()
+                };
+            }
+
+            nodes_to_check = new_nodes_to_check;
+        }
+
+        None
+    }
+
+    /**
+     Count the members stored in this node and its descendants, and find their bounds
+     and highest priority, in one walk. A member spanning several leaves is only counted
+     by the leaf owning it, as in a paged query of this node.
+     */
+    fn summarize(&self, placement: Placement) -> CellSummary {
+        let mut count = 0;
+        let mut content_bounds: Option<Rect> = None;
+        let mut nodes_to_visit = vec![self];
+
+        while let Some(node) = nodes_to_visit.pop() {
+            match node.elements {
+                Elements::Children(ref tl, ref tr, ref br, ref bl) => nodes_to_visit.extend([&**tl, &**tr, &**br, &**bl]),
+                Elements::Members(ref items) => {
+                    for item in items.iter().filter(|item| node.reports_in_page(&self.rect, &self.rect, item, placement)) {
+                        count += 1;
+                        content_bounds = Some(content_bounds.map_or(item.rect, |bounds| bounds.union(&item.rect)));
+                    }
+                },
+                Elements::Empty => (),
+            }
+        }
+
+        let max_priority = if count > 0 { Some(self.max_priority) } else { None };
+        CellSummary { cell: self.rect, count, content_bounds, max_priority }
+    }
+
+    /**
+     Check the invariants of this node and its descendants.
+     */
+    fn validate_node(&self) -> Result<(), String> {
+        let expected_priority =
+        match self.elements {
+            Elements::Children(ref tl, ref tr, ref br, ref bl) => {
+                let expected = QuadTree::child_rects(&self.rect);
+
+                for (child, expected_rect) in [&**tl, &**tr, &**br, &**bl].iter().zip(expected.iter()) {
+                    if child.rect != *expected_rect {
+                        return Err(format!("child {:?} of node {:?} should be {:?}", child.rect, self.rect, expected_rect))
+                    }
+
+                    child.validate_node()?;
+                }
+
+                cmp::max(cmp::max(tl.max_priority, tr.max_priority), cmp::max(br.max_priority, bl.max_priority))
+            },
+            Elements::Members(ref items) => {
+                if items.is_empty() {
+                    return Err(format!("leaf {:?} holds an empty member list", self.rect))
+                }
+
+                for (i, item) in items.iter().enumerate() {
+                    if !self.rect.intersects(&item.rect) {
+                        return Err(format!("leaf {:?} holds member {:?} outside its bounds", self.rect, item.rect))
+                    }
+
+                    if items.iter().skip(i + 1).any(|other| other.rect == item.rect) {
+                        return Err(format!("leaf {:?} holds member {:?} more than once", self.rect, item.rect))
+                    }
+                }
+
+                items.iter().fold(i32::MIN, |max_priority, item| cmp::max(max_priority, item.priority))
+            },
+            Elements::Empty => i32::MIN,
+        };
+
+        if self.max_priority != expected_priority {
+            return Err(format!("node {:?} caches max priority {}, expected {}", self.rect, self.max_priority, expected_priority))
+        }
+
+        Ok(())
+    }
+
+    /**
+     Create a node with bounds `node_rect` and the given children, which should tile it.
+     */
+    fn new_with_children(node_rect: Rect, tl: Box<Node>, tr: Box<Node>, br: Box<Node>, bl: Box<Node>) -> Node {
+        debug_assert!(Node::children_tile(&node_rect, &tl.rect, &tr.rect, &br.rect, &bl.rect),
+            "QuadTree node constructed by new_with_children with children that don't tile it.");
+
+        Node::from_trusted_children(node_rect, tl, tr, br, bl)
+    }
+
+    /**
+     Create a node with bounds `node_rect` and the given children, without checking
+     that they tile it. For rebuilding a node on insertion paths, where its children
+     were just taken from it, or made for it by `make_children_for_rect`.
+     */
+    fn from_trusted_children(node_rect: Rect, tl: Box<Node>, tr: Box<Node>, br: Box<Node>, bl: Box<Node>) -> Node {
+        let max_priority = cmp::max(cmp::max(tl.max_priority, tr.max_priority), cmp::max(br.max_priority, bl.max_priority));
+
+        Node { rect: node_rect, elements: Elements::Children(tl, tr, br, bl), max_priority }
+    }
+
+    /**
+     Check that `tl`, `tr`, `br`, and `bl` sit in those positions within `node_rect`,
+     sharing their inner edges and together covering it exactly.
+     */
+    fn children_tile(node_rect: &Rect, tl: &Rect, tr: &Rect, br: &Rect, bl: &Rect) -> bool {
+        tl.min_x() == bl.min_x() && tl.max_x() == bl.max_x() && tl.max_y() == bl.min_y()
+        && tl.max_x() == tr.min_x() && tl.min_y() == tr.min_y() && tl.max_y() == tr.max_y()
+        && bl.max_x() == br.min_x() && bl.min_y() == br.min_y() && bl.max_y() == br.max_y()
+        && tr.min_x() == br.min_x() && tr.max_x() == br.max_x() && tr.max_y() == br.min_y()
+        && node_rect.min_x() == tl.min_x() && node_rect.max_x() == tr.max_x()
+        && node_rect.min_y() == tl.min_y() && node_rect.max_y() == bl.max_y()
+    }
+
+    /**
+     Create a leaf with bounds `node_rect` holding `insert_items`.
+     */
+    fn new_with_members(node_rect: Rect, insert_items: Vec<Item>) -> Node {
+        debug_assert!(!insert_items.is_empty(), "QuadTree node constructed by new_with_members with no members.");
+        // Members spanning several nodes are stored in each of them, so the node only
+        // needs to intersect each member rather than contain it.
+        debug_assert!(insert_items.iter().all(|item| node_rect.intersects(&item.rect)),
+            "QuadTree node constructed by new_with_members does not intersect the rects it is passed in.");
+
+        Node::from_trusted_members(node_rect, insert_items)
+    }
+
+    /**
+     Create a leaf with bounds `node_rect` holding `items`, without checking that it
+     intersects them. For insertion paths, which only add items to leaves they intersect.
+     */
+    fn from_trusted_members(node_rect: Rect, items: Vec<Item>) -> Node {
+        let max_priority = items.iter().fold(i32::MIN, |max_priority, item| cmp::max(max_priority, item.priority));
+
+        Node { rect: node_rect, elements: Elements::Members(items), max_priority }
+    }
+
+    /**
+     Create an empty leaf with bounds `node_rect`.
+     */
+    fn new_empty(node_rect: Rect) -> Node {
+        Node { rect: node_rect, elements: Elements::Empty, max_priority: i32::MIN }
+    }
+
+    /**
+     Change the priority of the member `rect` in this node and its descendants.
+     Returns false if `rect` is not stored here.
+     */
+    fn set_priority(&mut self, rect: &Rect, priority: i32) -> bool {
+        if !self.rect.intersects(rect) {
+            return false;
+        }
+
+        let found = match self.elements {
+            Elements::Children(ref mut tl, ref mut tr, ref mut br, ref mut bl) => {
+                // Visit every child so all copies of a spanning member are updated.
+                let found_tl = tl.set_priority(rect, priority);
+                let found_tr = tr.set_priority(rect, priority);
+                let found_br = br.set_priority(rect, priority);
+                let found_bl = bl.set_priority(rect, priority);
+
+                self.max_priority = cmp::max(cmp::max(tl.max_priority, tr.max_priority), cmp::max(br.max_priority, bl.max_priority));
+                found_tl || found_tr || found_br || found_bl
+            },
+            Elements::Members(ref mut items) => {
+                let mut found = false;
+                for item in items.iter_mut() {
+                    if item.rect == *rect {
+                        item.priority = priority;
+                        found = true;
+                    }
+                }
+
+                self.max_priority = items.iter().fold(i32::MIN, |max_priority, item| cmp::max(max_priority, item.priority));
+                found
+            },
+            Elements::Empty => false,
+        };
+
+        found
+    }
+
+    /**
+     Check if `rect` would have to be split between the children of some inner node,
+     which `Placement::Reject` forbids.
+     */
+    fn spans_children(&self, rect: &Rect) -> bool {
+        let mut node = self;
+        while let Some(children) = node.children() {
+            match children.iter().find(|child| child.rect.contains(rect)) {
+                Some(child) => node = child,
+                None => return true,
+            }
+        }
+
+        false
+    }
+
+    /**
+     Remove the members for which `should_remove` returns true from this node and its
+     descendants for which `visit` returns true, appending each copy of them to `removed`.
+     */
+    fn remove_where_into(&mut self, config: &Config, visit: &mut dyn FnMut(&Rect) -> bool, should_remove: &mut dyn FnMut(&Item) -> bool, removed: &mut Vec<Item>) {
+        if !visit(&self.rect) {
+            return;
+        }
+
+        let is_emptied = match self.elements {
+            Elements::Children(ref mut tl, ref mut tr, ref mut br, ref mut bl) => {
+                tl.remove_where_into(config, visit, should_remove, removed);
+                tr.remove_where_into(config, visit, should_remove, removed);
+                br.remove_where_into(config, visit, should_remove, removed);
+                bl.remove_where_into(config, visit, should_remove, removed);
+
+                self.max_priority = cmp::max(cmp::max(tl.max_priority, tr.max_priority), cmp::max(br.max_priority, bl.max_priority));
+                if config.coalesce {
+                    if let Some(items) = Node::coalesced_members(&[&**tl, &**tr, &**br, &**bl], config.leaf_capacity) {
+                        self.elements = if items.is_empty() { Elements::Empty } else { Elements::Members(items) };
+                        return;
+                    }
+                }
+                tl.is_empty_leaf() && tr.is_empty_leaf() && br.is_empty_leaf() && bl.is_empty_leaf()
+            },
+            Elements::Members(ref mut items) => {
+                let mut kept = Vec::new();
+                for item in items.iter() {
+                    if should_remove(item) {
+                        removed.push(*item);
+                    } else {
+                        kept.push(*item);
+                    }
+                }
+
+                self.max_priority = kept.iter().fold(i32::MIN, |max_priority, item| cmp::max(max_priority, item.priority));
+                *items = kept;
+                items.is_empty()
+            },
+            Elements::Empty => false,
+        };
+
+        if is_emptied {
+            self.elements = Elements::Empty;
+            self.max_priority = i32::MIN;
+        }
+    }
+
+    /**
+     Get the distinct members of `children` if they are all leaves holding no more than
+     `capacity` members between them, so they can be merged into their parent.
+     */
+    fn coalesced_members(children: &[&Node], capacity: usize) -> Option<Vec<Item>> {
+        let mut items: Vec<Item> = Vec::new();
+        for child in children.iter() {
+            match child.elements {
+                Elements::Children(..) => return None,
+                Elements::Members(ref members) => {
+                    for item in members.iter() {
+                        if !items.contains(item) {
+                            items.push(*item);
+                        }
+                    }
+                },
+                Elements::Empty => (),
+            }
+
+            if items.len() > capacity {
+                return None;
+            }
+        }
+
+        Some(items)
+    }
+
+    fn is_empty_leaf(&self) -> bool {
+        matches!(self.elements, Elements::Empty)
+    }
+
+    /**
+     Insert an item into the node IFF its rectangle intersects the node.
+     `depth` is the node's depth below the root. A full leaf is split, unless it is
+     at the maximum depth, splitting it would make cells smaller than the minimum size,
+     as the tree's `config` says, or all its members share a point.
+     */
+    fn insert_rect_if_intersects<P: SplitPolicy>(self, to_insert: Item, depth: usize, config: &Config, policy: &P) -> Node {
+        if self.rect.intersects(&to_insert.rect) {
+            let node_rect = self.rect;
+            match self.elements {
+                Elements::Children(tl, tr, br, bl) => {
+                    let [tl, tr, br, bl] = Node::insert_into_children([tl, tr, br, bl], to_insert, depth + 1, config, policy);
+                    Node::from_trusted_children(node_rect, tl, tr, br, bl)
+                },
+                Elements::Members(mut items) => {
+                    items.push(to_insert);
+                    // Splitting can't separate members that all share a point, so keep them together.
+                    let share_a_point = Node::share_a_point(&items);
+                    let child_rects = QuadTree::child_rects(&self.rect);
+                    let would_span = config.placement == Placement::Reject
+                        && items.iter().any(|item| !item.is_point() && !child_rects.iter().any(|child| child.contains(&item.rect)));
+
+                    if !config.can_split(&self.rect, depth) || share_a_point || would_span
+                        || !policy.should_split(&self.rect, &items, depth, config) {
+                        Node::from_trusted_members(node_rect, items)
+                    } else {
+                        let (tl, tr, br, bl) = Node::make_children_for_rect(&self.rect);
+                        let mut children = [tl, tr, br, bl];
+
+                        for item in items.iter() {
+                            children = Node::insert_into_children(children, *item, depth + 1, config, policy);
+                        }
+
+                        let [tl, tr, br, bl] = children;
+                        Node::from_trusted_children(node_rect, tl, tr, br, bl)
+                    }
+                },
+                Elements::Empty => Node::from_trusted_members(node_rect, vec![to_insert]),
+            }
+        } else {
+            self
+        }
+    }
+
+    /**
+     Check if some point lies in every one of `items`' rects, including on their edges.
+     */
+    fn share_a_point(items: &[Item]) -> bool {
+        let (min_x, max_x, min_y, max_y) = items.iter().fold(
+            (f64::NEG_INFINITY, f64::INFINITY, f64::NEG_INFINITY, f64::INFINITY),
+            |(min_x, max_x, min_y, max_y), item| (min_x.max(item.rect.min_x()), max_x.min(item.rect.max_x()),
+                                                  min_y.max(item.rect.min_y()), max_y.min(item.rect.max_y())));
+
+        min_x <= max_x && min_y <= max_y
+    }
+
+    /**
+     Insert `item` into `children`, the children of a node, at `depth`. A rect goes into
+     every child it intersects, but a point on an edge shared by several children only
+     goes into the first of them, since unlike a rect it can't span them.
+     */
+    fn insert_into_children<P: SplitPolicy>(children: [Box<Node>; 4], item: Item, depth: usize, config: &Config, policy: &P) -> [Box<Node>; 4] {
+        let owner = if item.is_point() {
+            children.iter().position(|child| child.rect.contains_point(&item.rect.origin))
+        } else if config.placement == Placement::Reject {
+            // A member fitting no child, e.g. while restructuring, goes into each it intersects.
+            children.iter().position(|child| child.rect.contains(&item.rect))
+        } else {
+            None
+        };
+
+        let mut index = 0;
+        children.map(|child| {
+            let holds = owner.is_none_or(|owner| owner == index);
+            index += 1;
+
+            if holds {
+                Box::new(child.insert_rect_if_intersects(item, depth, config, policy))
+            } else {
+                child
+            }
+        })
+    }
+
+    /**
+     Create four nodes suitable for use as children, covering the passed in rect.
+     */
+    fn make_children_for_rect(rect: &Rect) -> (Box<Node>, Box<Node>, Box<Node>, Box<Node>,) {
+        let [tl, tr, br, bl] = QuadTree::child_rects(rect).map(|child_rect| Box::new(Node::new_empty(child_rect)));
+
+        (tl, tr, br, bl)
+    }
+
+    /**
+     Append the leaves of this node hit by `ray` to `leaves`, visiting children
+     in the order the ray enters them. Assumes the ray hits this node.
+     */
+    fn push_leaves_along_ray<'a>(&'a self, ray: &Ray, leaves: &mut Vec<&'a Node>) {
+        match self.elements {
+            Elements::Children(..) => {
+                for &(_, child) in self.children_along_ray(ray).iter() {
+                    child.push_leaves_along_ray(ray, leaves);
+                }
+            },
+            _ => leaves.push(self),
+        }
+    }
+
+    /**
+     Find the children of this node hit by `ray`, with the parameter at which the ray
+     enters each, in the order the ray enters them.
+     */
+    fn children_along_ray(&self, ray: &Ray) -> Vec<(f64, &Node)> {
+        let mut hit_children = Vec::new();
+        if let Elements::Children(ref tl, ref tr, ref br, ref bl) = self.elements {
+            for child in [&**tl, &**tr, &**br, &**bl].iter() {
+                if let Some((enter, _)) = child.rect.ray_intersection(ray) { hit_children.push((enter, *child)) }
+            }
+        }
+
+        hit_children.sort_by(|&(enter1, _), &(enter2, _)| enter1.partial_cmp(&enter2).unwrap_or(Ordering::Equal));
+        hit_children
+    }
+
+    fn find_first_ray_hit(&self, ray: &Ray, best: &mut Option<RayHit>) {
+        match self.elements {
+            Elements::Children(..) => {
+                for &(enter, child) in self.children_along_ray(ray).iter() {
+                    if best.is_some_and(|hit| enter > hit.t) {
+                        break;
+                    }
+
+                    child.find_first_ray_hit(ray, best);
+                }
+            },
+            Elements::Members(ref items) => {
+                for item in items.iter() {
+                    match item.rect.ray_intersection(ray) {
+                        Some((t, _)) if best.is_none_or(|hit| t < hit.t) => {
+                            *best = Some(RayHit {
+                                item: *item,
+                                t,
+                                point: ray.point_at(t),
+                                normal: Node::entry_normal(&item.rect, ray, t),
+                            });
+                        },
+                        _ => (),
+                    }
+                }
+            },
+            Elements::Empty => (),
+        }
+    }
+
+    /**
+     Find the outward normal of the edge of `rect` that `ray` enters through at `t`,
+     or zero if the ray starts inside `rect`.
+     */
+    fn entry_normal(rect: &Rect, ray: &Ray, t: f64) -> Point {
+        if t == 0. && rect.contains_point(&ray.origin) {
+            return Point::new(0., 0.)
+        }
+
+        let slab_entry = |origin: f64, direction: f64, min: f64, max: f64| {
+            if direction > 0. {
+                (min - origin) / direction
+            } else if direction < 0. {
+                (max - origin) / direction
+            } else {
+                f64::NEG_INFINITY
+            }
+        };
+
+        let x_entry = slab_entry(ray.origin.x, ray.direction.x, rect.min_x(), rect.max_x());
+        let y_entry = slab_entry(ray.origin.y, ray.direction.y, rect.min_y(), rect.max_y());
+
+        if x_entry >= y_entry {
+            Point::new(-ray.direction.x.signum(), 0.)
+        } else {
+            Point::new(0., -ray.direction.y.signum())
+        }
+    }
+
+    /**
+     Append the clusters of the cells `depth` levels below this node to `clusters`.
+     A member whose center lies on the edge between cells goes to the first of them,
+     and its key is recorded in `assigned` so the others skip it.
+     */
+    fn push_clusters(&self, depth: usize, clusters: &mut Vec<Cluster>, assigned: &mut HashSet<[u64; 4]>) {
+        match self.elements {
+            Elements::Children(ref tl, ref tr, ref br, ref bl) if depth > 0 => {
+                for child in [&**tl, &**tr, &**br, &**bl].iter() {
+                    child.push_clusters(depth - 1, clusters, assigned);
+                }
+                return;
+            },
+            _ => (),
+        }
+
+        let members: Vec<Rect> = self.members().into_iter()
+            .filter(|rect| self.rect.contains_point(&rect.center()) && !assigned.contains(&rect_key(rect)))
+            .collect();
+        if members.is_empty() {
+            return;
+        }
+
+        let centers: Vec<Point> = members.iter().map(|rect| rect.center()).collect();
+        let bounds = centers.iter().skip(1)
+            .fold(Rect::new(centers[0], Size::new(0., 0.)), |bounds, center| bounds.union(&Rect::new(*center, Size::new(0., 0.))));
+        let sum = centers.iter().fold(Point::new(0., 0.), |sum, center| sum.add(*center));
+        let count = centers.len() as f64;
+
+        assigned.extend(members.iter().map(rect_key));
+        clusters.push(Cluster {
+            cell: self.rect,
+            bounds,
+            centroid: Point::new(sum.x / count, sum.y / count),
+            members,
+        });
+    }
+
+    fn map_priorities_in(&mut self, f: &dyn Fn(&Item) -> i32) {
+        match self.elements {
+            Elements::Children(ref mut tl, ref mut tr, ref mut br, ref mut bl) => {
+                tl.map_priorities_in(f);
+                tr.map_priorities_in(f);
+                br.map_priorities_in(f);
+                bl.map_priorities_in(f);
+
+                self.max_priority = cmp::max(cmp::max(tl.max_priority, tr.max_priority), cmp::max(br.max_priority, bl.max_priority));
+            },
+            Elements::Members(ref mut items) => {
+                for item in items.iter_mut() {
+                    item.priority = f(item);
+                }
+
+                self.max_priority = items.iter().fold(i32::MIN, |max_priority, item| cmp::max(max_priority, item.priority));
+            },
+            Elements::Empty => (),
+        }
+    }
+
+    /**
+     Update `best` with the highest-priority member containing `point` in this node,
+     if it beats the current `best`. Assumes the node contains `point`.
+     */
+    fn find_topmost_at(&self, point: &Point, best: &mut Option<Item>) {
+        let can_improve = match *best {
+            Some(best_item) => self.max_priority > best_item.priority,
+            None => true,
+        };
+        if !can_improve {
+            return;
+        }
+
+        match self.elements {
+            Elements::Children(ref tl, ref tr, ref br, ref bl) => {
+                // Visit the most promising children first so the rest can be pruned.
+                let mut children: Vec<&Node> = [&**tl, &**tr, &**br, &**bl].iter().copied()
+                    .filter(|child| child.rect.contains_point(point))
+                    .collect();
+                children.sort_by_key(|child| cmp::Reverse(child.max_priority));
+
+                for child in children.iter() {
+                    child.find_topmost_at(point, best);
+                }
+            },
+            Elements::Members(ref items) => {
+                for item in items.iter() {
+                    let is_better = match *best {
+                        Some(best_item) => item.priority > best_item.priority,
+                        None => true,
+                    };
+
+                    if is_better && item.rect.contains_point(point) {
+                        *best = Some(*item);
+                    }
+                }
+            },
+            Elements::Empty => (),
+        }
+    }
+
+    /**
+     Visit the members intersecting `region` in this subtree. A member spanning several
+     leaves is only visited from the leaf that reports it in a paged query, so no list of
+     visited members is needed.
+     */
+    fn query_each_in(&self, root: &Rect, region: &Rect, placement: Placement, visit: &mut impl FnMut(&Rect)) {
+        match self.elements {
+            Elements::Children(ref tl, ref tr, ref br, ref bl) => {
+                for child in [&**tl, &**tr, &**br, &**bl] {
+                    if child.rect.intersects(region) {
+                        child.query_each_in(root, region, placement, visit);
+                    }
+                }
+            },
+            Elements::Members(ref items) => {
+                for item in items.iter() {
+                    if self.reports_in_page(root, region, item, placement) {
+                        visit(&item.rect);
+                    }
+                }
+            },
+            Elements::Empty => (),
+        }
+    }
+
+    /**
+     Append the members of this node to report for the paged query `token` to `rects`,
+     starting from `resume`, the rest of the token's path below this node, if the query
+     resumes within this node. `path` is the path to this node. Returns the token for
+     the next page once `rects` holds `page_size` members and another is found.
+     `tree` is the tree this node belongs to.
+     */
+    fn fill_page<T, P: SplitPolicy>(&self, tree: &QuadTree<T, P>, token: &QueryToken, resume: Option<&[Quadrant]>, path: &mut Vec<Quadrant>,
+                 rects: &mut Vec<Rect>, page_size: usize) -> Option<QueryToken> {
+        if !self.rect.intersects(&token.region) {
+            return None;
+        }
+
+        match self.elements {
+            Elements::Children(ref tl, ref tr, ref br, ref bl) => {
+                let start = resume.and_then(|resume| resume.first())
+                    .map_or(0, |quadrant| quadrant.index());
+
+                for (i, child) in [&**tl, &**tr, &**br, &**bl].iter().enumerate().skip(start) {
+                    let child_resume = if i == start { resume.map(|resume| resume.get(1..).unwrap_or(&[])) } else { None };
+
+                    path.push(Quadrant::ALL[i]);
+                    let next = child.fill_page(tree, token, child_resume, path, rects, page_size);
+                    path.pop();
+
+                    if next.is_some() {
+                        return next;
+                    }
+                }
+            },
+            Elements::Members(ref items) => {
+                let start = if resume.is_some() { token.offset } else { 0 };
+                for (i, item) in items.iter().enumerate().skip(start) {
+                    if !self.reports_in_page(&tree.root.rect, &token.region, item, tree.config.placement) {
+                        continue;
+                    }
+
+                    if rects.len() == page_size {
+                        return Some(QueryToken { region: token.region, path: path.clone(), offset: i });
+                    }
+                    rects.push(item.rect);
+                }
+            },
+            Elements::Empty => (),
+        }
+
+        None
+    }
+
+    /**
+     Check if this leaf is the only one storing `item`: a point is stored in a single
+     leaf, as is a member of a tree with `Placement::Reject` that fits in one. Any other
+     member is stored in every leaf it intersects, which `reports_in_page` relies on.
+     */
+    fn holds_alone(&self, item: &Item, placement: Placement) -> bool {
+        item.is_point() || (placement == Placement::Reject && self.rect.contains(&item.rect))
+    }
+
+    /**
+     Find the distinct members stored in this subtree on its boundary that belong in every
+     leaf they intersect, so in any leaf placed beside this node that they reach.
+     */
+    fn items_on_boundary(&self, placement: Placement) -> Vec<Item> {
+        let bounds = self.rect;
+        let on_boundary = |rect: &Rect| rect.min_x() <= bounds.min_x() || rect.max_x() >= bounds.max_x()
+            || rect.min_y() <= bounds.min_y() || rect.max_y() >= bounds.max_y();
+
+        let mut items = Vec::new();
+        let mut seen = HashSet::new();
+        let mut nodes_to_visit = vec![self];
+        while let Some(node) = nodes_to_visit.pop() {
+            match node.elements {
+                Elements::Children(ref tl, ref tr, ref br, ref bl) => {
+                    nodes_to_visit.extend([&**tl, &**tr, &**br, &**bl].into_iter().filter(|child| on_boundary(&child.rect)));
+                },
+                Elements::Members(ref node_items) => {
+                    items.extend(node_items.iter().filter(|item| {
+                        on_boundary(&item.rect) && !node.holds_alone(item, placement) && seen.insert(rect_key(&item.rect))
+                    }));
+                },
+                Elements::Empty => (),
+            }
+        }
+
+        items
+    }
+
+    /**
+     Store `item` in each leaf of this subtree it intersects that doesn't hold it yet,
+     splitting them as inserting it would. `depth` is the node's depth below the root.
+     */
+    fn fill_in_item<P: SplitPolicy>(self, item: Item, depth: usize, config: &Config, policy: &P) -> Node {
+        if !self.rect.intersects(&item.rect) || self.leaf_items().iter().any(|other| other.rect == item.rect) {
+            return self
+        }
+
+        match self.elements {
+            Elements::Children(tl, tr, br, bl) => {
+                let [tl, tr, br, bl] = [tl, tr, br, bl].map(|child| Box::new(child.fill_in_item(item, depth + 1, config, policy)));
+                Node::from_trusted_children(self.rect, tl, tr, br, bl)
+            },
+            elements => Node { elements, ..self }.insert_rect_if_intersects(item, depth, config, policy),
+        }
+    }
+
+    /**
+     Check that every member not held by one leaf alone is stored in every leaf it
+     intersects, so walks visiting each member once, like paged queries, find it.
+     Assumes this is the root.
+     */
+    fn validate_spanning_members(&self, placement: Placement) -> Result<(), String> {
+        let mut leaves = Vec::new();
+        let mut nodes_to_visit = vec![self];
+        while let Some(node) = nodes_to_visit.pop() {
+            match node.children() {
+                Some(children) => nodes_to_visit.extend(children),
+                None => leaves.push(node),
+            }
+        }
+
+        for leaf in leaves.iter() {
+            for item in leaf.leaf_items().iter().filter(|item| !leaf.holds_alone(item, placement)) {
+                let mut nodes_to_check = vec![self];
+                while let Some(node) = nodes_to_check.pop() {
+                    match node.children() {
+                        Some(children) => nodes_to_check.extend(children.into_iter().filter(|child| child.rect.intersects(&item.rect))),
+                        None if !node.leaf_items().iter().any(|other| other.rect == item.rect) => {
+                            return Err(format!("member {:?} is missing from leaf {:?}, which it intersects", item.rect, node.rect))
+                        },
+                        None => (),
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /**
+     Check if this leaf is the one that reports `item` in a paged query of `region`.
+     A member spanning several leaves is reported by the leaf owning the lowest corner
+     of the part of it inside `region` and `root`, where each leaf owns the points on
+     its top and left edges, but only owns those on its bottom and right edges if they
+     are on the root's edges too. A point is only stored in one leaf, which reports it,
+     as is a member of a tree with `Placement::Reject` that fits in one leaf.
+     */
+    pub(crate) fn reports_in_page(&self, root: &Rect, region: &Rect, item: &Item, placement: Placement) -> bool {
+        if !region.intersects(&item.rect) {
+            return false;
+        }
+
+        // A member placed in a single leaf is reported by it, even from its far edges.
+        if self.holds_alone(item, placement) {
+            return true;
+        }
+
+        let corner = Point::new(item.rect.min_x().max(region.min_x()).max(root.min_x()),
+                                item.rect.min_y().max(region.min_y()).max(root.min_y()));
+        let owns_x = self.rect.min_x() <= corner.x && (corner.x < self.rect.max_x() || self.rect.max_x() == root.max_x());
+        let owns_y = self.rect.min_y() <= corner.y && (corner.y < self.rect.max_y() || self.rect.max_y() == root.max_y());
+
+        owns_x && owns_y
+    }
+
+    /**
+     Append the items `items_in_nodes` would find in this subtree to `found`, skipping
+     those whose keys are already in `seen`. Assumes `visit` accepts this node.
+     */
+    fn push_items_in_nodes(&self, visit: &impl Fn(&Rect) -> bool, accept: &impl Fn(&Rect) -> bool,
+                           seen: &mut HashSet<[u64; 4]>, found: &mut Vec<Item>) {
+        match self.elements {
+            Elements::Children(ref tl, ref tr, ref br, ref bl) => {
+                for child in [&**tl, &**tr, &**br, &**bl] {
+                    if visit(&child.rect) {
+                        child.push_items_in_nodes(visit, accept, seen, found);
+                    }
+                }
+            },
+            Elements::Members(ref items) => {
+                for item in items.iter() {
+                    if accept(&item.rect) && seen.insert(rect_key(&item.rect)) {
+                        found.push(*item);
+                    }
+                }
+            },
+            Elements::Empty => (),
+        }
+    }
+
+    /**
+     Append the member pairs found under `node` and `other_node` that satisfy `predicate`
+     to `pairs`. A member spanning several leaves is seen once per leaf, so each pair
+     is only added the first time it is found, as recorded in `seen`.
+     */
+    fn push_joined_pairs(node: &Node, other_node: &Node, predicate: JoinPredicate,
+                         seen: &mut HashSet<([u64; 4], [u64; 4])>, pairs: &mut Vec<(Rect, Rect)>) {
+        if !predicate.may_match_within(&node.rect, &other_node.rect) {
+            return;
+        }
+
+        match (&node.elements, &other_node.elements) {
+            (&Elements::Empty, _) | (_, &Elements::Empty) => (),
+            (Elements::Members(items), Elements::Members(other_items)) => {
+                for item in items.iter() {
+                    for other_item in other_items.iter() {
+                        if predicate.matches(&item.rect, &other_item.rect)
+                            && seen.insert((rect_key(&item.rect), rect_key(&other_item.rect))) {
+                            pairs.push((item.rect, other_item.rect));
+                        }
+                    }
+                }
+            },
+            (Elements::Children(tl, tr, br, bl), _) => {
+                for child in [&**tl, &**tr, &**br, &**bl].iter() {
+                    Node::push_joined_pairs(child, other_node, predicate, seen, pairs);
+                }
+            },
+            (_, Elements::Children(tl, tr, br, bl)) => {
+                for other_child in [&**tl, &**tr, &**br, &**bl].iter() {
+                    Node::push_joined_pairs(node, other_child, predicate, seen, pairs);
+                }
+            },
+        }
+    }
+}
+
+/**
+ A node or member waiting to be visited by a best-first traversal.
+ */
+enum ScoredEntry<'a> {
+    Node(&'a Node),
+    Member(Item),
+}
+
+/**
+ A node or member paired with its score, ordered so that a `BinaryHeap` pops the
+ lowest score first, and a member before a node with the same score.
+ */
+struct Scored<'a> {
+    score: f64,
+    entry: ScoredEntry<'a>,
+}
+
+impl Scored<'_> {
+    fn is_member(&self) -> bool {
+        matches!(self.entry, ScoredEntry::Member(..))
+    }
+}
+
+impl PartialEq for Scored<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Scored<'_> {}
+
+impl PartialOrd for Scored<'_> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Scored<'_> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // Reversed, since the queue pops its greatest element.
+        other.score.partial_cmp(&self.score).unwrap_or(Ordering::Equal)
+            .then(self.is_member().cmp(&other.is_member()))
+    }
+}
+
+/**
+ An iterator over the members of a quadtree in increasing order of score, with their
+ scores, created by `QuadTree::traverse_best_first`.
+ */
+pub struct BestFirst<'a, N, I> {
+    queue: BinaryHeap<Scored<'a>>,
+    /// The keys of the members already returned, since a member spanning leaves is queued once per leaf.
+    visited: HashSet<[u64; 4]>,
+    score_node: N,
+    score_item: I,
+}
+
+impl<'a, N, I> Iterator for BestFirst<'a, N, I>
+    where N: FnMut(&Rect) -> Option<f64>, I: FnMut(&Item) -> Option<f64> {
+    type Item = (Item, f64);
+
+    fn next(&mut self) -> Option<(Item, f64)> {
+        while let Some(Scored { score, entry }) = self.queue.pop() {
+            let node: &'a Node = match entry {
+                ScoredEntry::Member(item) => {
+                    if self.visited.insert(rect_key(&item.rect)) {
+                        return Some((item, score))
+                    }
+                    continue;
+                },
+                ScoredEntry::Node(node) => node,
+            };
+
+            match node.elements {
+                Elements::Children(ref tl, ref tr, ref br, ref bl) => {
+                    for child in [&**tl, &**tr, &**br, &**bl] {
+                        if let Some(score) = (self.score_node)(&child.rect) {
+                            self.queue.push(Scored { score, entry: ScoredEntry::Node(child) });
+                        }
+                    }
+                },
+                Elements::Members(ref items) => {
+                    for item in items.iter() {
+                        if let Some(score) = (self.score_item)(item) {
+                            self.queue.push(Scored { score, entry: ScoredEntry::Member(*item) });
+                        }
+                    }
+                },
+                Elements::Empty => (),
+            }
+        }
+
+        None
+    }
+}
+
+/**
+ An iterator taking the members out of a quadtree, returned by `QuadTree::drain`.
+ */
+pub struct Drain {
+    root: Rect,
+    placement: Placement,
+    nodes: Vec<Node>,
+    items: vec::IntoIter<Item>,
+}
+
+impl Iterator for Drain {
+    type Item = Rect;
+
+    fn next(&mut self) -> Option<Rect> {
+        loop {
+            if let Some(item) = self.items.next() {
+                return Some(item.rect)
+            }
+
+            let mut node = self.nodes.pop()?;
+            match mem::replace(&mut node.elements, Elements::Empty) {
+                Elements::Children(tl, tr, br, bl) => self.nodes.extend([*bl, *br, *tr, *tl]),
+                Elements::Members(mut items) => {
+                    // A member in several leaves is only yielded from one of them.
+                    items.retain(|item| node.reports_in_page(&self.root, &self.root, item, self.placement));
+                    self.items = items.into_iter();
+                },
+                Elements::Empty => (),
+            }
+        }
+    }
+}
+
+/**
+ A leaf node of the spatial decomposition: its bounds and the members it holds.
+ */
+#[derive(Clone, Debug, PartialEq)]
+pub struct Leaf {
+    pub rect: Rect,
+    pub members: Vec<Rect>,
+}
+
+impl Leaf {
+    /// Check if the leaf holds any members.
+    pub fn is_occupied(&self) -> bool {
+        !self.members.is_empty()
+    }
+}
+
+/**
+ An iterator over the leaves of a quadtree, from top left to bottom left at each level.
+ */
+pub struct Leaves<'a> {
+    nodes_to_visit: Vec<&'a Node>,
+}
+
+impl Iterator for Leaves<'_> {
+    type Item = Leaf;
+
+    fn next(&mut self) -> Option<Leaf> {
+        loop {
+            let node = self.nodes_to_visit.pop()?;
+
+            match node.elements {
+                Elements::Children(ref tl, ref tr, ref br, ref bl) => {
+                    // Push in reverse so the top left child is visited first.
+                    self.nodes_to_visit.push(bl);
+                    self.nodes_to_visit.push(br);
+                    self.nodes_to_visit.push(tr);
+                    self.nodes_to_visit.push(tl);
+                },
+                Elements::Members(ref items) => {
+                    let rects = items.iter().map(|item| item.rect).collect();
+                    return Some(Leaf { rect: node.rect, members: rects })
+                },
+                Elements::Empty => return Some(Leaf { rect: node.rect, members: Vec::new() }),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::geometry::Point;
+    use crate::geometry::Ray;
+    use crate::geometry::Rect;
+    use crate::geometry::Segment;
+    use crate::geometry::Size;
+    use crate::geometry::Relation;
+    use crate::error::Error;
+    use super::{Growth, Item, JoinPredicate, Leaf, Placement, QuadTree, QueryCtx, QueryMode};
+
+    fn rect(x: f64, y: f64, width: f64, height: f64) -> Rect {
+        Rect::new(Point::new(x, y), Size::new(width, height))
+    }
+
+    fn new_tree() -> QuadTree {
+        QuadTree::builder().bounds(rect(0., 0., 64., 64.)).leaf_capacity(1).build()
+    }
+
+    #[test]
+    fn test_translate_in_keeps_a_chain_of_blocked_members() {
+        let mut tree = new_tree();
+        let (a, b, c) = (rect(0., 0., 10., 10.), rect(10., 0., 10., 10.), rect(20., 0., 10., 10.));
+        for member in [a, b, c] {
+            tree.try_insert(member, 0).unwrap();
+        }
+
+        // C blocks B, which then stays put and blocks A from taking its place.
+        let mut blocked = tree.translate_in(&rect(1., 1., 15., 5.), Point::new(10., 0.));
+        blocked.sort_by(|r1, r2| r1.min_x().partial_cmp(&r2.min_x()).unwrap());
+        assert_eq!(blocked, vec![a, b]);
+
+        let mut members = tree.members();
+        members.sort_by(|r1, r2| r1.min_x().partial_cmp(&r2.min_x()).unwrap());
+        assert_eq!(members, vec![a, b, c]);
+        assert_eq!(tree.len(), 3);
+        assert_eq!(tree.validate(), Ok(()));
+    }
+
+    #[test]
+    fn test_translate_in_moves_members_into_space_freed_by_the_selection() {
+        let mut tree = new_tree();
+        let (a, b) = (rect(0., 0., 10., 10.), rect(10., 0., 10., 10.));
+        tree.try_insert(a, 0).unwrap();
+        tree.try_insert(b, 0).unwrap();
+
+        assert!(tree.translate_in(&rect(1., 1., 15., 5.), Point::new(10., 0.)).is_empty());
+        assert!(tree.contains(&rect(10., 0., 10., 10.)));
+        assert!(tree.contains(&rect(20., 0., 10., 10.)));
+        assert!(!tree.contains(&a));
+        assert_eq!(tree.len(), 2);
+    }
+
+    /// Replace `key` with `rect`, checking the tree is left whole either way.
+    fn replace_in(tree: QuadTree, key: Rect, rect: Rect) -> (bool, QuadTree) {
+        let (replaced, tree) = tree.insert_or_replace(&key, rect, 7);
+        assert_eq!(tree.len(), 3);
+        assert_eq!(tree.validate(), Ok(()));
+
+        (replaced, tree)
+    }
+
+    /// Fill `tree` with a member `key`, inserted second, and one either side of it.
+    fn tree_with_key(mut tree: QuadTree) -> (QuadTree, Rect) {
+        let key = rect(40., 1., 2., 2.);
+        for member in [rect(1., 1., 2., 2.), key, rect(40., 40., 2., 2.)] {
+            tree.try_insert(member, 0).unwrap();
+        }
+
+        (tree, key)
+    }
+
+    fn assert_key_kept(tree: &QuadTree, key: &Rect) {
+        let item = tree.get(key).expect("key was lost");
+        assert_eq!((item.priority, item.sequence), (0, 1));
+    }
+
+    #[test]
+    fn test_insert_or_replace_keeps_sequence() {
+        let (tree, key) = tree_with_key(new_tree());
+        let moved = rect(44., 4., 2., 2.);
+        let (replaced, tree) = replace_in(tree, key, moved);
+
+        assert!(replaced);
+        assert!(!tree.contains(&key));
+        let item = tree.get(&moved).unwrap();
+        assert_eq!((item.priority, item.sequence), (7, 1));
+    }
+
+    #[test]
+    fn test_insert_or_replace_keeps_key_on_failure() {
+        let nan = rect(f64::NAN, 0., 1., 1.);
+        let far = rect(1e300, 1e300, 1., 1.);
+        let overlapping = rect(0., 0., 4., 4.);
+        let duplicate = rect(1., 1., 2., 2.);
+        for (bad, reason) in [(nan, "InvalidRect"), (far, "CannotGrow"), (overlapping, "Overlap"), (duplicate, "Duplicate")] {
+            let (mut tree, key) = tree_with_key(new_tree());
+            tree.remove_rect(&key);
+            let err = tree.try_insert(bad, 0).unwrap_err();
+            assert!(format!("{:?}", err).starts_with(reason), "{:?} was rejected with {:?}", bad, err);
+
+            let (tree, key) = tree_with_key(new_tree());
+            let (replaced, tree) = replace_in(tree, key, bad);
+            assert!(!replaced, "{:?} replaced the key", bad);
+            assert_key_kept(&tree, &key);
+        }
+    }
+
+    #[test]
+    fn test_insert_or_replace_keeps_key_when_rect_spans_leaves() {
+        let reject = QuadTree::builder().bounds(rect(0., 0., 64., 64.)).leaf_capacity(1).placement(Placement::Reject).build();
+        let (tree, key) = tree_with_key(reject);
+        let (replaced, tree) = replace_in(tree, key, rect(30., 30., 4., 4.));
+
+        assert!(!replaced);
+        assert_key_kept(&tree, &key);
+    }
+
+    #[test]
+    fn test_insert_or_replace_respects_allow_overlap() {
+        let overlapping = QuadTree::builder().bounds(rect(0., 0., 64., 64.)).leaf_capacity(1).allow_overlap(true).build();
+        let (tree, key) = tree_with_key(overlapping);
+        let (replaced, tree) = replace_in(tree, key, rect(0., 0., 4., 4.));
+
+        assert!(replaced);
+        assert!(!tree.contains(&key));
+    }
+
+    #[test]
+    fn test_insert_all_or_nothing_rolls_back_a_rect_spanning_leaves() {
+        let reject = QuadTree::builder().bounds(rect(0., 0., 64., 64.)).leaf_capacity(1).placement(Placement::Reject).build();
+        let (mut tree, key) = tree_with_key(reject);
+
+        let failures = tree.insert_all_or_nothing(&[rect(1., 40., 2., 2.), rect(30., 30., 4., 4.)]).unwrap_err();
+        assert_eq!(failures.len(), 1);
+        assert!(matches!(failures[0], (1, Error::SpansLeaves(_))));
+        assert_eq!(tree.len(), 3);
+        assert!(!tree.contains(&rect(1., 40., 2., 2.)));
+        assert_key_kept(&tree, &key);
+        assert_eq!(tree.validate(), Ok(()));
+    }
+
+    #[test]
+    fn test_insert_all_or_nothing_restores_the_root_bounds() {
+        let centered = QuadTree::builder().bounds(rect(0., 0., 64., 64.)).growth(Growth::Centered).build();
+        let (mut tree, key) = tree_with_key(centered);
+
+        let failures = tree.insert_all_or_nothing(&[rect(500., 500., 10., 10.), rect(1., 1., 1., 1.)]).unwrap_err();
+        assert!(matches!(failures[..], [(1, Error::Overlap(_))]));
+        assert_eq!(tree.bounds(), rect(0., 0., 64., 64.));
+        assert_eq!(tree.len(), 3);
+        assert_key_kept(&tree, &key);
+
+        tree.try_insert(rect(8., 8., 1., 1.), 0).unwrap();
+        assert_eq!(tree.get(&rect(8., 8., 1., 1.)).unwrap().sequence, 3);
+    }
+
+    /// A tree allowing overlap with `spanning` stored in every leaf, split by a small member in each corner.
+    fn tree_spanned_by(spanning: Rect) -> QuadTree {
+        let mut tree = QuadTree::builder().bounds(rect(0., 0., 64., 64.)).leaf_capacity(1).allow_overlap(true).build();
+        for corner in [rect(1., 1., 1., 1.), rect(61., 1., 1., 1.), rect(61., 61., 1., 1.), rect(1., 61., 1., 1.)] {
+            tree.try_insert(corner, 0).unwrap();
+        }
+        tree.try_insert(spanning, 0).unwrap();
+
+        tree
+    }
+
+    #[test]
+    fn test_join_reports_spanning_pairs_once() {
+        let (big, other_big) = (rect(0., 0., 64., 64.), rect(8., 8., 48., 48.));
+        let pairs = tree_spanned_by(big).join(&tree_spanned_by(other_big), JoinPredicate::Intersects);
+
+        // `big` meets everything in the other tree, and each corner meets its twin.
+        assert_eq!(pairs.iter().filter(|pair| **pair == (big, other_big)).count(), 1);
+        assert!(pairs.iter().enumerate().all(|(i, pair)| !pairs[i + 1..].contains(pair)));
+        assert_eq!(pairs.len(), 9);
+    }
+
+    #[test]
+    fn test_members_lists_spanning_members_once() {
+        let big = rect(0., 0., 64., 64.);
+        let tree = tree_spanned_by(big);
+        let members = tree.members();
+
+        assert!(tree.leaves().filter(|leaf| leaf.members.contains(&big)).count() > 1);
+        assert_eq!(members.iter().filter(|rect| **rect == big).count(), 1);
+        assert_eq!(members.len(), 5);
+        assert_eq!(tree.len(), 5);
+    }
+
+    #[test]
+    fn test_not_covered_by() {
+        let tree = tree_spanned_by(rect(0., 0., 64., 64.));
+        let mut other = new_tree();
+        other.try_insert(rect(60., 60., 4., 4.), 0).unwrap();
+
+        let mut uncovered = tree.not_covered_by(&other);
+        uncovered.sort_by(|r1, r2| (r1.min_y(), r1.min_x()).partial_cmp(&(r2.min_y(), r2.min_x())).unwrap());
+        assert_eq!(uncovered, vec![rect(1., 1., 1., 1.), rect(61., 1., 1., 1.), rect(1., 61., 1., 1.)]);
+        assert_eq!(tree.not_covered_by(&new_tree()).len(), 5);
+    }
+
+    #[test]
+    fn test_summaries_at_depth_count_spanning_members_once_per_cell() {
+        let big = rect(0., 0., 64., 64.);
+        let mut tree = tree_spanned_by(big);
+        tree.set_priority(&rect(61., 61., 1., 1.), 5);
+
+        let root = tree.summaries_at_depth(0);
+        assert_eq!(root.len(), 1);
+        assert_eq!((root[0].count, root[0].content_bounds, root[0].max_priority), (5, Some(big), Some(5)));
+
+        let cells = tree.summaries_at_depth(1);
+        assert_eq!(cells.iter().map(|cell| cell.cell).collect::<Vec<_>>(), QuadTree::child_rects(&big).to_vec());
+        assert!(cells.iter().all(|cell| cell.count == 2 && cell.content_bounds == Some(big)));
+        assert_eq!(cells.iter().map(|cell| cell.max_priority).collect::<Vec<_>>(), vec![Some(0), Some(0), Some(5), Some(0)]);
+    }
+
+    #[test]
+    fn test_summaries_at_depth_include_empty_cells() {
+        let mut tree = new_tree();
+        tree.try_insert(rect(1., 1., 1., 1.), 0).unwrap();
+        tree.try_insert(rect(61., 1., 1., 1.), 0).unwrap();
+
+        let counts: Vec<(usize, Option<i32>)> = tree.summaries_at_depth(3).iter().map(|cell| (cell.count, cell.max_priority)).collect();
+        assert_eq!(counts, vec![(1, Some(0)), (1, Some(0)), (0, None), (0, None)]);
+        assert_eq!(tree.summaries_at_depth(3)[3].content_bounds, None);
+    }
+
+    #[test]
+    fn test_sweep_pairs_finds_tunnelling_members() {
+        let mut tree = new_tree();
+        let (bullet, wall, far) = (rect(0., 10., 1., 1.), rect(30., 0., 2., 40.), rect(50., 50., 4., 4.));
+        for member in [bullet, wall, far] {
+            tree.try_insert(member, 0).unwrap();
+        }
+
+        // The bullet skips clean over the wall in one step, and reaches it after a quarter of it.
+        let pairs = tree.sweep_pairs(|rect| if *rect == bullet { Point::new(116., 0.) } else { Point::new(0., 0.) }, 1.);
+        assert_eq!(pairs.len(), 1);
+        let pair = (pairs[0].rect, pairs[0].other_rect);
+        assert!(pair == (bullet, wall) || pair == (wall, bullet));
+        assert!((pairs[0].time_of_impact - 0.25).abs() < 1e-9);
+
+        assert!(tree.sweep_pairs(|_| Point::new(0., 0.), 1.).is_empty());
+    }
+
+    #[test]
+    fn test_crop_keeps_items_and_clips() {
+        let mut tree = new_tree();
+        let (p, q, m) = (rect(1., 1., 1., 1.), rect(40., 40., 1., 1.), rect(20., 20., 4., 4.));
+        for (member, priority) in [(p, 1), (q, 2), (m, 3)] {
+            tree.try_insert(member, priority).unwrap();
+        }
+
+        let (cropped, rejected) = tree.crop(&rect(0., 0., 22., 22.), true);
+        assert!(rejected.is_empty());
+        assert_eq!(cropped.config(), tree.config());
+        assert_eq!(cropped.bounds(), rect(1., 1., 21., 21.));
+        assert_eq!(cropped.members_in_insertion_order(), vec![Item { rect: p, priority: 1, sequence: 0 },
+                                                             Item { rect: rect(20., 20., 2., 2.), priority: 3, sequence: 2 }]);
+
+        let (empty, rejected) = tree.crop(&rect(50., 0., 4., 4.), false);
+        assert!(empty.is_empty() && rejected.is_empty());
+        assert_eq!(empty.config(), tree.config());
+    }
+
+    #[test]
+    fn test_crop_returns_rejected_members() {
+        let mut tree = QuadTree::builder().bounds(rect(0., 0., 64., 64.)).leaf_capacity(1).placement(Placement::Reject).build();
+        let (p, q, m) = (rect(1., 1., 1., 1.), rect(40., 40., 1., 1.), rect(20., 20., 4., 4.));
+        for member in [p, q, m] {
+            tree.try_insert(member, 0).unwrap();
+        }
+
+        // The cropped root's center falls inside `m`, which then spans its children.
+        let (cropped, rejected) = tree.crop(&rect(0., 0., 64., 64.), false);
+        assert_eq!(rejected, vec![m]);
+        assert_eq!(cropped.len(), 2);
+        assert!(cropped.contains(&p) && cropped.contains(&q));
+    }
+
+    #[test]
+    fn test_filter_map_returns_rejected_items() {
+        let mut tree = new_tree();
+        for x in [0., 10., 20.] {
+            tree.try_insert(rect(x, 0., 4., 4.), 0).unwrap();
+        }
+
+        // Growing every member makes the second overlap the first, and drops the third.
+        let (grown, rejected) = tree.filter_map(|item| {
+            if item.rect.min_x() < 20. { Some(Item { rect: item.rect.inflate(4.), ..item }) } else { None }
+        });
+        assert_eq!(grown.members(), vec![rect(-4., -4., 12., 12.)]);
+        assert_eq!(rejected, vec![Item { rect: rect(6., -4., 12., 12.), priority: 0, sequence: 1 }]);
+    }
+
+    #[test]
+    fn test_filter_calls_keep_once_per_member() {
+        let tree = tree_spanned_by(rect(0., 0., 64., 64.));
+        let mut calls = 0;
+        let tree = tree.filter(|item| {
+            calls += 1;
+            item.rect.width() > 1.
+        });
+
+        assert_eq!(calls, 5);
+        assert_eq!(tree.members(), vec![rect(0., 0., 64., 64.)]);
+        assert_eq!(tree.len(), 1);
+    }
+
+    /// Sort rects by their lower-left corner, so results can be compared regardless of leaf order.
+    fn sorted(mut rects: Vec<Rect>) -> Vec<Rect> {
+        rects.sort_by(|r1, r2| (r1.min_y(), r1.min_x()).partial_cmp(&(r2.min_y(), r2.min_x())).unwrap());
+        rects
+    }
+
+    fn leaves_holding(tree: &QuadTree, rect: &Rect) -> usize {
+        tree.leaves().filter(|leaf| leaf.members.contains(rect)).count()
+    }
+
+    #[test]
+    fn test_points_on_shared_edges_are_stored_once() {
+        let mut tree = new_tree();
+        tree.try_insert(rect(1., 1., 1., 1.), 0).unwrap();
+        tree.try_insert(rect(61., 61., 1., 1.), 0).unwrap();
+
+        // The root's center lies on the edge of all four children.
+        let center = Point::new(32., 32.);
+        tree.insert_point(center, 0).unwrap();
+        let point = rect(32., 32., 0., 0.);
+        assert_eq!(leaves_holding(&tree, &point), 1);
+        assert!(tree.get(&point).unwrap().is_point());
+        assert_eq!(tree.query(&rect(31., 31., 2., 2.)), vec![point]);
+        assert_eq!(tree.len(), 3);
+        assert!(matches!(tree.insert_point(center, 0), Err(Error::Duplicate(_))));
+    }
+
+    #[test]
+    fn test_query_pages_return_each_member_once() {
+        let big = rect(0., 0., 64., 64.);
+        let tree = tree_spanned_by(big);
+
+        let mut page = tree.query_page(&big, 2);
+        let mut rects = Vec::new();
+        loop {
+            assert!(page.rects.len() <= 2);
+            rects.extend(page.rects);
+            match page.next {
+                Some(token) => page = tree.query_page_after(&token, 2),
+                None => break,
+            }
+        }
+        assert_eq!(sorted(rects), sorted(tree.members()));
+
+        assert_eq!(tree.query_page(&big, 0).rects.len(), 1);
+        let empty = tree.query_page(&rect(100., 100., 1., 1.), 2);
+        assert!(empty.rects.is_empty() && empty.next.is_none());
+    }
+
+    #[test]
+    fn test_reinserted_members_go_to_the_end_of_insertion_order() {
+        let mut tree = new_tree();
+        let (a, b, c) = (rect(1., 1., 2., 2.), rect(40., 1., 2., 2.), rect(40., 40., 2., 2.));
+        for member in [a, b, c] {
+            tree.try_insert(member, 0).unwrap();
+        }
+        assert!(tree.remove_rect(&b));
+        tree.try_insert(b, 0).unwrap();
+
+        let order: Vec<(Rect, u64)> = tree.members_in_insertion_order().iter().map(|item| (item.rect, item.sequence)).collect();
+        assert_eq!(order, vec![(a, 0), (c, 2), (b, 3)]);
+        let found: Vec<Rect> = tree.query_in_insertion_order(&rect(32., 0., 32., 64.)).iter().map(|item| item.rect).collect();
+        assert_eq!(found, vec![c, b]);
+        assert!(tree.query_in_insertion_order(&rect(10., 10., 1., 1.)).is_empty());
+    }
+
+    #[test]
+    fn test_insertion_order_of_spanning_members() {
+        let mut tree = QuadTree::builder().bounds(rect(0., 0., 64., 64.)).allow_overlap(true).max_depth(4).build();
+        // Each band spans a row of leaves, and each is inserted before the bands above it.
+        for i in (0..32).rev() {
+            tree.try_insert(rect(0., i as f64 * 2., 64., 1.), 0).unwrap();
+        }
+
+        let order: Vec<Rect> = tree.members_in_insertion_order().iter().map(|item| item.rect).collect();
+        let expected: Vec<Rect> = (0..32).rev().map(|i| rect(0., i as f64 * 2., 64., 1.)).collect();
+        assert_eq!(order, expected);
+    }
+
+    #[test]
+    fn test_retain_calls_keep_once_per_member() {
+        let big = rect(0., 0., 64., 64.);
+        let mut tree = tree_spanned_by(big);
+        let mut calls = 0;
+        tree.retain(|rect| {
+            calls += 1;
+            *rect != big
+        });
+
+        assert_eq!(calls, 5);
+        assert_eq!(leaves_holding(&tree, &big), 0);
+        assert_eq!(tree.len(), 4);
+        assert_eq!(tree.validate(), Ok(()));
+    }
+
+    #[test]
+    fn test_retain_calls_keep_once_per_member_after_growth() {
+        let mut tree = grown_tree();
+        let mut kept = Vec::new();
+        tree.retain(|rect| {
+            kept.push(*rect);
+            rect.width() == 0.
+        });
+
+        assert_eq!(sorted(kept), sorted(grown_tree().members()));
+        assert_eq!(tree.members(), vec![rect(64., 10., 0., 10.)]);
+        assert_eq!(tree.validate(), Ok(()));
+    }
+
+    #[test]
+    fn test_removals_coalesce_leaves() {
+        let corners = [rect(1., 1., 1., 1.), rect(61., 1., 1., 1.), rect(61., 61., 1., 1.), rect(1., 61., 1., 1.)];
+        let mut tree = QuadTree::builder().bounds(rect(0., 0., 64., 64.)).leaf_capacity(2).build();
+        for corner in corners {
+            tree.try_insert(corner, 0).unwrap();
+        }
+        assert_eq!(tree.leaves().count(), 4);
+
+        // Three members would still overflow a leaf, but two fit in one.
+        tree.remove_rect(&corners[0]);
+        assert_eq!(tree.leaves().count(), 4);
+        tree.remove_rect(&corners[1]);
+        assert_eq!(tree.leaves().count(), 1);
+        assert_eq!(sorted(tree.members()), vec![corners[3], corners[2]]);
+        assert_eq!(tree.validate(), Ok(()));
+    }
+
+    #[test]
+    fn test_removals_collapse_empty_subtrees_without_coalescing() {
+        let corners = [rect(1., 1., 1., 1.), rect(61., 1., 1., 1.), rect(61., 61., 1., 1.), rect(1., 61., 1., 1.)];
+        let mut tree = QuadTree::builder().bounds(rect(0., 0., 64., 64.)).coalesce(false).build();
+        for corner in corners {
+            tree.try_insert(corner, 0).unwrap();
+        }
+
+        assert_eq!(tree.remove_many(&corners[..3]).len(), 3);
+        assert_eq!(tree.leaves().count(), 4);
+        assert!(tree.remove_rect(&corners[3]));
+        assert_eq!(tree.leaves().count(), 1);
+        assert!(tree.is_empty());
+    }
+
+    #[test]
+    fn test_reject_placement_stores_each_member_once() {
+        let mut tree = QuadTree::builder().bounds(rect(0., 0., 64., 64.)).leaf_capacity(1).placement(Placement::Reject).build();
+        let middle = rect(30., 30., 4., 4.);
+        tree.try_insert(middle, 0).unwrap();
+        tree.try_insert(rect(1., 1., 1., 1.), 0).unwrap();
+
+        // The root can't split while `middle` would span its children.
+        assert_eq!(tree.leaves().count(), 1);
+        assert!(tree.remove_rect(&middle));
+        tree.try_insert(rect(61., 61., 1., 1.), 0).unwrap();
+        assert_eq!(tree.leaves().count(), 4);
+        assert!(matches!(tree.try_insert(middle, 0), Err(Error::SpansLeaves(_))));
+        assert_eq!(tree.report().duplicated_members, 0);
+    }
+
+    #[test]
+    fn test_query_related_finds_each_relation() {
+        let region = rect(10., 10., 10., 10.);
+        let mut tree = QuadTree::builder().bounds(rect(0., 0., 64., 64.)).leaf_capacity(1).allow_overlap(true).build();
+        let related = [
+            (Relation::Equals, region),
+            (Relation::Within, rect(12., 12., 2., 2.)),
+            (Relation::Contains, rect(5., 5., 20., 20.)),
+            (Relation::Overlaps, rect(18., 18., 4., 4.)),
+            (Relation::Touches, rect(20., 10., 2., 2.)),
+            (Relation::Disjoint, rect(50., 50., 2., 2.)),
+        ];
+        for (_, member) in related {
+            tree.try_insert(member, 0).unwrap();
+        }
+
+        for (relation, member) in related {
+            assert_eq!(tree.query_related(&region, relation), vec![member], "{:?}", relation);
+        }
+        assert!(tree.query_related(&rect(40., 10., 2., 2.), Relation::Touches).is_empty());
+    }
+
+    #[test]
+    fn test_query_into_appends_each_member_once() {
+        let big = rect(0., 0., 64., 64.);
+        let tree = tree_spanned_by(big);
+        let sentinel = rect(-1., -1., 1., 1.);
+
+        let mut out = vec![sentinel];
+        tree.query_into(&big, &mut out);
+        assert_eq!(out[0], sentinel);
+        assert_eq!(out.len(), 6);
+        assert_eq!(out.iter().filter(|rect| **rect == big).count(), 1);
+
+        tree.query_into(&rect(100., 100., 1., 1.), &mut out);
+        assert_eq!(out.len(), 6);
+    }
+
+    #[test]
+    fn test_query_each_matches_query_after_growth() {
+        let tree = grown_tree();
+        for x in (0..128).step_by(8) {
+            for y in (0..128).step_by(8) {
+                let region = rect(x as f64, y as f64, 12., 12.);
+                let mut found = Vec::new();
+                tree.query_each(&region, |rect| found.push(*rect));
+                assert_eq!(sorted(found), sorted(tree.query(&region)), "{:?}", region);
+            }
+        }
+    }
+
+    #[test]
+    fn test_query_with_modes() {
+        let big = rect(0., 0., 64., 64.);
+        let tree = tree_spanned_by(big);
+        let mut ctx = QueryCtx::with_capacity(8);
+
+        // The region misses the corner stored in the same leaf.
+        let region = rect(10., 10., 1., 1.);
+        let broad: Vec<Rect> = tree.query_with(&mut ctx, &region, QueryMode::Broad).iter().map(|item| item.rect).collect();
+        assert_eq!(sorted(broad), vec![big, rect(1., 1., 1., 1.)]);
+        let exact: Vec<Rect> = tree.query_with(&mut ctx, &region, QueryMode::Exact).iter().map(|item| item.rect).collect();
+        assert_eq!(exact, vec![big]);
+        assert!(tree.query_with(&mut ctx, &rect(100., 100., 1., 1.), QueryMode::Broad).is_empty());
+    }
+
+    #[test]
+    fn test_growth_strategies() {
+        let expected = [
+            (Growth::Doubling, rect(0., 0., 128., 128.)),
+            (Growth::Centered, rect(-50.5, -50.5, 206., 206.)),
+            (Growth::PowerOfTwo, rect(0., 0., 128., 128.)),
+            (Growth::Fit, rect(0., 0., 104., 104.)),
+        ];
+        for (growth, bounds) in expected {
+            let mut tree = QuadTree::builder().bounds(rect(0., 0., 64., 64.)).growth(growth).build();
+            tree.try_insert(rect(1., 1., 1., 1.), 0).unwrap();
+            tree.try_insert(rect(100., 100., 4., 4.), 0).unwrap();
+
+            assert_eq!(tree.bounds(), bounds, "{:?}", growth);
+            assert_eq!(tree.len(), 2);
+            assert_eq!(tree.validate(), Ok(()));
+            assert!(tree.try_insert(rect(f64::INFINITY, 0., 1., 1.), 0).is_err());
+        }
+    }
+
+    #[test]
+    fn test_growth_stores_edge_members_in_new_leaves() {
+        let member = rect(10., 54., 10., 10.);
+        let mut tree = new_tree();
+        tree.try_insert(member, 0).unwrap();
+        tree.try_insert(rect(10., 100., 4., 4.), 0).unwrap();
+        assert_eq!(tree.validate(), Ok(()));
+
+        // The member's bottom edge is in the leaf grown below the old root, which reports it.
+        let region = rect(0., 64., 64., 10.);
+        assert_eq!(tree.query(&region), vec![member]);
+        assert_eq!(tree.query_page(&region, 10).rects, vec![member]);
+        assert_eq!(leaves_holding(&tree, &member), 2);
+    }
+
+    #[test]
+    fn test_remove_rect_only_removes_exact_members() {
+        let mut tree = tree_spanned_by(rect(0., 0., 64., 64.));
+
+        assert!(!tree.remove_rect(&rect(1., 1., 1., 2.)));
+        assert!(!tree.remove_rect(&rect(100., 100., 1., 1.)));
+        assert_eq!(tree.len(), 5);
+        assert!(tree.remove_rect(&rect(0., 0., 64., 64.)));
+        assert!(!tree.remove_rect(&rect(0., 0., 64., 64.)));
+        assert_eq!(tree.len(), 4);
+        assert_eq!(tree.report().duplicated_members, 0);
+    }
+
+    #[test]
+    fn test_clear_keeps_bounds_and_config() {
+        let mut tree = tree_spanned_by(rect(0., 0., 64., 64.));
+        let config = tree.config();
+        tree.clear();
+
+        assert!(tree.is_empty() && tree.members().is_empty());
+        assert_eq!(tree.bounds(), rect(0., 0., 64., 64.));
+        assert_eq!(tree.config(), config);
+        assert_eq!(tree.leaves().count(), 1);
+        tree.try_insert(rect(1., 1., 1., 1.), 0).unwrap();
+        assert_eq!(tree.get(&rect(1., 1., 1., 1.)).unwrap().sequence, 0);
+    }
+
+    #[test]
+    fn test_drain_yields_each_member_once() {
+        let big = rect(0., 0., 64., 64.);
+        let mut tree = tree_spanned_by(big);
+        let members = tree.members();
+
+        let drained: Vec<Rect> = tree.drain().collect();
+        assert_eq!(sorted(drained), sorted(members));
+        assert!(tree.is_empty());
+        assert_eq!(tree.validate(), Ok(()));
+
+        let mut tree = tree_spanned_by(big);
+        assert!(tree.drain().next().is_some());
+        assert!(tree.members().is_empty());
+        assert_eq!(tree.drain().next(), None);
+    }
+
+    /// A tree grown right and down from 0..64, with members on the old root's right and bottom edges.
+    fn grown_tree() -> QuadTree {
+        let mut tree = new_tree();
+        for member in [rect(64., 10., 0., 10.), rect(10., 54., 10., 10.), rect(100., 10., 4., 4.), rect(10., 100., 4., 4.)] {
+            tree.try_insert(member, 0).unwrap();
+        }
+        tree
+    }
+
+    #[test]
+    fn test_member_values() {
+        let (a, b, c) = (rect(1., 1., 30., 2.), rect(40., 40., 2., 2.), rect(1., 40., 2., 2.));
+        let mut tree: QuadTree<&str> = QuadTree::builder().bounds(rect(0., 0., 64., 64.)).build_with_values();
+        tree.try_insert_with(a, 0, "spans").unwrap();
+        tree.try_insert_with(b, 0, "corner").unwrap();
+        tree.try_insert(c, 0).unwrap();
+        assert!(matches!(tree.try_insert_with(b, 0, "again"), Err(Error::Duplicate(_))));
+        assert_eq!(tree.value(&b), Some(&"corner"));
+        assert_eq!(tree.value(&c), None);
+
+        let leaves: Vec<Leaf> = tree.leaves().collect();
+        let mut tree = tree.map(str::len);
+        assert_eq!(tree.leaves().collect::<Vec<Leaf>>(), leaves);
+        assert_eq!((tree.value(&a), tree.value(&b), tree.value(&c)), (Some(&5), Some(&6), None));
+
+        *tree.value_mut(&b).unwrap() += 1;
+        assert_eq!(tree.translate_in(&b, Point::new(10., 10.)), vec![]);
+        assert_eq!(tree.value(&b), None);
+        assert_eq!(tree.value(&rect(50., 50., 2., 2.)), Some(&7));
+
+        let (inserted, mut tree) = tree.insert_or_replace(&a, rect(1., 1., 20., 2.), 0);
+        assert!(inserted);
+        assert_eq!(tree.value(&rect(1., 1., 20., 2.)), Some(&5));
+
+        assert!(tree.remove_rect(&rect(1., 1., 20., 2.)));
+        tree.try_insert(rect(1., 1., 20., 2.), 0).unwrap();
+        assert_eq!(tree.value(&rect(1., 1., 20., 2.)), None);
+        assert_eq!(tree.remove_in(&rect(0., 0., 64., 64.)).len(), 3);
+        assert_eq!(tree.value(&rect(50., 50., 2., 2.)), None);
+    }
+
+    #[test]
+    fn test_drain_after_growth() {
+        let mut tree = grown_tree();
+        let members = tree.members();
+        assert_eq!(members.len(), 4);
+
+        let drained: Vec<Rect> = tree.drain().collect();
+        assert_eq!(sorted(drained), sorted(members));
+        assert!(tree.is_empty());
+    }
+
+    #[test]
+    fn test_len_tracks_inserts_and_removals() {
+        let mut tree = new_tree();
+        assert_eq!(tree.len(), 0);
+        tree.try_insert(rect(1., 1., 4., 4.), 0).unwrap();
+        tree.try_insert(rect(20., 20., 24., 24.), 0).unwrap();
+        assert_eq!(tree.len(), 2);
+
+        assert!(tree.try_insert(rect(2., 2., 1., 1.), 0).is_err());
+        assert_eq!(tree.len(), 2);
+        assert_eq!(tree.remove_in(&rect(0., 0., 64., 64.)).len(), 2);
+        assert_eq!(tree.len(), 0);
+    }
+
+    #[test]
+    fn test_overlap_errors_report_every_conflict() {
+        let mut tree = new_tree();
+        let (a, b, far) = (rect(0., 0., 4., 4.), rect(10., 0., 4., 4.), rect(40., 40., 4., 4.));
+        for member in [a, b, far] {
+            tree.try_insert(member, 0).unwrap();
+        }
+
+        match tree.try_insert(rect(2., 2., 10., 1.), 0) {
+            Err(Error::Overlap(conflicts)) => assert_eq!(sorted(conflicts), vec![a, b]),
+            result => panic!("unexpected result {:?}", result),
+        }
+        assert_eq!(tree.len(), 3);
+    }
+
+    #[test]
+    fn test_min_cell_size_limits_both_dimensions() {
+        let mut tree = QuadTree::builder().bounds(rect(0., 0., 256., 64.)).min_cell_size(4.).build();
+        for i in 0..8 {
+            tree.try_insert(rect(i as f64 * 0.1, 0., 0.05, 0.05), 0).unwrap();
+        }
+
+        // Splitting the 16 by 4 cells again would make them 2 high.
+        assert_eq!(tree.stats().max_depth, 4);
+        assert!(tree.leaves().all(|leaf| leaf.rect.width() >= 4. && leaf.rect.height() >= 4.));
+        assert_eq!(tree.len(), 8);
+    }
+
+    #[test]
+    fn test_touching_members_are_split() {
+        let mut tree = QuadTree::builder().bounds(rect(0., 0., 64., 64.)).leaf_capacity(4).build();
+        for i in 0..64 {
+            for j in 0..64 {
+                tree.try_insert(rect(i as f64, j as f64, 1., 1.), 0).unwrap();
+            }
+        }
+        // A leaf holds at most the four tiles around a corner of its cell, which share it.
+        assert!(tree.leaves().all(|leaf| leaf.members.len() <= 4));
+        assert_eq!(tree.len(), 4096);
+
+        let mut tree = QuadTree::builder().bounds(rect(0., 0., 64., 64.)).allow_overlap(true).build();
+        let background = rect(0., 0., 64., 64.);
+        tree.try_insert(background, 0).unwrap();
+        tree.try_insert(rect(1., 1., 2., 2.), 0).unwrap();
+        tree.try_insert(rect(40., 40., 2., 2.), 0).unwrap();
+        assert!(tree.leaves().count() > 1);
+        assert_eq!(sorted(tree.query(&rect(1., 1., 1., 1.))), sorted(vec![background, rect(1., 1., 2., 2.)]));
+        assert_eq!(tree.validate(), Ok(()));
+    }
+
+    #[test]
+    fn test_largest_empty_rect_in() {
+        let mut tree = new_tree();
+        tree.try_insert(rect(1., 1., 1., 1.), 0).unwrap();
+        tree.try_insert(rect(61., 61., 1., 1.), 0).unwrap();
+
+        // Both empty quadrants are the same size until the region clips one of them.
+        assert_eq!(tree.largest_empty_rect_in(&rect(0., 0., 64., 48.)), Some(rect(32., 0., 32., 32.)));
+        assert_eq!(tree.largest_empty_rect_in(&rect(2., 2., 4., 4.)), None);
+        assert_eq!(tree.largest_empty_rect_in(&rect(100., 100., 4., 4.)), None);
+        assert_eq!(new_tree().largest_empty_rect_in(&rect(-8., -8., 16., 16.)), Some(rect(0., 0., 8., 8.)));
+    }
+
+    #[test]
+    fn test_hit_test_orders_by_z() {
+        let mut tree = QuadTree::builder().bounds(rect(0., 0., 64., 64.)).allow_overlap(true).build();
+        let (a, b) = (rect(0., 0., 10., 10.), rect(5., 5., 10., 10.));
+        for member in [a, b, rect(40., 40., 4., 4.)] {
+            tree.try_insert(member, 0).unwrap();
+        }
+
+        assert_eq!(tree.hit_test(Point::new(6., 6.), |rect| rect.min_x()), vec![b, a]);
+        assert_eq!(tree.hit_test(Point::new(6., 6.), |rect| -rect.min_x()), vec![a, b]);
+        assert_eq!(tree.hit_test(Point::new(2., 2.), |rect| rect.min_x()), vec![a]);
+        assert!(tree.hit_test(Point::new(30., 30.), |rect| rect.min_x()).is_empty());
+        assert!(tree.hit_test(Point::new(-30., 30.), |rect| rect.min_x()).is_empty());
+    }
+
+    #[test]
+    fn test_topmost_at_prefers_higher_priority() {
+        let mut tree = QuadTree::builder().bounds(rect(0., 0., 64., 64.)).allow_overlap(true).build();
+        let (a, b) = (rect(0., 0., 10., 10.), rect(5., 5., 10., 10.));
+        tree.try_insert(a, 2).unwrap();
+        tree.try_insert(b, 1).unwrap();
+        tree.try_insert(rect(40., 40., 4., 4.), 9).unwrap();
+
+        assert_eq!(tree.topmost_at(Point::new(6., 6.)), Some(a));
+        assert_eq!(tree.topmost_at(Point::new(12., 12.)), Some(b));
+        assert_eq!(tree.topmost_at(Point::new(30., 30.)), None);
+        assert_eq!(tree.topmost_at(Point::new(100., 100.)), None);
+    }
+
+    #[test]
+    fn test_set_priority_updates_every_copy() {
+        let big = rect(0., 0., 64., 64.);
+        let mut tree = tree_spanned_by(big);
+        assert_eq!(tree.get(&big).map(|item| item.priority), Some(0));
+
+        assert!(tree.set_priority(&big, 9));
+        assert_eq!(tree.get(&big).map(|item| item.priority), Some(9));
+        assert_eq!(tree.root().max_priority(), 9);
+        assert!(tree.root().children().unwrap().iter().all(|child| child.max_priority() == 9));
+        assert_eq!(tree.topmost_at(Point::new(1.5, 1.5)), Some(big));
+    }
+
+    #[test]
+    fn test_get_and_set_priority_of_a_non_member() {
+        let mut tree = tree_spanned_by(rect(0., 0., 64., 64.));
+        let near_miss = rect(1., 1., 1., 2.);
+
+        assert_eq!(tree.get(&near_miss), None);
+        assert!(!tree.set_priority(&near_miss, 9));
+        assert!(!tree.set_priority(&rect(100., 100., 1., 1.), 9));
+        assert_eq!(tree.root().max_priority(), 0);
+    }
+
+    #[test]
+    fn test_locate() {
+        let big = rect(0., 0., 64., 64.);
+        let tree = tree_spanned_by(big);
+        let corner = rect(1., 1., 1., 1.);
+
+        let quadrant = tree.root().quadrant_of(Point::new(1.5, 1.5)).unwrap();
+        let path = tree.locate(&corner).unwrap();
+        assert_eq!(path.quadrants, vec![quadrant]);
+        assert_eq!(path.to_quadkey(), quadrant.quadkey_digit().to_string());
+
+        // A member spanning every child is held by the root.
+        assert_eq!(tree.locate(&big).unwrap().to_quadkey(), "");
+        assert_eq!(tree.locate(&rect(1., 1., 1., 2.)), None);
+        assert_eq!(new_tree().locate(&corner), None);
+    }
+
+    #[test]
+    fn test_expand_to_include() {
+        let mut tree = new_tree();
+        tree.try_insert(rect(1., 1., 1., 1.), 0).unwrap();
+
+        assert!(tree.expand_to_include(&rect(8., 8., 8., 8.)).is_ok());
+        assert_eq!(tree.bounds(), rect(0., 0., 64., 64.));
+        assert!(tree.expand_to_include(&rect(100., -20., 4., 4.)).is_ok());
+        assert!(tree.bounds().contains(&rect(100., -20., 4., 4.)));
+        assert!(tree.contains(&rect(1., 1., 1., 1.)));
+        assert_eq!(tree.len(), 1);
+
+        let bounds = tree.bounds();
+        for bad in [rect(f64::INFINITY, 0., 1., 1.), rect(1e300, 1e300, 1., 1.)] {
+            assert!(matches!(tree.expand_to_include(&bad), Err(Error::CannotGrow(_))), "{:?} was reached", bad);
+            assert_eq!(tree.bounds(), bounds);
+        }
+    }
+
+    #[test]
+    fn test_raycast_first() {
+        let mut tree = new_tree();
+        let (near, far) = (rect(10., 0., 2., 10.), rect(20., 0., 2., 10.));
+        tree.try_insert(near, 0).unwrap();
+        tree.try_insert(far, 0).unwrap();
+
+        let hit = tree.raycast_first(&Ray::new(Point::new(0., 5.), Point::new(1., 0.))).unwrap();
+        assert_eq!((hit.item.rect, hit.t, hit.point, hit.normal), (near, 10., Point::new(10., 5.), Point::new(-1., 0.)));
+        let hit = tree.raycast_first(&Ray::new(Point::new(30., 5.), Point::new(-1., 0.))).unwrap();
+        assert_eq!((hit.item.rect, hit.normal), (far, Point::new(1., 0.)));
+
+        // Starting inside a member hits it straight away, with no normal.
+        let hit = tree.raycast_first(&Ray::new(Point::new(11., 5.), Point::new(1., 0.))).unwrap();
+        assert_eq!((hit.item.rect, hit.t, hit.normal), (near, 0., Point::new(0., 0.)));
+
+        assert_eq!(tree.raycast_first(&Ray::new(Point::new(0., 5.), Point::new(-1., 0.))), None);
+        assert_eq!(tree.raycast_first(&Ray::new(Point::new(0., 30.), Point::new(1., 0.))), None);
+    }
+
+    #[test]
+    fn test_segment_crossings() {
+        let mut tree = new_tree();
+        let (near, far) = (rect(10., 0., 2., 10.), rect(20., 0., 2., 10.));
+        tree.try_insert(near, 0).unwrap();
+        tree.try_insert(far, 0).unwrap();
+
+        let crossings = tree.segment_crossings(&Segment::new(Point::new(0., 5.), Point::new(40., 5.)));
+        let found: Vec<(Rect, f64, f64)> = crossings.iter().map(|crossing| (crossing.rect, crossing.enter, crossing.exit)).collect();
+        assert_eq!(found, vec![(near, 0.25, 0.3), (far, 0.5, 0.55)]);
+
+        let backwards = tree.segment_crossings(&Segment::new(Point::new(40., 5.), Point::new(0., 5.)));
+        assert_eq!(backwards.iter().map(|crossing| crossing.rect).collect::<Vec<_>>(), vec![far, near]);
+
+        // The segment stops short of `far`, or misses both.
+        assert_eq!(tree.segment_crossings(&Segment::new(Point::new(0., 5.), Point::new(15., 5.))).len(), 1);
+        assert!(tree.segment_crossings(&Segment::new(Point::new(0., 30.), Point::new(40., 30.))).is_empty());
+    }
+
+    #[test]
+    fn test_insert_many_reports_each_rect() {
+        let mut tree = new_tree();
+        let (a, far) = (rect(1., 1., 2., 2.), rect(200., 200., 2., 2.));
+        let nan = rect(f64::NAN, 0., 1., 1.);
+
+        let results = tree.insert_many([a, a, nan, far]);
+        assert_eq!(results.len(), 4);
+        assert!(matches!(results[..], [Ok(_), Err(Error::Duplicate(_)), Err(Error::InvalidRect(_)), Ok(_)]));
+        assert_eq!((results[0].as_ref().ok(), results[3].as_ref().ok()), (Some(&a), Some(&far)));
+        assert_eq!(tree.len(), 2);
+        assert_eq!(tree.validate(), Ok(()));
+
+        let bounds = tree.bounds();
+        assert!(tree.insert_many(Vec::new()).is_empty());
+        assert_eq!((tree.len(), tree.bounds()), (2, bounds));
+    }
+
+    #[test]
+    fn test_pairs_within() {
+        let mut tree = new_tree();
+        let mut other = new_tree();
+        let (a, b, c) = (rect(0., 0., 2., 2.), rect(5., 0., 2., 2.), rect(40., 40., 2., 2.));
+        tree.try_insert(a, 0).unwrap();
+        other.try_insert(b, 0).unwrap();
+        other.try_insert(c, 0).unwrap();
+
+        assert_eq!(tree.pairs_within(&other, 3.), vec![(a, b)]);
+        assert!(tree.pairs_within(&other, 2.9).is_empty());
+        assert_eq!(tree.pairs_within(&other, 100.).len(), 2);
+        assert!(tree.pairs_within(&new_tree(), 100.).is_empty());
+    }
+
+    #[test]
+    fn test_cluster() {
+        let mut tree = new_tree();
+        for corner in [rect(1., 1., 1., 1.), rect(61., 1., 1., 1.), rect(61., 61., 1., 1.), rect(1., 61., 1., 1.)] {
+            tree.try_insert(corner, 0).unwrap();
+        }
+
+        let root = tree.cluster(0);
+        assert_eq!(root.len(), 1);
+        assert_eq!((root[0].cell, root[0].bounds), (rect(0., 0., 64., 64.), rect(1.5, 1.5, 60., 60.)));
+        assert_eq!((root[0].centroid, root[0].members.len()), (Point::new(31.5, 31.5), 4));
+
+        let cells = tree.cluster(1);
+        assert_eq!(cells.len(), 4);
+        assert!(cells.iter().all(|cluster| cluster.members.len() == 1 && cluster.cell.contains(&cluster.members[0])));
+        // Leaves shallower than the depth form one cell each.
+        assert_eq!(tree.cluster(5), cells);
+        assert!(new_tree().cluster(1).is_empty());
+    }
+
+    #[test]
+    fn test_pop_nearest() {
+        let mut tree = new_tree();
+        let (a, b) = (rect(1., 1., 2., 2.), rect(40., 40., 2., 2.));
+        tree.try_insert(a, 3).unwrap();
+        tree.try_insert(b, 4).unwrap();
+
+        assert_eq!(tree.pop_nearest(Point::new(30., 30.)), Some(Item { rect: b, priority: 4, sequence: 1 }));
+        assert_eq!(tree.len(), 1);
+        assert_eq!(tree.pop_nearest(Point::new(30., 30.)).map(|item| item.rect), Some(a));
+        assert_eq!(tree.pop_nearest(Point::new(30., 30.)), None);
+        assert!(tree.is_empty());
+    }
+
+    #[test]
+    fn test_traverse_best_first_visits_in_score_order() {
+        let mut tree = new_tree();
+        for x in [40., 1., 61., 20.] {
+            tree.try_insert(rect(x, x, 1., 1.), 0).unwrap();
+        }
+
+        // Score by left edge, skipping the member at 20.
+        let visited: Vec<(f64, f64)> = tree.traverse_best_first(|node_rect| Some(node_rect.min_x()),
+                                                                |item| Some(item.rect.min_x()).filter(|x| *x != 20.))
+            .map(|(item, score)| (item.rect.min_x(), score)).collect();
+        assert_eq!(visited, vec![(1., 1.), (40., 40.), (61., 61.)]);
+
+        assert_eq!(tree.traverse_best_first(|_| None, |item| Some(item.rect.min_x())).next(), None);
+        assert_eq!(new_tree().traverse_best_first(|_| Some(0.), |_| Some(0.)).next(), None);
+    }
+
+    #[test]
+    fn test_query_n_stops_early() {
+        let big = rect(0., 0., 64., 64.);
+        let tree = tree_spanned_by(big);
+
+        assert_eq!(tree.query_n(&big, 2).len(), 2);
+        assert_eq!(sorted(tree.query_n(&big, 10)), sorted(tree.members()));
+        assert!(tree.query_n(&big, 0).is_empty());
+        assert!(tree.query_n(&rect(100., 100., 1., 1.), 2).is_empty());
+    }
+
+    #[test]
+    fn test_query_while() {
+        let big = rect(0., 0., 64., 64.);
+        let tree = tree_spanned_by(big);
+
+        let mut visited = 0;
+        assert!(!tree.query_while(&big, |_| {
+            visited += 1;
+            false
+        }));
+        assert_eq!(visited, 1);
+
+        let mut visited = Vec::new();
+        assert!(tree.query_while(&big, |rect| {
+            visited.push(*rect);
+            true
+        }));
+        assert_eq!(sorted(visited), sorted(tree.members()));
+        assert!(tree.query_while(&rect(100., 100., 1., 1.), |_| panic!("visited a member outside the region")));
+    }
+}
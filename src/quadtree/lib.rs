@@ -1,8 +0,0 @@
-#![crate_id="quadtree#0.1.0"]
-#![crate_type = "lib"]
-
-#![desc = "A basic quadtree library."]
-#![license = "MIT"]
-
-pub mod geometry;
-pub mod quadtree;
@@ -0,0 +1,197 @@
+use crate::geometry::Point;
+use crate::geometry::Rect;
+use crate::geometry::Size;
+use crate::quadtree::QuadTree;
+use crate::split::SplitPolicy;
+
+/// How many times a member is subdivided while testing it against a region.
+const MAX_SUBDIVISIONS: usize = 6;
+
+/**
+ A query region built from rects and circles combined with union, intersection, and
+ difference, e.g. `Region::rect(a).union(Region::circle(c, r)).minus(Region::rect(hole))`.
+ All shapes are closed, including their boundaries.
+ */
+#[derive(Clone, Debug, PartialEq)]
+pub enum Region {
+    Rect(Rect),
+    /// A disc with a center and radius.
+    Circle(Point, f64),
+    Union(Box<Region>, Box<Region>),
+    Intersection(Box<Region>, Box<Region>),
+    /// The points of the first region that aren't in the second.
+    Difference(Box<Region>, Box<Region>),
+}
+
+/**
+ How much of a rect a region covers, as far as can be told cheaply.
+ */
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Coverage {
+    /// The rect and the region have no point in common.
+    Outside,
+    /// The region covers the whole rect.
+    Inside,
+    /// The region covers part of the rect, or may cover some of it.
+    Partial,
+}
+
+impl Region {
+    pub fn rect(rect: Rect) -> Region {
+        Region::Rect(rect)
+    }
+
+    pub fn circle(center: Point, radius: f64) -> Region {
+        Region::Circle(center, radius)
+    }
+
+    pub fn union(self, other: Region) -> Region {
+        Region::Union(Box::new(self), Box::new(other))
+    }
+
+    pub fn intersect(self, other: Region) -> Region {
+        Region::Intersection(Box::new(self), Box::new(other))
+    }
+
+    pub fn minus(self, other: Region) -> Region {
+        Region::Difference(Box::new(self), Box::new(other))
+    }
+
+    /**
+     Check if `point` lies in the region.
+     */
+    pub fn contains_point(&self, point: &Point) -> bool {
+        match *self {
+            Region::Rect(ref rect) => rect.contains_point(point),
+            Region::Circle(center, radius) => distance(&center, point) <= radius,
+            Region::Union(ref a, ref b) => a.contains_point(point) || b.contains_point(point),
+            Region::Intersection(ref a, ref b) => a.contains_point(point) && b.contains_point(point),
+            Region::Difference(ref a, ref b) => a.contains_point(point) && !b.contains_point(point),
+        }
+    }
+
+    /**
+     Find how much of `rect` the region covers. `Outside` and `Inside` are always
+     right, but combined regions may answer `Partial` for a rect they cover entirely
+     or miss, e.g. one straddling both halves of a union.
+     */
+    pub fn coverage(&self, rect: &Rect) -> Coverage {
+        match *self {
+            Region::Rect(ref region) => {
+                if region.contains(rect) {
+                    Coverage::Inside
+                } else if region.intersects(rect) {
+                    Coverage::Partial
+                } else {
+                    Coverage::Outside
+                }
+            },
+            Region::Circle(center, radius) => {
+                let farthest = Point::new((rect.min_x() - center.x).abs().max((rect.max_x() - center.x).abs()),
+                                          (rect.min_y() - center.y).abs().max((rect.max_y() - center.y).abs()));
+                if distance(&Point::new(0., 0.), &farthest) <= radius {
+                    Coverage::Inside
+                } else if rect.distance_to(&Rect::new(center, Size::new(0., 0.))) <= radius {
+                    Coverage::Partial
+                } else {
+                    Coverage::Outside
+                }
+            },
+            Region::Union(ref a, ref b) => match (a.coverage(rect), b.coverage(rect)) {
+                (Coverage::Inside, _) | (_, Coverage::Inside) => Coverage::Inside,
+                (Coverage::Outside, Coverage::Outside) => Coverage::Outside,
+                _ => Coverage::Partial,
+            },
+            Region::Intersection(ref a, ref b) => match (a.coverage(rect), b.coverage(rect)) {
+                (Coverage::Outside, _) | (_, Coverage::Outside) => Coverage::Outside,
+                (Coverage::Inside, Coverage::Inside) => Coverage::Inside,
+                _ => Coverage::Partial,
+            },
+            Region::Difference(ref a, ref b) => match (a.coverage(rect), b.coverage(rect)) {
+                (Coverage::Outside, _) | (_, Coverage::Inside) => Coverage::Outside,
+                (Coverage::Inside, Coverage::Outside) => Coverage::Inside,
+                _ => Coverage::Partial,
+            },
+        }
+    }
+
+    /**
+     Check if `rect` has any point in the region. Where `coverage` can't tell, the rect
+     is subdivided until each piece is clearly inside or outside. A piece still undecided
+     after a few subdivisions counts as intersecting, so a rect within about 1/64 of its
+     size of an intersection's or difference's edge may be reported as intersecting it.
+     Points are always tested exactly.
+     */
+    pub fn intersects(&self, rect: &Rect) -> bool {
+        if rect.is_point() {
+            return self.contains_point(&rect.origin)
+        }
+        self.intersects_within(rect, MAX_SUBDIVISIONS)
+    }
+
+    fn intersects_within(&self, rect: &Rect, subdivisions: usize) -> bool {
+        match self.coverage(rect) {
+            Coverage::Outside => false,
+            Coverage::Inside => true,
+            Coverage::Partial if subdivisions == 0 => true,
+            Coverage::Partial => QuadTree::child_rects(rect).iter()
+                .any(|piece| self.intersects_within(piece, subdivisions - 1)),
+        }
+    }
+}
+
+fn distance(a: &Point, b: &Point) -> f64 {
+    ((a.x - b.x).powi(2) + (a.y - b.y).powi(2)).sqrt()
+}
+
+impl<T, P: SplitPolicy> QuadTree<T, P> {
+    /**
+     Find the members intersecting `region`. Nodes the region doesn't reach are
+     skipped, however the region is combined.
+     */
+    pub fn query_region(&self, region: &Region) -> Vec<Rect> {
+        self.items_in_nodes(|node_rect| region.coverage(node_rect) != Coverage::Outside,
+                            |rect| region.intersects(rect))
+            .into_iter().map(|item| item.rect).collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::geometry::{Point, Rect, Size};
+    use crate::quadtree::QuadTreeBuilder;
+    use super::{Coverage, Region};
+
+    fn rect(x: f64, y: f64, width: f64, height: f64) -> Rect {
+        Rect::new(Point::new(x, y), Size::new(width, height))
+    }
+
+    #[test]
+    fn test_query_region() {
+        let mut tree = QuadTreeBuilder::new().bounds(rect(0., 0., 64., 64.)).leaf_capacity(4).max_depth(12).build();
+        for i in 0..32 {
+            for j in 0..32 {
+                let side = if (i + j) % 3 == 0 { 0. } else { 1. };
+                tree.try_insert(rect(i as f64 * 2., j as f64 * 2., side, side), 0).unwrap();
+            }
+        }
+
+        let region = Region::rect(rect(0., 0., 32., 32.))
+            .union(Region::circle(Point::new(48., 48.), 10.))
+            .minus(Region::rect(rect(8., 8., 16., 16.)).intersect(Region::circle(Point::new(16., 16.), 8.)));
+
+        let mut expected: Vec<Rect> = tree.members().into_iter().filter(|member| region.intersects(member)).collect();
+        let mut found = tree.query_region(&region);
+        assert!(!found.is_empty());
+        expected.sort_by(|a, b| a.origin.x.total_cmp(&b.origin.x).then(a.origin.y.total_cmp(&b.origin.y)));
+        found.sort_by(|a, b| a.origin.x.total_cmp(&b.origin.x).then(a.origin.y.total_cmp(&b.origin.y)));
+        assert_eq!(found, expected);
+
+        // Inside the square, outside the hole and the circle.
+        assert!(region.intersects(&rect(2., 2., 1., 1.)));
+        assert!(!region.intersects(&rect(16., 16., 1., 1.)));
+        assert!(region.intersects(&rect(50., 50., 0., 0.)));
+        assert!(!region.intersects(&rect(34., 2., 1., 1.)));
+        assert_eq!(region.coverage(&rect(40., 0., 8., 8.)), Coverage::Outside);
+    }
+}
@@ -0,0 +1,204 @@
+/*!
+ Drawing a quadtree to a PNG image, for documenting its behavior and debugging
+ datasets too large to draw as vector graphics.
+
+ The root is scaled to fit the image, keeping its aspect ratio, with its top left
+ corner, where `y` is smallest, at the top left of the image.
+ */
+
+use crate::error::Error;
+use crate::geometry::Rect;
+use crate::quadtree::{Node, QuadTree};
+use crate::split::SplitPolicy;
+
+use image::{ImageError, Rgba, RgbaImage};
+
+use std::path::Path;
+
+/**
+ The colors used to draw a tree. Members are filled, then outlined, over the borders
+ of the nodes holding them.
+ */
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RenderStyle {
+    pub background: Rgba<u8>,
+    pub node_border: Rgba<u8>,
+    pub member_fill: Rgba<u8>,
+    pub member_border: Rgba<u8>,
+}
+
+impl Default for RenderStyle {
+    fn default() -> RenderStyle {
+        RenderStyle {
+            background: Rgba([255, 255, 255, 255]),
+            node_border: Rgba([160, 160, 160, 255]),
+            member_fill: Rgba([120, 170, 230, 255]),
+            member_border: Rgba([20, 60, 140, 255]),
+        }
+    }
+}
+
+/**
+ Maps world coordinates within the root to pixels.
+ */
+struct Viewport {
+    bounds: Rect,
+    scale: f64,
+    width: u32,
+    height: u32,
+}
+
+impl Viewport {
+    fn new(bounds: Rect, width: u32, height: u32) -> Viewport {
+        let scale = (width as f64 / bounds.width()).min(height as f64 / bounds.height());
+        let scale = if scale.is_finite() { scale } else { 1. };
+
+        Viewport { bounds, scale, width, height }
+    }
+
+    /**
+     The pixel columns and rows covered by `rect`, clamped to the image. Every rect
+     covers at least one pixel, so points are drawn too.
+     */
+    fn pixels(&self, rect: &Rect) -> (u32, u32, u32, u32) {
+        let column = |x: f64| ((x - self.bounds.min_x()) * self.scale).floor().clamp(0., (self.width - 1) as f64) as u32;
+        let row = |y: f64| ((y - self.bounds.min_y()) * self.scale).floor().clamp(0., (self.height - 1) as f64) as u32;
+        let (left, top) = (column(rect.min_x()), row(rect.min_y()));
+        let right = ((rect.max_x() - self.bounds.min_x()) * self.scale).ceil() as u32;
+        let bottom = ((rect.max_y() - self.bounds.min_y()) * self.scale).ceil() as u32;
+
+        (left, top, right.saturating_sub(1).clamp(left, self.width - 1), bottom.saturating_sub(1).clamp(top, self.height - 1))
+    }
+
+    fn fill(&self, image: &mut RgbaImage, rect: &Rect, color: Rgba<u8>) {
+        let (left, top, right, bottom) = self.pixels(rect);
+        for y in top..=bottom {
+            for x in left..=right {
+                image.put_pixel(x, y, color);
+            }
+        }
+    }
+
+    fn outline(&self, image: &mut RgbaImage, rect: &Rect, color: Rgba<u8>) {
+        let (left, top, right, bottom) = self.pixels(rect);
+        for x in left..=right {
+            image.put_pixel(x, top, color);
+            image.put_pixel(x, bottom, color);
+        }
+        for y in top..=bottom {
+            image.put_pixel(left, y, color);
+            image.put_pixel(right, y, color);
+        }
+    }
+}
+
+impl<T, P: SplitPolicy> QuadTree<T, P> {
+    /**
+     Draw the tree's node borders and members into a new `width` by `height` image.
+     */
+    pub fn render_image(&self, width: u32, height: u32, style: &RenderStyle) -> RgbaImage {
+        let mut image = RgbaImage::from_pixel(width, height, style.background);
+        if width == 0 || height == 0 {
+            return image
+        }
+
+        let viewport = Viewport::new(self.bounds(), width, height);
+
+        let mut nodes_to_draw: Vec<&Node> = vec![self.root()];
+        while let Some(node) = nodes_to_draw.pop() {
+            viewport.outline(&mut image, &node.bounds(), style.node_border);
+            if let Some(children) = node.children() {
+                nodes_to_draw.extend(children);
+            }
+        }
+
+        for rect in self.members().iter() {
+            viewport.fill(&mut image, rect, style.member_fill);
+            viewport.outline(&mut image, rect, style.member_border);
+        }
+
+        image
+    }
+
+    /**
+     Draw the tree as `render_image` does, and save it as a PNG file at `path`.
+     Fails with `Serialization`, without creating the file, if either dimension is zero.
+     */
+    pub fn render_png<Q: AsRef<Path>>(&self, path: Q, width: u32, height: u32, style: &RenderStyle) -> Result<(), Error> {
+        if width == 0 || height == 0 {
+            return Err(Error::Serialization(format!("can't encode a {}x{} PNG", width, height)))
+        }
+
+        self.render_image(width, height, style).save_with_format(path, image::ImageFormat::Png).map_err(|err| match err {
+            ImageError::IoError(err) => Error::Io(err),
+            err => Error::Serialization(err.to_string()),
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::error::Error;
+    use crate::geometry::Point;
+    use crate::geometry::Rect;
+    use crate::geometry::Size;
+    use crate::quadtree::QuadTree;
+    use super::RenderStyle;
+
+    fn rect(x: f64, y: f64, width: f64, height: f64) -> Rect {
+        Rect::new(Point::new(x, y), Size::new(width, height))
+    }
+
+    fn tree() -> QuadTree {
+        let mut tree = QuadTree::builder().bounds(rect(0., 0., 64., 64.)).build();
+        tree.try_insert(rect(4., 4., 16., 8.), 0).unwrap();
+        tree.try_insert(rect(48., 48., 8., 8.), 0).unwrap();
+        tree
+    }
+
+    #[test]
+    fn test_render_image_draws_nodes_and_members() {
+        let style = RenderStyle::default();
+        let image = tree().render_image(128, 128, &style);
+
+        assert_eq!(image.dimensions(), (128, 128));
+        assert_eq!(*image.get_pixel(20, 20), style.member_fill);
+        assert_eq!(*image.get_pixel(8, 8), style.member_border);
+        // The root's children meet in the middle of the image.
+        assert_eq!(*image.get_pixel(64, 100), style.node_border);
+        assert_eq!(*image.get_pixel(100, 20), style.background);
+    }
+
+    #[test]
+    fn test_render_image_keeps_aspect_ratio() {
+        let style = RenderStyle::default();
+        let image = tree().render_image(128, 64, &style);
+
+        // The root is drawn 64 pixels square, leaving the right half blank.
+        assert_eq!(*image.get_pixel(63, 10), style.node_border);
+        assert_eq!(*image.get_pixel(100, 10), style.background);
+    }
+
+    #[test]
+    fn test_render_png_failures() {
+        let style = RenderStyle::default();
+        assert_eq!(tree().render_image(0, 64, &style).dimensions(), (0, 64));
+
+        let path = std::env::temp_dir().join(format!("quadtree-empty-{}.png", std::process::id()));
+        assert!(matches!(tree().render_png(&path, 0, 64, &style), Err(Error::Serialization(_))));
+        assert!(!path.exists());
+
+        let path = std::env::temp_dir().join("quadtree-missing-dir").join("tree.png");
+        assert!(matches!(tree().render_png(&path, 64, 64, &style), Err(Error::Io(_))));
+    }
+
+    #[test]
+    fn test_render_png_writes_a_file() {
+        let path = std::env::temp_dir().join(format!("quadtree-render-{}.png", std::process::id()));
+        tree().render_png(&path, 32, 32, &RenderStyle::default()).unwrap();
+
+        let image = image::open(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!((image.width(), image.height()), (32, 32));
+    }
+}
@@ -0,0 +1,230 @@
+use crate::geometry::Rect;
+use crate::quadtree::{rect_key, Node, QuadTree};
+use crate::split::SplitPolicy;
+
+use std::collections::HashMap;
+use std::fmt;
+
+/**
+ A summary of a quadtree's shape and occupancy, for monitoring the health of a
+ long-lived index. Printable with `{}`, and serializable with serde when the `serde`
+ feature is enabled.
+ */
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TreeReport {
+    /// The total number of nodes, including the root.
+    pub nodes: usize,
+    /// The number of leaf nodes.
+    pub leaves: usize,
+    /// The number of leaf nodes holding no members.
+    pub empty_leaves: usize,
+    /// The fraction of leaves holding no members.
+    pub empty_leaf_fraction: f64,
+    /// The number of distinct members.
+    pub members: usize,
+    /// Entry `i` is the number of leaves at depth `i` below the root.
+    pub depth_histogram: Vec<usize>,
+    /// Entry `i` is the number of leaves holding exactly `i` members.
+    pub members_per_leaf: Vec<usize>,
+    /// The bounds of the leaf holding the most members, if any leaf holds members.
+    pub largest_leaf: Option<Rect>,
+    /// The number of members held by the largest leaf.
+    pub largest_leaf_members: usize,
+    /// The number of members stored in more than one leaf because they span leaf boundaries.
+    pub duplicated_members: usize,
+    /// The number of pairs of members whose interiors overlap.
+    pub overlapping_pairs: usize,
+}
+
+/**
+ Cheap figures on a quadtree's shape, found in a single walk, for tuning how trees are
+ built and spotting pathological subdivision. See `TreeReport` for a fuller picture.
+ */
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TreeStats {
+    /// The depth of the deepest leaf below the root.
+    pub max_depth: usize,
+    /// The total number of nodes, including the root.
+    pub nodes: usize,
+    /// The number of leaf nodes.
+    pub leaves: usize,
+    /// The number of distinct members.
+    pub members: usize,
+    /// The mean number of members held by a leaf, counting a member once per leaf holding it.
+    pub average_occupancy: f64,
+}
+
+impl TreeStats {
+    /**
+     Walk `tree` depth first and count its nodes and leaf entries.
+     */
+    pub fn new<T, P: SplitPolicy>(tree: &QuadTree<T, P>) -> TreeStats {
+        let mut stats = TreeStats { max_depth: 0, nodes: 0, leaves: 0, members: tree.len(), average_occupancy: 0. };
+        let mut entries = 0;
+        let mut nodes_to_check = vec![(tree.root(), 0)];
+
+        while let Some((node, depth)) = nodes_to_check.pop() {
+            stats.nodes += 1;
+            match node.children() {
+                Some(children) => nodes_to_check.extend(children.iter().map(|child| (*child, depth + 1))),
+                None => {
+                    stats.leaves += 1;
+                    stats.max_depth = stats.max_depth.max(depth);
+                    entries += node.leaf_items().len();
+                },
+            }
+        }
+
+        stats.average_occupancy = entries as f64 / stats.leaves as f64;
+        stats
+    }
+}
+
+impl TreeReport {
+    /**
+     Walk `tree` level by level and summarize it.
+     */
+    pub fn new<T, P: SplitPolicy>(tree: &QuadTree<T, P>) -> TreeReport {
+        let mut nodes = 0;
+        let mut depth_histogram = Vec::new();
+        let mut members_per_leaf = Vec::new();
+        let mut largest_leaf = None;
+        let mut largest_leaf_members = 0;
+        // The number of leaves holding each member, and the members in the order found.
+        let mut copies: HashMap<[u64; 4], usize> = HashMap::new();
+        let mut distinct = Vec::new();
+        let mut nodes_to_check = vec![tree.root()];
+
+        while !nodes_to_check.is_empty() {
+            let mut new_nodes_to_check: Vec<&Node> = Vec::new();
+            let mut leaves_at_depth = 0;
+
+            for node in nodes_to_check.iter() {
+                nodes += 1;
+
+                if let Some(children) = node.children() {
+                    new_nodes_to_check.extend(children);
+                    continue
+                }
+
+                let items = node.leaf_items();
+                for item in items.iter() {
+                    let count = copies.entry(rect_key(&item.rect)).or_insert(0);
+                    if *count == 0 {
+                        distinct.push(item.rect);
+                    }
+                    *count += 1;
+                }
+                let count = items.len();
+
+                leaves_at_depth += 1;
+                while members_per_leaf.len() <= count {
+                    members_per_leaf.push(0);
+                }
+                members_per_leaf[count] += 1;
+
+                if count > largest_leaf_members {
+                    largest_leaf_members = count;
+                    largest_leaf = Some(node.bounds());
+                }
+            }
+
+            depth_histogram.push(leaves_at_depth);
+            nodes_to_check = new_nodes_to_check;
+        }
+
+        let duplicated_members = copies.values().filter(|&&count| count > 1).count();
+
+        let mut overlaps = 0;
+        for rect in distinct.iter() {
+            overlaps += tree.query(rect).iter().filter(|other| *other != rect && other.overlaps(rect)).count();
+        }
+
+        let leaves = depth_histogram.iter().sum();
+        let empty_leaves = members_per_leaf.first().copied().unwrap_or(0);
+
+        TreeReport {
+            nodes,
+            leaves,
+            empty_leaves,
+            empty_leaf_fraction: empty_leaves as f64 / leaves as f64,
+            members: distinct.len(),
+            depth_histogram,
+            members_per_leaf,
+            largest_leaf,
+            largest_leaf_members,
+            duplicated_members,
+            overlapping_pairs: overlaps / 2,
+        }
+    }
+}
+
+impl fmt::Display for TreeReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "nodes: {}, leaves: {} ({} empty, {:.1}%), members: {}",
+            self.nodes, self.leaves, self.empty_leaves, self.empty_leaf_fraction * 100., self.members)?;
+        writeln!(f, "leaves by depth: {:?}", self.depth_histogram)?;
+        writeln!(f, "leaves by member count: {:?}", self.members_per_leaf)?;
+        if let Some(rect) = self.largest_leaf {
+            writeln!(f, "largest leaf: {} members in {:?}", self.largest_leaf_members, rect)?;
+        }
+        write!(f, "duplicated members: {}, overlapping pairs: {}", self.duplicated_members, self.overlapping_pairs)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::geometry::Point;
+    use crate::geometry::Rect;
+    use crate::geometry::Size;
+    use crate::quadtree::QuadTree;
+
+    fn rect(x: f64, y: f64, width: f64, height: f64) -> Rect {
+        Rect::new(Point::new(x, y), Size::new(width, height))
+    }
+
+    #[test]
+    fn test_report_counts_spanning_members_once() {
+        let mut tree = QuadTree::builder().bounds(rect(0., 0., 64., 64.)).leaf_capacity(1).allow_overlap(true).build();
+        for corner in [rect(1., 1., 1., 1.), rect(61., 1., 1., 1.), rect(61., 61., 1., 1.), rect(1., 61., 1., 1.)] {
+            tree.try_insert(corner, 0).unwrap();
+        }
+        tree.try_insert(rect(0., 0., 64., 64.), 0).unwrap();
+
+        let report = tree.report();
+        assert_eq!((report.nodes, report.leaves, report.empty_leaves), (5, 4, 0));
+        assert_eq!(report.members, 5);
+        assert_eq!(report.duplicated_members, 1);
+        assert_eq!(report.overlapping_pairs, 4);
+        assert_eq!(report.members_per_leaf, vec![0, 0, 4]);
+    }
+
+    #[test]
+    fn test_report_of_empty_tree() {
+        let report = QuadTree::builder().bounds(rect(0., 0., 64., 64.)).build().report();
+
+        assert_eq!((report.nodes, report.leaves, report.empty_leaves, report.members), (1, 1, 1, 0));
+        assert_eq!(report.largest_leaf, None);
+        assert_eq!(report.duplicated_members, 0);
+    }
+
+    #[test]
+    fn test_stats_agree_with_report() {
+        let mut tree = QuadTree::builder().bounds(rect(0., 0., 64., 64.)).leaf_capacity(1).allow_overlap(true).build();
+        for corner in [rect(1., 1., 1., 1.), rect(61., 1., 1., 1.), rect(61., 61., 1., 1.), rect(1., 61., 1., 1.)] {
+            tree.try_insert(corner, 0).unwrap();
+        }
+        tree.try_insert(rect(0., 0., 64., 64.), 0).unwrap();
+
+        let (stats, report) = (tree.stats(), tree.report());
+        assert_eq!((stats.nodes, stats.leaves, stats.members), (report.nodes, report.leaves, report.members));
+        assert_eq!(stats.max_depth, 1);
+        assert_eq!(stats.average_occupancy, 2.);
+
+        let empty = QuadTree::builder().bounds(rect(0., 0., 64., 64.)).build().stats();
+        assert_eq!((empty.max_depth, empty.nodes, empty.leaves, empty.members), (0, 1, 1, 0));
+        assert_eq!(empty.average_occupancy, 0.);
+    }
+}
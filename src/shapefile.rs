@@ -0,0 +1,208 @@
+/*!
+ Reading the bounding boxes of ESRI shapefile geometries into a quadtree.
+
+ Only the `.shp` file is read, since it holds every shape's geometry and bounds. Each
+ record's bounds are stored in the tree, and its record number is kept alongside so
+ query results can be matched back to rows of the shapefile's `.dbf` table.
+ */
+
+use crate::error::Error;
+use crate::geometry::Point;
+use crate::geometry::Rect;
+use crate::geometry::Size;
+use crate::quadtree::QuadTree;
+
+use std::io::Read;
+
+/// The code every `.shp` file starts with.
+const FILE_CODE: i32 = 9994;
+/// The length of the `.shp` file header, in bytes.
+const HEADER_LEN: usize = 100;
+
+/**
+ A shapefile record's number, counted from 1 as in the file, and the bounds of its shape.
+ */
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ShapeRecord {
+    pub number: u32,
+    pub bounds: Rect,
+}
+
+/**
+ A quadtree of shapefile record bounds that answers queries with record numbers.
+ Overlapping shapes are allowed, and records with identical bounds share a member.
+ */
+#[derive(Debug)]
+pub struct ShapeIndex {
+    tree: QuadTree,
+    /// The record numbers stored under each member, by the member's insertion sequence.
+    numbers: Vec<Vec<u32>>,
+}
+
+impl ShapeIndex {
+    /**
+     Read the records of a `.shp` file and bulk-load their bounds. Null shapes are
+     skipped. Fails with `Serialization` if the file is malformed or holds an unknown
+     shape type, and with `InvalidRect` if a shape's bounds aren't finite.
+     */
+    pub fn read<R: Read>(reader: R) -> Result<ShapeIndex, Error> {
+        let (bounds, records) = read_records(reader)?;
+        let mut tree = QuadTree::builder().bounds(bounds).allow_overlap(true).build();
+        let mut numbers: Vec<Vec<u32>> = Vec::new();
+
+        for record in records.iter() {
+            match tree.try_insert(record.bounds, 0) {
+                Ok(()) => numbers.push(vec![record.number]),
+                Err(Error::Duplicate(_)) => {
+                    let sequence = tree.get(&record.bounds).map_or(0, |item| item.sequence);
+                    numbers[sequence as usize].push(record.number);
+                },
+                Err(err) => return Err(err),
+            }
+        }
+
+        Ok(ShapeIndex { tree, numbers })
+    }
+
+    /**
+     Get the tree holding the records' bounds.
+     */
+    pub fn tree(&self) -> &QuadTree {
+        &self.tree
+    }
+
+    /**
+     Find the numbers of the records whose bounds intersect `region`, in file order
+     except that records sharing bounds are listed together.
+     */
+    pub fn query(&self, region: &Rect) -> Vec<u32> {
+        self.tree.query_in_insertion_order(region).iter()
+            .flat_map(|item| self.numbers[item.sequence as usize].iter().copied())
+            .collect()
+    }
+}
+
+/**
+ Read the bounds given in a `.shp` file's header, and the number and bounds of each
+ record, skipping null shapes. The bounds of a point shape are the point itself.
+ */
+pub fn read_records<R: Read>(mut reader: R) -> Result<(Rect, Vec<ShapeRecord>), Error> {
+    let mut header = [0; HEADER_LEN];
+    reader.read_exact(&mut header)?;
+
+    if be_i32(&header, 0) != FILE_CODE {
+        return Err(Error::Serialization("not a shapefile".to_string()))
+    }
+
+    // Lengths are counted in 16 bit words.
+    let file_len = be_i32(&header, 24).max(0) as usize * 2;
+    let bounds = bounds_from(&header, 36);
+    let mut remaining = file_len.saturating_sub(HEADER_LEN);
+    let mut records = Vec::new();
+
+    while remaining > 0 {
+        let mut record_header = [0; 8];
+        reader.read_exact(&mut record_header)?;
+        let number = be_i32(&record_header, 0) as u32;
+        let content_len = be_i32(&record_header, 4).max(0) as usize * 2;
+
+        let mut content = vec![0; content_len];
+        reader.read_exact(&mut content)?;
+        remaining = remaining.saturating_sub(8 + content_len);
+
+        if content.len() < 4 {
+            return Err(Error::Serialization(format!("record {} is truncated", number)))
+        }
+
+        let shape_type = le_i32(&content, 0);
+        let needed = match shape_type {
+            0 => continue,
+            1 | 11 | 21 => 20,
+            3 | 5 | 8 | 13 | 15 | 18 | 23 | 25 | 28 | 31 => 36,
+            _ => return Err(Error::Serialization(format!("record {} has unknown shape type {}", number, shape_type))),
+        };
+
+        if content.len() < needed {
+            return Err(Error::Serialization(format!("record {} is truncated", number)))
+        }
+
+        let bounds = if needed == 20 {
+            Rect::new(Point::new(le_f64(&content, 4), le_f64(&content, 12)), Size::new(0., 0.))
+        } else {
+            bounds_from(&content, 4)
+        };
+        records.push(ShapeRecord { number, bounds });
+    }
+
+    Ok((bounds, records))
+}
+
+/// Read a box stored as little-endian min x, min y, max x, and max y at `offset`.
+fn bounds_from(bytes: &[u8], offset: usize) -> Rect {
+    let min = Point::new(le_f64(bytes, offset), le_f64(bytes, offset + 8));
+    let max = Point::new(le_f64(bytes, offset + 16), le_f64(bytes, offset + 24));
+
+    Rect::new(min, Size::new(max.x - min.x, max.y - min.y))
+}
+
+fn be_i32(bytes: &[u8], offset: usize) -> i32 {
+    i32::from_be_bytes([bytes[offset], bytes[offset + 1], bytes[offset + 2], bytes[offset + 3]])
+}
+
+fn le_i32(bytes: &[u8], offset: usize) -> i32 {
+    i32::from_le_bytes([bytes[offset], bytes[offset + 1], bytes[offset + 2], bytes[offset + 3]])
+}
+
+fn le_f64(bytes: &[u8], offset: usize) -> f64 {
+    let mut word = [0; 8];
+    word.copy_from_slice(&bytes[offset..offset + 8]);
+    f64::from_le_bytes(word)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::geometry::{Point, Rect, Size};
+    use super::ShapeIndex;
+
+    /// Append a record with the given number and content to a `.shp` file being built.
+    fn push_record(file: &mut Vec<u8>, number: i32, content: &[u8]) {
+        file.extend_from_slice(&number.to_be_bytes());
+        file.extend_from_slice(&(content.len() as i32 / 2).to_be_bytes());
+        file.extend_from_slice(content);
+    }
+
+    fn shape(shape_type: i32, values: &[f64]) -> Vec<u8> {
+        let mut content = shape_type.to_le_bytes().to_vec();
+        for value in values.iter() {
+            content.extend_from_slice(&value.to_le_bytes());
+        }
+        content
+    }
+
+    #[test]
+    fn test_read_shapefile() {
+        let mut file = vec![0; 100];
+        file[..4].copy_from_slice(&9994i32.to_be_bytes());
+        file[28..32].copy_from_slice(&1000i32.to_le_bytes());
+        for (i, value) in [0f64, 0., 100., 100.].iter().enumerate() {
+            file[36 + i * 8..44 + i * 8].copy_from_slice(&value.to_le_bytes());
+        }
+
+        push_record(&mut file, 1, &shape(1, &[10., 10.]));
+        push_record(&mut file, 2, &shape(0, &[]));
+        // A polygon, whose points after its bounds are ignored.
+        push_record(&mut file, 3, &shape(5, &[20., 20., 60., 40., 0., 0.]));
+        push_record(&mut file, 4, &shape(3, &[20., 20., 60., 40.]));
+        push_record(&mut file, 5, &shape(1, &[90., 90.]));
+        let len = (file.len() as i32 / 2).to_be_bytes();
+        file[24..28].copy_from_slice(&len);
+
+        let index = ShapeIndex::read(&file[..]).unwrap();
+        assert_eq!(index.tree().members().len(), 3);
+        assert_eq!(index.query(&Rect::new(Point::new(0., 0.), Size::new(30., 30.))), vec![1, 3, 4]);
+        assert_eq!(index.query(&Rect::new(Point::new(50., 35.), Size::new(50., 60.))), vec![3, 4, 5]);
+
+        file[3] = 0;
+        assert!(ShapeIndex::read(&file[..]).is_err());
+    }
+}
@@ -0,0 +1,86 @@
+/*!
+ Policies deciding when a full leaf is split into four children.
+
+ A tree is generic over its policy, which is set with `QuadTreeBuilder::split_policy`
+ and owned by the tree, so a policy can carry settings of its own. Leaves are always
+ split at their midpoint, and never beyond the config's maximum depth or minimum cell
+ size, whatever the policy says.
+ */
+
+use crate::geometry::Rect;
+use crate::quadtree::{Config, Item};
+
+use std::fmt;
+
+/**
+ Decides whether a leaf should be split after a member is inserted into it.
+ */
+pub trait SplitPolicy: fmt::Debug {
+    /**
+     Check if the leaf with bounds `node_rect`, `depth` levels below the root, should be
+     split now that it holds `items`.
+     */
+    fn should_split(&self, node_rect: &Rect, items: &[Item], depth: usize, config: &Config) -> bool;
+}
+
+/**
+ Split a leaf once it holds more members than the config's leaf capacity. The default.
+ */
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CapacitySplit;
+
+impl SplitPolicy for CapacitySplit {
+    fn should_split(&self, _node_rect: &Rect, items: &[Item], _depth: usize, config: &Config) -> bool {
+        items.len() > config.leaf_capacity
+    }
+}
+
+/**
+ Split a leaf over capacity only while its members leave more than `max_waste`, a
+ fraction from 0 to 1, of its area outside their bounds. Leaves whose members are
+ spread across them aren't split further, which keeps trees of large members shallow.
+ */
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct WasteSplit {
+    pub max_waste: f64,
+}
+
+impl SplitPolicy for WasteSplit {
+    fn should_split(&self, node_rect: &Rect, items: &[Item], depth: usize, config: &Config) -> bool {
+        if !CapacitySplit.should_split(node_rect, items, depth, config) {
+            return false;
+        }
+
+        let area = node_rect.width() * node_rect.height();
+        let bounds = items.iter().skip(1).fold(items[0].rect, |bounds, item| bounds.union(&item.rect));
+        let covered = bounds.intersect(node_rect).map_or(0., |bounds| bounds.width() * bounds.height());
+
+        area > 0. && 1. - covered / area > self.max_waste
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::geometry::{Point, Rect, Size};
+    use crate::quadtree::QuadTree;
+    use super::WasteSplit;
+
+    #[test]
+    fn test_waste_split_keeps_spread_members_together() {
+        let bounds = Rect::new(Point::new(0., 0.), Size::new(100., 100.));
+        let mut default_tree = QuadTree::builder().bounds(bounds).build();
+        let mut waste_tree = QuadTree::builder().bounds(bounds).split_policy(WasteSplit { max_waste: 0.6 }).build();
+
+        for &(x, y) in [(0., 0.), (55., 0.), (55., 55.), (0., 55.)].iter() {
+            let rect = Rect::new(Point::new(x, y), Size::new(45., 45.));
+            default_tree.try_insert(rect, 0).unwrap();
+            waste_tree.try_insert(rect, 0).unwrap();
+        }
+
+        assert!(default_tree.leaves().count() > 1);
+        assert_eq!(waste_tree.leaves().count(), 1);
+        assert!(waste_tree.validate().is_ok());
+    }
+}
@@ -0,0 +1,259 @@
+/*!
+ Reading and writing rects as well-known binary (WKB), the geometry encoding used by
+ PostGIS, GDAL, and most other GIS tools.
+
+ A rect is written as a little-endian polygon with a single closed ring, or as a
+ point or line string if it is degenerate. Any two-dimensional geometry can be read,
+ and is reduced to its bounds.
+ */
+
+use crate::error::Error;
+use crate::geometry::Point;
+use crate::geometry::Rect;
+use crate::geometry::Size;
+use crate::quadtree::QuadTree;
+use crate::split::SplitPolicy;
+
+use std::io::Read;
+use std::io::Write;
+
+const POINT: u32 = 1;
+const LINE_STRING: u32 = 2;
+const POLYGON: u32 = 3;
+const MULTI_POINT: u32 = 4;
+const MULTI_LINE_STRING: u32 = 5;
+const MULTI_POLYGON: u32 = 6;
+const GEOMETRY_COLLECTION: u32 = 7;
+
+/// The byte order marker for little-endian values.
+const LITTLE_ENDIAN: u8 = 1;
+
+/**
+ Encode a rect as WKB.
+ */
+pub fn to_wkb(rect: &Rect) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    push_rect(&mut bytes, rect);
+    bytes
+}
+
+/**
+ Decode a WKB geometry and find its bounds. Fails with `Serialization` if the bytes
+ are truncated, have trailing data, or hold an unsupported or empty geometry.
+ */
+pub fn from_wkb(bytes: &[u8]) -> Result<Rect, Error> {
+    let mut reader = bytes;
+    let rect = read_rect(&mut reader)?;
+    if !reader.is_empty() {
+        return Err(Error::Serialization("trailing data after WKB geometry".to_string()))
+    }
+    Ok(rect)
+}
+
+/**
+ Write a rect as WKB.
+ */
+pub fn write_rect<W: Write>(mut writer: W, rect: &Rect) -> Result<(), Error> {
+    writer.write_all(&to_wkb(rect))?;
+    Ok(())
+}
+
+/**
+ Read one WKB geometry and find its bounds, leaving any following data unread.
+ */
+pub fn read_rect<R: Read>(mut reader: R) -> Result<Rect, Error> {
+    let mut bounds = None;
+    read_geometry(&mut reader, &mut bounds, 0)?;
+    bounds.ok_or_else(|| Error::Serialization("WKB geometry is empty".to_string()))
+}
+
+/**
+ Write rects as a single WKB geometry collection.
+ */
+pub fn write_collection<W: Write>(mut writer: W, rects: &[Rect]) -> Result<(), Error> {
+    let mut bytes = vec![LITTLE_ENDIAN];
+    bytes.extend_from_slice(&GEOMETRY_COLLECTION.to_le_bytes());
+    bytes.extend_from_slice(&(rects.len() as u32).to_le_bytes());
+    for rect in rects.iter() {
+        push_rect(&mut bytes, rect);
+    }
+    writer.write_all(&bytes)?;
+    Ok(())
+}
+
+/**
+ Write rects as hex-encoded WKB, one per line, the text form PostGIS accepts for
+ geometry columns, e.g. in `COPY ... FROM STDIN`.
+ */
+pub fn write_hex_lines<W: Write>(mut writer: W, rects: &[Rect]) -> Result<(), Error> {
+    for rect in rects.iter() {
+        let hex: String = to_wkb(rect).iter().map(|byte| format!("{:02X}", byte)).collect();
+        writeln!(writer, "{}", hex)?;
+    }
+    Ok(())
+}
+
+impl<T, P: SplitPolicy> QuadTree<T, P> {
+    /**
+     Find the members intersecting `region`, encoded as WKB.
+     */
+    pub fn query_wkb(&self, region: &Rect) -> Vec<Vec<u8>> {
+        self.query(region).iter().map(to_wkb).collect()
+    }
+}
+
+fn push_rect(bytes: &mut Vec<u8>, rect: &Rect) {
+    let min = Point::new(rect.min_x(), rect.min_y());
+    let max = Point::new(rect.max_x(), rect.max_y());
+
+    bytes.push(LITTLE_ENDIAN);
+    if rect.is_point() {
+        bytes.extend_from_slice(&POINT.to_le_bytes());
+        push_points(bytes, &[min]);
+    } else if rect.is_degenerate() {
+        bytes.extend_from_slice(&LINE_STRING.to_le_bytes());
+        bytes.extend_from_slice(&2u32.to_le_bytes());
+        push_points(bytes, &[min, max]);
+    } else {
+        // One exterior ring, counter-clockwise and closed.
+        bytes.extend_from_slice(&POLYGON.to_le_bytes());
+        bytes.extend_from_slice(&1u32.to_le_bytes());
+        bytes.extend_from_slice(&5u32.to_le_bytes());
+        push_points(bytes, &[min, Point::new(max.x, min.y), max, Point::new(min.x, max.y), min]);
+    }
+}
+
+fn push_points(bytes: &mut Vec<u8>, points: &[Point]) {
+    for point in points.iter() {
+        bytes.extend_from_slice(&point.x.to_le_bytes());
+        bytes.extend_from_slice(&point.y.to_le_bytes());
+    }
+}
+
+/// How deeply geometry collections may be nested, so malformed data can't overflow the stack.
+const MAX_NESTING: usize = 32;
+
+/**
+ Read a geometry, growing `bounds` to cover each of its points.
+ */
+fn read_geometry<R: Read>(reader: &mut R, bounds: &mut Option<Rect>, nesting: usize) -> Result<(), Error> {
+    if nesting > MAX_NESTING {
+        return Err(Error::Serialization("WKB geometry collections are nested too deeply".to_string()))
+    }
+
+    let little_endian = match read_bytes::<_, 1>(reader)?[0] {
+        0 => false,
+        1 => true,
+        order => return Err(Error::Serialization(format!("unknown WKB byte order {}", order))),
+    };
+    let geometry_type = read_u32(reader, little_endian)?;
+
+    match geometry_type {
+        POINT => {
+            let point = read_point(reader, little_endian)?;
+            // An empty point is written with NaN coordinates.
+            if !(point.x.is_nan() && point.y.is_nan()) {
+                extend_bounds(bounds, point);
+            }
+        },
+        LINE_STRING => read_points(reader, little_endian, bounds)?,
+        POLYGON => {
+            for _ in 0..read_u32(reader, little_endian)? {
+                read_points(reader, little_endian, bounds)?;
+            }
+        },
+        MULTI_POINT | MULTI_LINE_STRING | MULTI_POLYGON | GEOMETRY_COLLECTION => {
+            for _ in 0..read_u32(reader, little_endian)? {
+                read_geometry(reader, bounds, nesting + 1)?;
+            }
+        },
+        _ => return Err(Error::Serialization(format!("unsupported WKB geometry type {}", geometry_type))),
+    }
+
+    Ok(())
+}
+
+fn read_points<R: Read>(reader: &mut R, little_endian: bool, bounds: &mut Option<Rect>) -> Result<(), Error> {
+    for _ in 0..read_u32(reader, little_endian)? {
+        let point = read_point(reader, little_endian)?;
+        extend_bounds(bounds, point);
+    }
+    Ok(())
+}
+
+fn extend_bounds(bounds: &mut Option<Rect>, point: Point) {
+    let rect = Rect::new(point, Size::new(0., 0.));
+    *bounds = Some(bounds.map_or(rect, |bounds| bounds.union(&rect)));
+}
+
+fn read_point<R: Read>(reader: &mut R, little_endian: bool) -> Result<Point, Error> {
+    Ok(Point::new(read_f64(reader, little_endian)?, read_f64(reader, little_endian)?))
+}
+
+fn read_bytes<R: Read, const N: usize>(reader: &mut R) -> Result<[u8; N], Error> {
+    let mut bytes = [0; N];
+    reader.read_exact(&mut bytes).map_err(|_| Error::Serialization("WKB geometry is truncated".to_string()))?;
+    Ok(bytes)
+}
+
+fn read_u32<R: Read>(reader: &mut R, little_endian: bool) -> Result<u32, Error> {
+    let bytes = read_bytes(reader)?;
+    Ok(if little_endian { u32::from_le_bytes(bytes) } else { u32::from_be_bytes(bytes) })
+}
+
+fn read_f64<R: Read>(reader: &mut R, little_endian: bool) -> Result<f64, Error> {
+    let bytes = read_bytes(reader)?;
+    Ok(if little_endian { f64::from_le_bytes(bytes) } else { f64::from_be_bytes(bytes) })
+}
+
+#[cfg(test)]
+mod test {
+    use crate::geometry::{Point, Rect, Size};
+    use crate::quadtree::QuadTree;
+    use super::{from_wkb, read_rect, to_wkb, write_collection, write_hex_lines};
+
+    fn rect(x: f64, y: f64, width: f64, height: f64) -> Rect {
+        Rect::new(Point::new(x, y), Size::new(width, height))
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let rects = [rect(1., 2., 3., 4.), rect(5., 5., 0., 0.), rect(0., 1., 0., 6.)];
+        for rect in rects.iter() {
+            assert_eq!(from_wkb(&to_wkb(rect)).unwrap(), *rect);
+        }
+        assert_eq!(to_wkb(&rects[0]).len(), 1 + 4 + 4 + 4 + 5 * 16);
+        assert_eq!(to_wkb(&rects[1]).len(), 1 + 4 + 16);
+
+        // A big-endian line string, and a collection reduced to its bounds.
+        let mut bytes = vec![0, 0, 0, 0, 2, 0, 0, 0, 2];
+        for value in [1f64, 2., -3., 4.].iter() {
+            bytes.extend_from_slice(&value.to_be_bytes());
+        }
+        assert_eq!(from_wkb(&bytes).unwrap(), rect(-3., 2., 4., 2.));
+
+        let mut collection = Vec::new();
+        write_collection(&mut collection, &rects).unwrap();
+        assert_eq!(read_rect(&collection[..]).unwrap(), rect(0., 1., 5., 6.));
+
+        assert!(from_wkb(&bytes[..bytes.len() - 1]).is_err());
+        bytes[0] = 7;
+        assert!(from_wkb(&bytes).is_err());
+    }
+
+    #[test]
+    fn test_export_query() {
+        let mut tree = QuadTree::builder().bounds(rect(0., 0., 16., 16.)).build();
+        tree.try_insert(rect(1., 1., 2., 2.), 0).unwrap();
+        tree.try_insert(rect(10., 10., 2., 2.), 0).unwrap();
+
+        let found = tree.query_wkb(&rect(0., 0., 4., 4.));
+        assert_eq!(found.len(), 1);
+        assert_eq!(from_wkb(&found[0]).unwrap(), rect(1., 1., 2., 2.));
+
+        let mut text = Vec::new();
+        write_hex_lines(&mut text, &[rect(1., 1., 0., 0.)]).unwrap();
+        assert_eq!(String::from_utf8(text).unwrap(),
+                   "0101000000000000000000F03F000000000000F03F\n");
+    }
+}